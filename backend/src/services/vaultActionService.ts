@@ -546,6 +546,7 @@ export async function getRebalancePlan(
     amount_in: string;
     min_amount_out: string;
     pool_address: string;
+    path?: string[];
   }>;
   total_steps: number;
 }> {
@@ -629,6 +630,7 @@ export async function buildRebalanceStepTransaction(
     amount_in: string;
     min_amount_out: string;
     pool_address: string;
+    path?: string[];
   },
   network?: string
 ): Promise<{ xdr: string; contractAddress: string }> {
@@ -672,6 +674,14 @@ export async function buildRebalanceStepTransaction(
         key: StellarSdk.xdr.ScVal.scvSymbol("min_amount_out"),
         val: StellarSdk.nativeToScVal(BigInt(step.min_amount_out), { type: "i128" })
       }),
+      new StellarSdk.xdr.ScMapEntry({
+        key: StellarSdk.xdr.ScVal.scvSymbol("path"),
+        // Direct swap unless the plan supplied a multi-hop route
+        val: StellarSdk.xdr.ScVal.scvVec(
+          (step.path && step.path.length >= 2 ? step.path : [step.from_token, step.to_token])
+            .map((addr) => StellarSdk.Address.fromString(addr).toScVal())
+        )
+      }),
       new StellarSdk.xdr.ScMapEntry({
         key: StellarSdk.xdr.ScVal.scvSymbol("pool_address"),
         val: StellarSdk.Address.fromString(step.pool_address).toScVal()
@@ -7,7 +7,7 @@
 //! testnet tokens for liquidity pools and vaults.
 
 use soroban_sdk::{
-    contract, contractimpl, Address, Env, String, symbol_short, Map,
+    contract, contracterror, contractimpl, log, panic_with_error, Address, Env, String, symbol_short, Vec,
 };
 
 const BALANCE: soroban_sdk::Symbol = symbol_short!("BALANCE");
@@ -16,6 +16,14 @@ const DECIMALS: soroban_sdk::Symbol = symbol_short!("decimals");
 const NAME: soroban_sdk::Symbol = symbol_short!("name");
 const SYMBOL: soroban_sdk::Symbol = symbol_short!("symbol");
 const ADMIN: soroban_sdk::Symbol = symbol_short!("admin");
+const SUPPLY: soroban_sdk::Symbol = symbol_short!("supply");
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum TokenError {
+    InsufficientAllowance = 1, // Spender asked for more than `allowance` reports as available
+}
 
 #[contract]
 pub struct CustomToken;
@@ -47,6 +55,7 @@ impl CustomToken {
         // Mint initial supply to admin if specified
         if initial_supply > 0 {
             Self::write_balance(&env, admin.clone(), initial_supply);
+            Self::write_supply(&env, initial_supply);
         }
 
         env.events().publish(
@@ -55,6 +64,39 @@ impl CustomToken {
         );
     }
 
+    /// Initialize the token and mint to several recipients in one call
+    /// 
+    /// Handy for seeding liquidity-pool test fixtures
+    /// 
+    /// # Arguments
+    /// * `admin` - The admin address that can mint tokens
+    /// * `decimals` - Number of decimals (typically 7 for Stellar)
+    /// * `name` - Token name (e.g., "Syft Token A")
+    /// * `symbol` - Token symbol (e.g., "TKNA")
+    /// * `recipients` - (address, amount) pairs to mint to
+    pub fn initialize_and_distribute(
+        env: Env,
+        admin: Address,
+        decimals: u32,
+        name: String,
+        symbol: String,
+        recipients: Vec<(Address, i128)>,
+    ) {
+        Self::initialize(env.clone(), admin, decimals, name, symbol, 0);
+
+        for (to, amount) in recipients.iter() {
+            if amount < 0 {
+                panic!("negative amount");
+            }
+
+            let balance = Self::read_balance(&env, to.clone());
+            Self::write_balance(&env, to.clone(), balance + amount);
+            Self::write_supply(&env, Self::read_supply(&env) + amount);
+
+            env.events().publish((symbol_short!("mint"),), (to, amount));
+        }
+    }
+
     /// Mint new tokens (admin only)
     pub fn mint(env: Env, to: Address, amount: i128) {
         let admin: Address = env.storage().instance()
@@ -64,10 +106,16 @@ impl CustomToken {
 
         let balance = Self::read_balance(&env, to.clone());
         Self::write_balance(&env, to.clone(), balance + amount);
+        Self::write_supply(&env, Self::read_supply(&env) + amount);
 
         env.events().publish((symbol_short!("mint"),), (to, amount));
     }
 
+    /// Get total minted supply
+    pub fn total_supply(env: Env) -> i128 {
+        Self::read_supply(&env)
+    }
+
     /// Get token balance
     pub fn balance(env: Env, account: Address) -> i128 {
         Self::read_balance(&env, account)
@@ -102,7 +150,9 @@ impl CustomToken {
 
         let allowance = Self::read_allowance(&env, from.clone(), spender.clone());
         if allowance < amount {
-            panic!("insufficient allowance");
+            // Typed so integrators can tell this apart and retry with `allowance(from, spender)`
+            log!(&env, "Insufficient allowance: requested {}, available {}", amount, allowance);
+            panic_with_error!(&env, TokenError::InsufficientAllowance);
         }
 
         let from_balance = Self::read_balance(&env, from.clone());
@@ -163,6 +213,14 @@ impl CustomToken {
         env.storage().persistent().set(&key, &amount);
     }
 
+    fn read_supply(env: &Env) -> i128 {
+        env.storage().instance().get(&SUPPLY).unwrap_or(0)
+    }
+
+    fn write_supply(env: &Env, amount: i128) {
+        env.storage().instance().set(&SUPPLY, &amount);
+    }
+
     fn read_allowance(env: &Env, from: Address, spender: Address) -> i128 {
         let key = (ALLOWANCE, from, spender);
         env.storage().persistent().get(&key).unwrap_or(0)
@@ -177,7 +235,7 @@ impl CustomToken {
 #[cfg(test)]
 mod test {
     use super::*;
-    use soroban_sdk::{testutils::Address as _, Env};
+    use soroban_sdk::{testutils::{Address as _, Logs}, Env};
 
     #[test]
     fn test_token_initialization() {
@@ -218,4 +276,66 @@ mod test {
         assert_eq!(client.balance(&admin), 900_0000000);
         assert_eq!(client.balance(&user), 100_0000000);
     }
+
+    #[test]
+    fn test_initialize_and_distribute() {
+        let env = Env::default();
+        env.mock_all_auths();
+        
+        let contract_id = env.register_contract(None, CustomToken);
+        let client = CustomTokenClient::new(&env, &contract_id);
+        
+        let admin = Address::generate(&env);
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+        let carol = Address::generate(&env);
+        let name = String::from_str(&env, "Test Token");
+        let symbol = String::from_str(&env, "TEST");
+        
+        let recipients = soroban_sdk::vec![
+            &env,
+            (alice.clone(), 100_0000000),
+            (bob.clone(), 250_0000000),
+            (carol.clone(), 50_0000000),
+        ];
+        client.initialize_and_distribute(&admin, &7, &name, &symbol, &recipients);
+        
+        assert_eq!(client.name(), name);
+        assert_eq!(client.balance(&alice), 100_0000000);
+        assert_eq!(client.balance(&bob), 250_0000000);
+        assert_eq!(client.balance(&carol), 50_0000000);
+        assert_eq!(client.balance(&admin), 0);
+        assert_eq!(client.total_supply(), 400_0000000);
+    }
+
+    #[test]
+    fn test_transfer_from_insufficient_allowance() {
+        let env = Env::default();
+        env.mock_all_auths();
+        
+        let contract_id = env.register_contract(None, CustomToken);
+        let client = CustomTokenClient::new(&env, &contract_id);
+        
+        let admin = Address::generate(&env);
+        let spender = Address::generate(&env);
+        let name = String::from_str(&env, "Test Token");
+        let symbol = String::from_str(&env, "TEST");
+        
+        client.initialize(&admin, &7, &name, &symbol, &1000);
+        client.approve(&admin, &spender, &100);
+        
+        let result = client.try_transfer_from(&spender, &admin, &spender, &150);
+        assert_eq!(
+            result,
+            Err(Ok(soroban_sdk::Error::from_contract_error(TokenError::InsufficientAllowance as u32)))
+        );
+        assert!(env.logs().all().iter().any(|line| {
+            line.contains("Insufficient allowance") && line.contains("150, 100")
+        }));
+        
+        // Nothing moved, and the available amount is what a retry can use
+        assert_eq!(client.allowance(&admin, &spender), 100);
+        client.transfer_from(&spender, &admin, &spender, &100);
+        assert_eq!(client.balance(&spender), 100);
+    }
 }
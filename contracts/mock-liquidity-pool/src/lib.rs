@@ -4,6 +4,13 @@ use soroban_sdk::{contract, contractimpl, contracttype, Address, Env, Symbol, sy
 
 const LP_TOKENS: Symbol = symbol_short!("LP_TOKENS");
 const TOTAL_LP: Symbol = symbol_short!("TOTAL_LP");
+const RESERVE_A: Symbol = symbol_short!("RESERVE_A");
+const RESERVE_B: Symbol = symbol_short!("RESERVE_B");
+const MIN_LP_DUR: Symbol = symbol_short!("MIN_LPDUR");
+const LP_TIME: Symbol = symbol_short!("LP_TIME");
+const TOKEN_A: Symbol = symbol_short!("TOKEN_A");
+const TOKEN_B: Symbol = symbol_short!("TOKEN_B");
+const SKIP_AUTH: Symbol = symbol_short!("SKIP_AUTH");
 
 /// Liquidity position for a user
 #[contracttype]
@@ -14,11 +21,29 @@ pub struct LiquidityPosition {
     pub token_b_provided: i128,
 }
 
+/// Pool-wide snapshot (mirrors RealLiquidityPool::get_pool_info)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MockPoolInfo {
+    pub reserve_a: i128,
+    pub reserve_b: i128,
+    pub total_lp: i128,
+}
+
 #[contract]
 pub struct MockLiquidityPool;
 
 #[contractimpl]
 impl MockLiquidityPool {
+    /// Set the minimum time (seconds) LP tokens must be held before removal
+    /// Optional: a pool that was never initialized has no lock; zero disables it
+    pub fn initialize(env: Env, min_lp_duration: u64) {
+        if env.storage().instance().has(&MIN_LP_DUR) {
+            panic!("Already initialized");
+        }
+        env.storage().instance().set(&MIN_LP_DUR, &min_lp_duration);
+    }
+    
     /// Add liquidity to the pool
     /// This is a simplified version that accepts tokens and returns LP tokens
     /// Returns (lp_tokens, amount_a_used, amount_b_used)
@@ -34,16 +59,31 @@ impl MockLiquidityPool {
         deadline: u64,
     ) -> (i128, i128, i128) {
         // Verify user authorization
-        user.require_auth();
+        Self::require_user_auth(&env, &user);
         
         // Check deadline
         if env.ledger().timestamp() > deadline {
             panic!("Deadline expired");
         }
         
-        // For simplicity, we'll use the desired amounts as-is
-        let amount_a = amount_a_desired;
-        let amount_b = amount_b_desired;
+        // Match the reserve ratio exactly like RealLiquidityPool::add_liquidity
+        let reserve_a: i128 = env.storage().instance().get(&RESERVE_A).unwrap_or(0);
+        let reserve_b: i128 = env.storage().instance().get(&RESERVE_B).unwrap_or(0);
+        let (amount_a, amount_b) = if reserve_a == 0 && reserve_b == 0 {
+            // First liquidity provision - use desired amounts
+            (amount_a_desired, amount_b_desired)
+        } else {
+            let amount_b_optimal = Self::quote(env.clone(), amount_a_desired, reserve_a, reserve_b);
+            if amount_b_optimal <= amount_b_desired {
+                (amount_a_desired, amount_b_optimal)
+            } else {
+                let amount_a_optimal = Self::quote(env.clone(), amount_b_desired, reserve_b, reserve_a);
+                if amount_a_optimal > amount_a_desired {
+                    panic!("Insufficient amounts");
+                }
+                (amount_a_optimal, amount_b_desired)
+            }
+        };
         
         // Verify minimum amounts
         if amount_a < amount_a_min || amount_b < amount_b_min {
@@ -68,11 +108,22 @@ impl MockLiquidityPool {
         };
         
         env.storage().instance().set(&(LP_TOKENS, user.clone()), &position);
+        env.storage().instance().set(&(LP_TIME, user.clone()), &env.ledger().timestamp());
         
         // Update total LP tokens
         let total_lp: i128 = env.storage().instance().get(&TOTAL_LP).unwrap_or(0);
         env.storage().instance().set(&TOTAL_LP, &(total_lp + lp_tokens));
         
+        // Remember which token each reserve holds (first deposit fixes the pair)
+        if !env.storage().instance().has(&TOKEN_A) {
+            env.storage().instance().set(&TOKEN_A, &token_a);
+            env.storage().instance().set(&TOKEN_B, &token_b);
+        }
+        
+        // Update reserves
+        env.storage().instance().set(&RESERVE_A, &(reserve_a + amount_a));
+        env.storage().instance().set(&RESERVE_B, &(reserve_b + amount_b));
+        
         // Emit event
         env.events().publish(
             (symbol_short!("add_liq"), user),
@@ -95,7 +146,7 @@ impl MockLiquidityPool {
         deadline: u64,
     ) -> (i128, i128) {
         // Verify user authorization
-        user.require_auth();
+        Self::require_user_auth(&env, &user);
         
         // Check deadline
         if env.ledger().timestamp() > deadline {
@@ -112,20 +163,34 @@ impl MockLiquidityPool {
                 token_b_provided: 0,
             });
         
-        if position.lp_tokens < lp_tokens {
+        if lp_tokens <= 0 {
+            panic!("LP amount must be positive");
+        }
+        
+        // Checked before dividing by position.lp_tokens so the share math can't underflow
+        if position.lp_tokens <= 0 || position.lp_tokens < lp_tokens {
             panic!("Insufficient LP tokens");
         }
         
+        // Flash-liquidity guard: no removal within min_lp_duration of the last add
+        let min_lp_duration: u64 = env.storage().instance().get(&MIN_LP_DUR).unwrap_or(0);
+        if min_lp_duration > 0 {
+            let added_at: u64 = env.storage().instance().get(&(LP_TIME, user.clone())).unwrap_or(0);
+            if env.ledger().timestamp() < added_at.saturating_add(min_lp_duration) {
+                panic!("Liquidity locked");
+            }
+        }
+        
         // Calculate token amounts to return (proportional to LP tokens)
         let amount_a = position.token_a_provided
             .checked_mul(lp_tokens)
             .and_then(|v| v.checked_div(position.lp_tokens))
-            .unwrap();
+            .expect("Token A amount overflow");
             
         let amount_b = position.token_b_provided
             .checked_mul(lp_tokens)
             .and_then(|v| v.checked_div(position.lp_tokens))
-            .unwrap();
+            .expect("Token B amount overflow");
         
         // Verify minimum amounts
         if amount_a < amount_a_min || amount_b < amount_b_min {
@@ -140,9 +205,12 @@ impl MockLiquidityPool {
         token_b_client.transfer(&env.current_contract_address(), &user, &amount_b);
         
         // Update user's position
-        let new_lp_tokens = position.lp_tokens - lp_tokens;
-        let new_amount_a = position.token_a_provided - amount_a;
-        let new_amount_b = position.token_b_provided - amount_b;
+        let new_lp_tokens = position.lp_tokens.checked_sub(lp_tokens)
+            .expect("LP position underflow");
+        let new_amount_a = position.token_a_provided.checked_sub(amount_a)
+            .expect("Token A position underflow");
+        let new_amount_b = position.token_b_provided.checked_sub(amount_b)
+            .expect("Token B position underflow");
         
         if new_lp_tokens > 0 {
             let new_position = LiquidityPosition {
@@ -157,7 +225,22 @@ impl MockLiquidityPool {
         
         // Update total LP tokens
         let total_lp: i128 = env.storage().instance().get(&TOTAL_LP).unwrap_or(0);
-        env.storage().instance().set(&TOTAL_LP, &(total_lp - lp_tokens));
+        let new_total_lp = total_lp.checked_sub(lp_tokens)
+            .filter(|v| *v >= 0)
+            .expect("Total LP underflow");
+        env.storage().instance().set(&TOTAL_LP, &new_total_lp);
+        
+        // Update reserves
+        let reserve_a: i128 = env.storage().instance().get(&RESERVE_A).unwrap_or(0);
+        let reserve_b: i128 = env.storage().instance().get(&RESERVE_B).unwrap_or(0);
+        let new_reserve_a = reserve_a.checked_sub(amount_a)
+            .filter(|v| *v >= 0)
+            .expect("Reserve A underflow");
+        let new_reserve_b = reserve_b.checked_sub(amount_b)
+            .filter(|v| *v >= 0)
+            .expect("Reserve B underflow");
+        env.storage().instance().set(&RESERVE_A, &new_reserve_a);
+        env.storage().instance().set(&RESERVE_B, &new_reserve_b);
         
         // Emit event
         env.events().publish(
@@ -185,23 +268,110 @@ impl MockLiquidityPool {
         env.storage().instance().get(&TOTAL_LP).unwrap_or(0)
     }
     
-    /// Get quote for adding liquidity (helper for frontend)
+    /// Get a snapshot of reserves and total LP tokens
+    pub fn get_pool_info(env: Env) -> MockPoolInfo {
+        MockPoolInfo {
+            reserve_a: env.storage().instance().get(&RESERVE_A).unwrap_or(0),
+            reserve_b: env.storage().instance().get(&RESERVE_B).unwrap_or(0),
+            total_lp: env.storage().instance().get(&TOTAL_LP).unwrap_or(0),
+        }
+    }
+    
+    /// Value of both reserves in quote_token terms (mirrors RealLiquidityPool for 50/50 pools)
+    /// Balanced constant-product pools hold equal value on each side, so this is 2x the quote reserve
+    pub fn total_liquidity_value(env: Env, quote_token: Address) -> i128 {
+        let token_a: Option<Address> = env.storage().instance().get(&TOKEN_A);
+        let token_b: Option<Address> = env.storage().instance().get(&TOKEN_B);
+        
+        let reserve_quote: i128 = if token_a.as_ref() == Some(&quote_token) {
+            env.storage().instance().get(&RESERVE_A).unwrap_or(0)
+        } else if token_b.as_ref() == Some(&quote_token) {
+            env.storage().instance().get(&RESERVE_B).unwrap_or(0)
+        } else {
+            panic!("Unknown quote token");
+        };
+        
+        reserve_quote.checked_mul(2).expect("Liquidity value overflow")
+    }
+    
+    /// Whether add/remove_liquidity skip user.require_auth() (test-only relaxed mode)
+    pub fn is_auth_relaxed(env: Env) -> bool {
+        env.storage().instance().get(&SKIP_AUTH).unwrap_or(false)
+    }
+    
+    /// Output for swapping amount_in of token_in against the tracked reserves
+    /// Same 0.3% fee constant-product formula as RealLiquidityPool; quote stays the liquidity ratio
+    pub fn quote_swap(env: Env, token_in: Address, amount_in: i128) -> i128 {
+        if amount_in <= 0 {
+            panic!("Amount must be positive");
+        }
+        
+        let token_a: Option<Address> = env.storage().instance().get(&TOKEN_A);
+        let token_b: Option<Address> = env.storage().instance().get(&TOKEN_B);
+        let reserve_a: i128 = env.storage().instance().get(&RESERVE_A).unwrap_or(0);
+        let reserve_b: i128 = env.storage().instance().get(&RESERVE_B).unwrap_or(0);
+        
+        let (reserve_in, reserve_out) = if token_a.as_ref() == Some(&token_in) {
+            (reserve_a, reserve_b)
+        } else if token_b.as_ref() == Some(&token_in) {
+            (reserve_b, reserve_a)
+        } else {
+            panic!("Unknown token");
+        };
+        
+        let amount_in_with_fee = amount_in.checked_mul(997).expect("Swap quote overflow");
+        let numerator = amount_in_with_fee.checked_mul(reserve_out).expect("Swap quote overflow");
+        let denominator = reserve_in.checked_mul(1000)
+            .and_then(|v| v.checked_add(amount_in_with_fee))
+            .expect("Swap quote overflow");
+        
+        numerator / denominator
+    }
+    
+    /// Get quote for adding liquidity: amount_b matching amount_a at the reserve ratio
+    /// Same formula as RealLiquidityPool's quote; returns amount_a when a reserve is empty
     pub fn quote(
         _env: Env,
         amount_a: i128,
-        _reserve_a: i128,
-        _reserve_b: i128,
+        reserve_a: i128,
+        reserve_b: i128,
     ) -> i128 {
-        // Simplified: return proportional amount
-        // In real pool, this would calculate based on reserves
-        amount_a / 10 // Mock ratio
+        if reserve_a == 0 || reserve_b == 0 {
+            return amount_a;
+        }
+        amount_a.checked_mul(reserve_b)
+            .and_then(|v| v.checked_div(reserve_a))
+            .unwrap_or(0)
+    }
+}
+
+/// Test-only helpers; never compiled into a deployed pool
+#[cfg(any(test, feature = "testutils"))]
+#[contractimpl]
+impl MockLiquidityPool {
+    /// Initialize, optionally skipping user.require_auth() in add/remove_liquidity
+    /// Lets a vault drive the mock without mock_all_auths; token transfers still check
+    /// the user's authorization
+    pub fn initialize_with_auth_mode(env: Env, min_lp_duration: u64, skip_user_auth: bool) {
+        Self::initialize(env.clone(), min_lp_duration);
+        if skip_user_auth {
+            env.storage().instance().set(&SKIP_AUTH, &true);
+        }
+    }
+}
+
+impl MockLiquidityPool {
+    fn require_user_auth(env: &Env, user: &Address) {
+        if !Self::is_auth_relaxed(env.clone()) {
+            user.require_auth();
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use soroban_sdk::{testutils::Address as _, Address, Env};
+    use soroban_sdk::{testutils::{Address as _, Ledger, MockAuth, MockAuthInvoke}, Address, Env, IntoVal};
 
     #[test]
     fn test_add_liquidity() {
@@ -229,4 +399,219 @@ mod test {
         assert_eq!(amount_a, 1000);
         assert_eq!(amount_b, 500);
     }
+
+    #[test]
+    fn test_get_pool_info_after_add_liquidity() {
+        let env = Env::default();
+        env.mock_all_auths();
+        
+        let contract_id = env.register_contract(None, MockLiquidityPool);
+        let client = MockLiquidityPoolClient::new(&env, &contract_id);
+        
+        let admin = Address::generate(&env);
+        let token_a = env.register_stellar_asset_contract_v2(admin.clone()).address();
+        let token_b = env.register_stellar_asset_contract_v2(admin.clone()).address();
+        
+        let user = Address::generate(&env);
+        token::StellarAssetClient::new(&env, &token_a).mint(&user, &1000);
+        token::StellarAssetClient::new(&env, &token_b).mint(&user, &500);
+        
+        client.add_liquidity(&user, &token_a, &token_b, &1000, &500, &0, &0, &9999999999);
+        
+        let info = client.get_pool_info();
+        assert_eq!(info, MockPoolInfo { reserve_a: 1000, reserve_b: 500, total_lp: 1500 });
+    }
+
+    #[test]
+    fn test_add_liquidity_matches_reserve_ratio() {
+        let env = Env::default();
+        env.mock_all_auths();
+        
+        let contract_id = env.register_contract(None, MockLiquidityPool);
+        let client = MockLiquidityPoolClient::new(&env, &contract_id);
+        
+        let admin = Address::generate(&env);
+        let token_a = env.register_stellar_asset_contract_v2(admin.clone()).address();
+        let token_b = env.register_stellar_asset_contract_v2(admin.clone()).address();
+        
+        let user = Address::generate(&env);
+        token::StellarAssetClient::new(&env, &token_a).mint(&user, &2000);
+        token::StellarAssetClient::new(&env, &token_b).mint(&user, &2000);
+        
+        // Seed a 1:2 pool
+        client.add_liquidity(&user, &token_a, &token_b, &1000, &2000, &0, &0, &9999999999);
+        
+        // 1000 A would need 2000 B at 1:2, more than desired, so A is cut to 500 instead
+        token::StellarAssetClient::new(&env, &token_b).mint(&user, &1000);
+        let (_, amount_a, amount_b) = client.add_liquidity(&user, &token_a, &token_b, &1000, &1000, &0, &0, &9999999999);
+        assert_eq!((amount_a, amount_b), (500, 1000));
+        assert_eq!(client.get_pool_info().reserve_a, 1500);
+        assert_eq!(client.get_pool_info().reserve_b, 3000);
+    }
+
+    #[test]
+    fn test_quote_swap_matches_real_pool_formula() {
+        let env = Env::default();
+        env.mock_all_auths();
+        
+        let contract_id = env.register_contract(None, MockLiquidityPool);
+        let client = MockLiquidityPoolClient::new(&env, &contract_id);
+        
+        let admin = Address::generate(&env);
+        let token_a = env.register_stellar_asset_contract_v2(admin.clone()).address();
+        let token_b = env.register_stellar_asset_contract_v2(admin.clone()).address();
+        
+        let user = Address::generate(&env);
+        token::StellarAssetClient::new(&env, &token_a).mint(&user, &1_000_000);
+        token::StellarAssetClient::new(&env, &token_b).mint(&user, &2_000_000);
+        client.add_liquidity(&user, &token_a, &token_b, &1_000_000, &2_000_000, &0, &0, &9999999999);
+        
+        // RealLiquidityPool's default-fee output: in * 9970 * r_out / (r_in * 10000 + in * 9970)
+        let real_amount_out = |amount_in: i128, reserve_in: i128, reserve_out: i128| {
+            amount_in * 9970 * reserve_out / (reserve_in * 10_000 + amount_in * 9970)
+        };
+        
+        assert_eq!(client.quote_swap(&token_a, &10_000), real_amount_out(10_000, 1_000_000, 2_000_000));
+        assert_eq!(client.quote_swap(&token_b, &10_000), real_amount_out(10_000, 2_000_000, 1_000_000));
+        
+        // quote still answers the liquidity ratio, with no fee
+        assert_eq!(client.quote(&10_000, &1_000_000, &2_000_000), 20_000);
+    }
+
+    #[test]
+    fn test_total_liquidity_value_in_quote_token() {
+        let env = Env::default();
+        env.mock_all_auths();
+        
+        let contract_id = env.register_contract(None, MockLiquidityPool);
+        let client = MockLiquidityPoolClient::new(&env, &contract_id);
+        
+        let admin = Address::generate(&env);
+        let token_a = env.register_stellar_asset_contract_v2(admin.clone()).address();
+        let token_b = env.register_stellar_asset_contract_v2(admin.clone()).address();
+        
+        let user = Address::generate(&env);
+        token::StellarAssetClient::new(&env, &token_a).mint(&user, &1000);
+        token::StellarAssetClient::new(&env, &token_b).mint(&user, &2000);
+        
+        client.add_liquidity(&user, &token_a, &token_b, &1000, &2000, &0, &0, &9999999999);
+        
+        assert_eq!(client.total_liquidity_value(&token_b), 2000 * 2);
+        assert_eq!(client.total_liquidity_value(&token_a), 1000 * 2);
+    }
+
+    #[test]
+    fn test_remove_full_position() {
+        let env = Env::default();
+        env.mock_all_auths();
+        
+        let contract_id = env.register_contract(None, MockLiquidityPool);
+        let client = MockLiquidityPoolClient::new(&env, &contract_id);
+        
+        let admin = Address::generate(&env);
+        let token_a = env.register_stellar_asset_contract_v2(admin.clone()).address();
+        let token_b = env.register_stellar_asset_contract_v2(admin.clone()).address();
+        
+        let user = Address::generate(&env);
+        token::StellarAssetClient::new(&env, &token_a).mint(&user, &1000);
+        token::StellarAssetClient::new(&env, &token_b).mint(&user, &500);
+        
+        let (lp_tokens, _, _) = client.add_liquidity(&user, &token_a, &token_b, &1000, &500, &0, &0, &9999999999);
+        let (amount_a, amount_b) = client.remove_liquidity(&user, &token_a, &token_b, &lp_tokens, &1000, &500, &9999999999);
+        
+        assert_eq!((amount_a, amount_b), (1000, 500));
+        assert_eq!(client.get_position(&user).lp_tokens, 0);
+        assert_eq!(client.get_pool_info(), MockPoolInfo { reserve_a: 0, reserve_b: 0, total_lp: 0 });
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient LP tokens")]
+    fn test_remove_more_than_held() {
+        let env = Env::default();
+        env.mock_all_auths();
+        
+        let contract_id = env.register_contract(None, MockLiquidityPool);
+        let client = MockLiquidityPoolClient::new(&env, &contract_id);
+        
+        let admin = Address::generate(&env);
+        let token_a = env.register_stellar_asset_contract_v2(admin.clone()).address();
+        let token_b = env.register_stellar_asset_contract_v2(admin.clone()).address();
+        
+        let user = Address::generate(&env);
+        token::StellarAssetClient::new(&env, &token_a).mint(&user, &1000);
+        token::StellarAssetClient::new(&env, &token_b).mint(&user, &500);
+        
+        let (lp_tokens, _, _) = client.add_liquidity(&user, &token_a, &token_b, &1000, &500, &0, &0, &9999999999);
+        client.remove_liquidity(&user, &token_a, &token_b, &(lp_tokens + 1), &0, &0, &9999999999);
+    }
+
+    #[test]
+    fn test_min_lp_duration_locks_removal() {
+        let env = Env::default();
+        env.mock_all_auths();
+        
+        let contract_id = env.register_contract(None, MockLiquidityPool);
+        let client = MockLiquidityPoolClient::new(&env, &contract_id);
+        client.initialize(&3600);
+        
+        let admin = Address::generate(&env);
+        let token_a = env.register_stellar_asset_contract_v2(admin.clone()).address();
+        let token_b = env.register_stellar_asset_contract_v2(admin.clone()).address();
+        
+        let user = Address::generate(&env);
+        token::StellarAssetClient::new(&env, &token_a).mint(&user, &1000);
+        token::StellarAssetClient::new(&env, &token_b).mint(&user, &500);
+        
+        let (lp_tokens, _, _) = client.add_liquidity(&user, &token_a, &token_b, &1000, &500, &0, &0, &9999999999);
+        
+        // Removing right away is rejected
+        let result = client.try_remove_liquidity(&user, &token_a, &token_b, &lp_tokens, &0, &0, &9999999999);
+        assert!(result.is_err());
+        
+        // Once the window has passed the tokens come back
+        env.ledger().with_mut(|li| li.timestamp += 3600);
+        let (amount_a, amount_b) = client.remove_liquidity(&user, &token_a, &token_b, &lp_tokens, &0, &0, &9999999999);
+        assert_eq!((amount_a, amount_b), (1000, 500));
+    }
+
+    #[test]
+    fn test_relaxed_auth_lets_vault_add_liquidity() {
+        let env = Env::default();
+        
+        let contract_id = env.register_contract(None, MockLiquidityPool);
+        let client = MockLiquidityPoolClient::new(&env, &contract_id);
+        client.initialize_with_auth_mode(&0, &true);
+        assert!(client.is_auth_relaxed());
+        
+        let admin = Address::generate(&env);
+        let token_a = env.register_stellar_asset_contract_v2(admin.clone()).address();
+        let token_b = env.register_stellar_asset_contract_v2(admin.clone()).address();
+        
+        let vault = Address::generate(&env);
+        env.mock_all_auths();
+        token::StellarAssetClient::new(&env, &token_a).mint(&vault, &1000);
+        token::StellarAssetClient::new(&env, &token_b).mint(&vault, &500);
+        
+        // Only the vault's token transfers are authorized; the pool call itself is not
+        let transfer_a = MockAuthInvoke {
+            contract: &token_a,
+            fn_name: "transfer",
+            args: (&vault, &contract_id, 1000i128).into_val(&env),
+            sub_invokes: &[],
+        };
+        let transfer_b = MockAuthInvoke {
+            contract: &token_b,
+            fn_name: "transfer",
+            args: (&vault, &contract_id, 500i128).into_val(&env),
+            sub_invokes: &[],
+        };
+        env.mock_auths(&[
+            MockAuth { address: &vault, invoke: &transfer_a },
+            MockAuth { address: &vault, invoke: &transfer_b },
+        ]);
+        
+        let (lp_tokens, _, _) = client.add_liquidity(&vault, &token_a, &token_b, &1000, &500, &0, &0, &9999999999);
+        assert_eq!(lp_tokens, 1500);
+        assert_eq!(client.get_position(&vault).lp_tokens, 1500);
+    }
 }
@@ -8,6 +8,8 @@ pub enum DataKey {
     Token,        // The XLM token being staked
     TotalStaked,  // Total amount currently staked
     UserStake(Address), // Amount staked per user
+    RewardToken,  // Token rewards are paid in (defaults to the staked token)
+    Rewards(Address), // Unclaimed rewards per user
 }
 
 #[contract]
@@ -214,6 +216,63 @@ impl MockStakingPool {
         env.storage().instance().get(&DataKey::Token)
             .expect("not initialized")
     }
+
+    /// Pay rewards in a token other than the staked one
+    pub fn set_reward_token(env: Env, reward_token: Address) {
+        env.storage().instance().set(&DataKey::RewardToken, &reward_token);
+    }
+
+    /// Get the token rewards are paid in (vault-compatible interface)
+    pub fn get_reward_token(env: Env) -> Address {
+        env.storage().instance().get(&DataKey::RewardToken)
+            .unwrap_or_else(|| Self::get_token(env))
+    }
+
+    /// Simulate yield: funder transfers reward tokens in and they are credited to user
+    pub fn accrue_rewards(env: Env, funder: Address, user: Address, amount: i128) {
+        funder.require_auth();
+
+        if amount <= 0 {
+            panic!("amount must be positive");
+        }
+
+        let reward_token = Self::get_reward_token(env.clone());
+        let token_client = token::Client::new(&env, &reward_token);
+        token_client.transfer(&funder, &env.current_contract_address(), &amount);
+
+        let rewards_key = DataKey::Rewards(user);
+        let pending: i128 = env.storage().persistent().get(&rewards_key).unwrap_or(0);
+        env.storage().persistent().set(&rewards_key, &(pending + amount));
+    }
+
+    /// Claim accrued rewards (vault-compatible interface)
+    /// Transfers all unclaimed rewards to sender; returns 0 when nothing has accrued
+    pub fn claim_rewards(env: Env, sender: Address) -> i128 {
+        // No auth required, matching withdraw: rewards only ever go to sender
+        let rewards_key = DataKey::Rewards(sender.clone());
+        let pending: i128 = env.storage().persistent().get(&rewards_key).unwrap_or(0);
+        if pending == 0 {
+            return 0;
+        }
+
+        let reward_token = Self::get_reward_token(env.clone());
+        let token_client = token::Client::new(&env, &reward_token);
+        token_client.transfer(&env.current_contract_address(), &sender, &pending);
+
+        env.storage().persistent().remove(&rewards_key);
+
+        env.events().publish(
+            (soroban_sdk::symbol_short!("claim"),),
+            (sender.clone(), pending)
+        );
+
+        pending
+    }
+
+    /// Get user's unclaimed rewards
+    pub fn get_pending_rewards(env: Env, user: Address) -> i128 {
+        env.storage().persistent().get(&DataKey::Rewards(user)).unwrap_or(0)
+    }
 }
 
 #[cfg(test)]
@@ -293,4 +352,40 @@ mod test {
         pool.stake_tokens(&user, &500);
         pool.unstake_tokens(&user, &600); // Should panic
     }
+
+    #[test]
+    fn test_accrue_and_claim_rewards() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let vault = Address::generate(&env);
+
+        let (token, token_admin) = create_token_contract(&env, &admin);
+        let (reward, reward_admin) = create_token_contract(&env, &admin);
+        let pool_id = env.register(MockStakingPool, ());
+        let pool = MockStakingPoolClient::new(&env, &pool_id);
+
+        pool.initialize(&token.address);
+        token_admin.mint(&vault, &1000);
+        pool.stake_tokens(&vault, &1000);
+
+        // Nothing accrued yet: claiming is a no-op
+        assert_eq!(pool.get_reward_token(), token.address);
+        assert_eq!(pool.claim_rewards(&vault), 0);
+
+        // Rewards paid in a separate token
+        pool.set_reward_token(&reward.address);
+        reward_admin.mint(&admin, &50);
+        pool.accrue_rewards(&admin, &vault, &50);
+        assert_eq!(pool.get_pending_rewards(&vault), 50);
+        assert_eq!(reward.balance(&pool_id), 50);
+
+        // Claim pays out everything and resets the balance; stake is untouched
+        assert_eq!(pool.claim_rewards(&vault), 50);
+        assert_eq!(reward.balance(&vault), 50);
+        assert_eq!(pool.get_pending_rewards(&vault), 0);
+        assert_eq!(pool.claim_rewards(&vault), 0);
+        assert_eq!(pool.get_user_stake(&vault), 1000);
+    }
 }
@@ -10,9 +10,15 @@
 //! - Token swaps with 0.3% fee
 //! - Real token transfers and balance tracking
 //! - Proper price calculation and slippage protection
+//!
+//! Stored reserves (`RESERVE_A`/`RESERVE_B`) are the sole source of truth for
+//! pricing and are only written by the pool's own functions. Tokens sent to the
+//! pool outside those functions (donations) never move the price on their own;
+//! the difference is reconciled explicitly via `sync` (adopt balances as reserves)
+//! or `skim` (send the excess out).
 
 use soroban_sdk::{
-    contract, contractimpl, contracttype, token, Address, Env, Symbol, Vec,
+    contract, contractimpl, contracttype, token, Address, Env, String, Symbol, Vec,
     symbol_short, panic_with_error, contracterror,
 };
 
@@ -23,6 +29,42 @@ const RESERVE_A: Symbol = symbol_short!("RESERVE_A");
 const RESERVE_B: Symbol = symbol_short!("RESERVE_B");
 const TOTAL_SHARES: Symbol = symbol_short!("SHARES");
 const LP_TOKEN: Symbol = symbol_short!("LP_TOKEN");
+const K_LAST: Symbol = symbol_short!("K_LAST");
+const FEE_TO: Symbol = symbol_short!("FEE_TO");
+const ADMIN: Symbol = symbol_short!("ADMIN");
+const LP_NAME: Symbol = symbol_short!("LP_NAME");
+const LP_SYMBOL: Symbol = symbol_short!("LP_SYMBOL");
+const LP_DECIMALS: Symbol = symbol_short!("LP_DEC");
+const FEE_TIERS: Symbol = symbol_short!("FEE_TIERS");
+const REF_FEE: Symbol = symbol_short!("REF_FEE");
+const MIN_LP_DUR: Symbol = symbol_short!("MIN_LPDUR");
+const PAUSED: Symbol = symbol_short!("PAUSED");
+const WEIGHT_A: Symbol = symbol_short!("WEIGHT_A");
+const FEES_A: Symbol = symbol_short!("FEES_A");
+const FEES_B: Symbol = symbol_short!("FEES_B");
+
+/// Largest reserve/balance gap get_pool_info_checked still reports as consistent
+const RESERVE_TOLERANCE: i128 = 1;
+
+/// Swap fee when no tiers are configured (0.3%)
+const DEFAULT_FEE_BPS: i128 = 30;
+
+/// Token weights always sum to this; 50/50 is the plain constant-product curve
+const TOTAL_WEIGHT: u32 = 100;
+
+/// Fixed-point scale for the weighted invariant's fractional powers (1.0 = 1e18)
+const WEIGHT_SCALE: i128 = 1_000_000_000_000_000_000;
+
+/// Longest token symbol used in LP metadata; longer or unreadable symbols fall back to generic names
+const MAX_SYMBOL_LEN: usize = 12;
+
+/// A swap fee that applies from a given input size upward
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeeTier {
+    pub min_amount_in: i128,
+    pub fee_bps: i128,
+}
 
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -46,6 +88,10 @@ pub enum PoolError {
     InvalidTokenPair = 6,
     SlippageExceeded = 7,
     Unauthorized = 8,
+    InvalidFeeTier = 9,
+    LiquidityLocked = 10,
+    Paused = 11,
+    InvalidWeights = 12,
 }
 
 #[contract]
@@ -54,25 +100,181 @@ pub struct RealLiquidityPool;
 #[contractimpl]
 impl RealLiquidityPool {
     /// Initialize the liquidity pool with two tokens
-    pub fn initialize(env: Env, token_a: Address, token_b: Address) {
+    /// Tokens are stored sorted, so "a"/"b" amounts elsewhere refer to token_0/token_1;
+    /// admin controls pausing, the referral fee and the protocol fee recipient
+    pub fn initialize(env: Env, admin: Address, token_a: Address, token_b: Address) {
         // Check if already initialized
         if env.storage().instance().has(&TOKEN_A) {
             panic_with_error!(&env, PoolError::AlreadyInitialized);
         }
 
-        // Store token addresses
+        // A self-pair has no swap direction
+        if token_a == token_b {
+            panic_with_error!(&env, PoolError::InvalidTokenPair);
+        }
+
+        // Store token addresses in canonical order (token_0 is always the lower address)
+        // so pools for the same pair look identical regardless of argument order
+        let (token_a, token_b) = if token_b < token_a {
+            (token_b, token_a)
+        } else {
+            (token_a, token_b)
+        };
         env.storage().instance().set(&TOKEN_A, &token_a);
         env.storage().instance().set(&TOKEN_B, &token_b);
+        env.storage().instance().set(&ADMIN, &admin);
         
         // Initialize reserves to 0
         env.storage().instance().set(&RESERVE_A, &0i128);
         env.storage().instance().set(&RESERVE_B, &0i128);
         env.storage().instance().set(&TOTAL_SHARES, &0i128);
 
+        // LP share metadata so wallets can display positions, named after the pair
+        let (lp_name, lp_symbol) = Self::lp_metadata(&env, &token_a, &token_b);
+        env.storage().instance().set(&LP_NAME, &lp_name);
+        env.storage().instance().set(&LP_SYMBOL, &lp_symbol);
+        env.storage().instance().set(&LP_DECIMALS, &7u32);
+
         // Emit initialization event
         env.events().publish((symbol_short!("init"),), (token_a, token_b));
     }
 
+    /// Initialize the pool with a protocol fee recipient
+    /// fee_to receives 1/6 of fee growth as LP shares on each liquidity event
+    pub fn initialize_with_fee_to(env: Env, admin: Address, token_a: Address, token_b: Address, fee_to: Address) {
+        Self::initialize(env.clone(), admin, token_a, token_b);
+        env.storage().instance().set(&FEE_TO, &fee_to);
+    }
+
+    /// Initialize the pool with size-based swap fee tiers
+    /// Each swap pays the tier with the largest min_amount_in not above its input;
+    /// inputs below every tier pay the default 0.3%
+    pub fn initialize_with_fee_tiers(env: Env, admin: Address, token_a: Address, token_b: Address, tiers: Vec<FeeTier>) {
+        if tiers.is_empty() {
+            panic_with_error!(&env, PoolError::InvalidFeeTier);
+        }
+        for tier in tiers.iter() {
+            if tier.min_amount_in < 0 || !(0..10_000).contains(&tier.fee_bps) {
+                panic_with_error!(&env, PoolError::InvalidFeeTier);
+            }
+        }
+
+        Self::initialize(env.clone(), admin, token_a, token_b);
+        env.storage().instance().set(&FEE_TIERS, &tiers);
+    }
+
+    /// Initialize a weighted pool (Balancer-style x^wa * y^wb = k) holding weight_a/weight_b of value
+    /// Weights must be positive and sum to 100; 50/50 behaves exactly like initialize
+    pub fn initialize_with_weights(env: Env, admin: Address, token_a: Address, token_b: Address, weight_a: u32, weight_b: u32) {
+        if weight_a == 0 || weight_b == 0 || weight_a + weight_b != TOTAL_WEIGHT {
+            panic_with_error!(&env, PoolError::InvalidWeights);
+        }
+
+        // initialize stores tokens sorted, so the weights follow their tokens
+        let weight_0 = if token_b < token_a { weight_b } else { weight_a };
+        Self::initialize(env.clone(), admin, token_a, token_b);
+        if weight_0 != TOTAL_WEIGHT / 2 {
+            env.storage().instance().set(&WEIGHT_A, &weight_0);
+        }
+    }
+
+    /// Token weights as (weight of token_0, weight of token_1)
+    pub fn get_weights(env: Env) -> (u32, u32) {
+        let weight_a: u32 = env.storage().instance().get(&WEIGHT_A).unwrap_or(TOTAL_WEIGHT / 2);
+        (weight_a, TOTAL_WEIGHT - weight_a)
+    }
+
+    /// Initialize the pool with a minimum time (seconds) LP shares must be held
+    /// remove_liquidity reverts until min_lp_duration has passed since the user's last add;
+    /// zero disables the lock
+    pub fn initialize_with_min_lp_duration(env: Env, admin: Address, token_a: Address, token_b: Address, min_lp_duration: u64) {
+        Self::initialize(env.clone(), admin, token_a, token_b);
+        if min_lp_duration > 0 {
+            env.storage().instance().set(&MIN_LP_DUR, &min_lp_duration);
+        }
+    }
+
+    /// Minimum LP hold time in seconds (0 = no lock)
+    pub fn get_min_lp_duration(env: Env) -> u64 {
+        env.storage().instance().get(&MIN_LP_DUR).unwrap_or(0)
+    }
+
+    /// Fee (bps) a swap of amount_in would pay
+    pub fn fee_bps_for(env: Env, amount_in: i128) -> i128 {
+        let tiers: Vec<FeeTier> = match env.storage().instance().get(&FEE_TIERS) {
+            Some(tiers) => tiers,
+            None => return DEFAULT_FEE_BPS,
+        };
+
+        let mut selected: Option<FeeTier> = None;
+        for tier in tiers.iter() {
+            if tier.min_amount_in <= amount_in
+                && selected.as_ref().is_none_or(|s| tier.min_amount_in >= s.min_amount_in)
+            {
+                selected = Some(tier);
+            }
+        }
+
+        selected.map_or(DEFAULT_FEE_BPS, |tier| tier.fee_bps)
+    }
+
+    /// Pool admin, set at initialization
+    pub fn get_admin(env: Env) -> Address {
+        env.storage().instance()
+            .get(&ADMIN)
+            .unwrap_or_else(|| panic_with_error!(&env, PoolError::NotInitialized))
+    }
+
+    /// Change or clear the protocol fee recipient (admin only)
+    /// Clearing it sends all fees to LPs again
+    pub fn set_fee_to(env: Env, fee_to: Option<Address>) {
+        Self::require_admin(&env);
+
+        match fee_to {
+            Some(addr) => env.storage().instance().set(&FEE_TO, &addr),
+            None => env.storage().instance().remove(&FEE_TO),
+        }
+    }
+
+    /// Set the share of swap input paid to referrers, in bps (admin only)
+    /// 0 turns referral payouts off
+    pub fn set_referral_fee_bps(env: Env, fee_bps: i128) {
+        Self::require_admin(&env);
+
+        if !(0..=100).contains(&fee_bps) {
+            panic_with_error!(&env, PoolError::InvalidFeeTier);
+        }
+
+        env.storage().instance().set(&REF_FEE, &fee_bps);
+    }
+
+    /// Halt swaps and new liquidity (admin only); removals stay open so LPs can exit
+    pub fn set_paused(env: Env, paused: bool) {
+        Self::require_admin(&env);
+
+        if paused {
+            env.storage().instance().set(&PAUSED, &true);
+        } else {
+            env.storage().instance().remove(&PAUSED);
+        }
+        env.events().publish((symbol_short!("paused"),), paused);
+    }
+
+    /// Whether swaps and adds are halted
+    pub fn is_paused(env: Env) -> bool {
+        env.storage().instance().has(&PAUSED)
+    }
+
+    /// Share of swap input paid to referrers, in bps (0 if unset)
+    pub fn get_referral_fee_bps(env: Env) -> i128 {
+        env.storage().instance().get(&REF_FEE).unwrap_or(0)
+    }
+
+    /// Get the protocol fee recipient, if protocol fees are on
+    pub fn get_fee_to(env: Env) -> Option<Address> {
+        env.storage().instance().get(&FEE_TO)
+    }
+
     /// Add liquidity to the pool
     /// Returns: (liquidity_minted, amount_a_used, amount_b_used)
     pub fn add_liquidity(
@@ -85,6 +287,10 @@ impl RealLiquidityPool {
     ) -> (i128, i128, i128) {
         user.require_auth();
 
+        if Self::is_paused(env.clone()) {
+            panic_with_error!(&env, PoolError::Paused);
+        }
+
         // Get pool info
         let token_a: Address = env.storage().instance()
             .get(&TOKEN_A)
@@ -95,7 +301,8 @@ impl RealLiquidityPool {
 
         let reserve_a: i128 = env.storage().instance().get(&RESERVE_A).unwrap_or(0);
         let reserve_b: i128 = env.storage().instance().get(&RESERVE_B).unwrap_or(0);
-        let total_shares: i128 = env.storage().instance().get(&TOTAL_SHARES).unwrap_or(0);
+        // Settle the protocol's cut of fees before pricing shares
+        let total_shares = Self::mint_protocol_fee(&env, reserve_a, reserve_b);
 
         // Calculate optimal amounts
         let (amount_a, amount_b) = if reserve_a == 0 && reserve_b == 0 {
@@ -107,15 +314,18 @@ impl RealLiquidityPool {
             
             if amount_b_optimal <= amount_b_desired {
                 if amount_b_optimal < amount_b_min {
-                    panic_with_error!(&env, PoolError::InsufficientAmount);
+                    panic_with_error!(&env, PoolError::SlippageExceeded);
                 }
                 (amount_a_desired, amount_b_optimal)
             } else {
                 // Calculate optimal amount_a based on amount_b
                 let amount_a_optimal = Self::quote(amount_b_desired, reserve_b, reserve_a);
-                if amount_a_optimal > amount_a_desired || amount_a_optimal < amount_a_min {
+                if amount_a_optimal > amount_a_desired {
                     panic_with_error!(&env, PoolError::InsufficientAmount);
                 }
+                if amount_a_optimal < amount_a_min {
+                    panic_with_error!(&env, PoolError::SlippageExceeded);
+                }
                 (amount_a_optimal, amount_b_desired)
             }
         };
@@ -143,6 +353,11 @@ impl RealLiquidityPool {
             panic_with_error!(&env, PoolError::InsufficientLiquidity);
         }
 
+        // Re-check both minimums before moving funds; each branch above only checked one side
+        if amount_a < amount_a_min || amount_b < amount_b_min {
+            panic_with_error!(&env, PoolError::SlippageExceeded);
+        }
+
         // Transfer tokens from user to pool
         let pool_address = env.current_contract_address();
         let token_a_client = token::TokenClient::new(&env, &token_a);
@@ -159,17 +374,21 @@ impl RealLiquidityPool {
         env.storage().instance().set(&RESERVE_A, &new_reserve_a);
         env.storage().instance().set(&RESERVE_B, &new_reserve_b);
         env.storage().instance().set(&TOTAL_SHARES, &new_total_shares);
+        Self::update_k_last(&env, new_reserve_a, new_reserve_b);
 
         // Store user's LP shares
         let user_shares_key = (symbol_short!("LP"), user.clone());
         let current_shares: i128 = env.storage().instance().get(&user_shares_key).unwrap_or(0);
         env.storage().instance().set(&user_shares_key, &(current_shares + liquidity));
+        // Any add restarts the user's LP lock window
+        env.storage().instance().set(&(symbol_short!("LP_TIME"), user.clone()), &env.ledger().timestamp());
 
         // Emit event
         env.events().publish(
             (symbol_short!("add_liq"), user),
             (liquidity, amount_a, amount_b)
         );
+        Self::emit_reserves(&env);
 
         (liquidity, amount_a, amount_b)
     }
@@ -185,74 +404,60 @@ impl RealLiquidityPool {
     ) -> (i128, i128) {
         user.require_auth();
 
-        if liquidity <= 0 {
-            panic_with_error!(&env, PoolError::InsufficientLiquidity);
-        }
-
-        // Get pool info
-        let token_a: Address = env.storage().instance()
-            .get(&TOKEN_A)
-            .unwrap_or_else(|| panic_with_error!(&env, PoolError::NotInitialized));
-        let token_b: Address = env.storage().instance()
-            .get(&TOKEN_B)
-            .unwrap_or_else(|| panic_with_error!(&env, PoolError::NotInitialized));
+        Self::burn_liquidity(&env, &user, &user, liquidity, amount_a_min, amount_b_min)
+    }
 
-        let reserve_a: i128 = env.storage().instance().get(&RESERVE_A).unwrap_or(0);
-        let reserve_b: i128 = env.storage().instance().get(&RESERVE_B).unwrap_or(0);
-        let total_shares: i128 = env.storage().instance().get(&TOTAL_SHARES).unwrap_or(0);
+    /// Move LP shares between holders; the receiver inherits the sender's
+    /// min_lp_duration lock so transfers can't be used to skip it
+    pub fn lp_transfer(env: Env, from: Address, to: Address, amount: i128) {
+        from.require_auth();
 
-        // Check user has enough shares
-        let user_shares_key = (symbol_short!("LP"), user.clone());
-        let user_shares: i128 = env.storage().instance().get(&user_shares_key).unwrap_or(0);
-        
-        if user_shares < liquidity {
+        if amount <= 0 {
             panic_with_error!(&env, PoolError::InsufficientLiquidity);
         }
 
-        // Calculate token amounts to return
-        let amount_a = liquidity.checked_mul(reserve_a)
-            .and_then(|v| v.checked_div(total_shares))
-            .unwrap_or_else(|| panic_with_error!(&env, PoolError::InsufficientAmount));
-        let amount_b = liquidity.checked_mul(reserve_b)
-            .and_then(|v| v.checked_div(total_shares))
-            .unwrap_or_else(|| panic_with_error!(&env, PoolError::InsufficientAmount));
-
-        // Check minimum amounts
-        if amount_a < amount_a_min || amount_b < amount_b_min {
-            panic_with_error!(&env, PoolError::SlippageExceeded);
+        // Both balances are read before either is written, so a self-transfer would mint shares
+        if from == to {
+            return;
         }
 
-        // Transfer tokens back to user
-        let pool_address = env.current_contract_address();
-        let token_a_client = token::TokenClient::new(&env, &token_a);
-        let token_b_client = token::TokenClient::new(&env, &token_b);
-
-        token_a_client.transfer(&pool_address, &user, &amount_a);
-        token_b_client.transfer(&pool_address, &user, &amount_b);
-
-        // Update reserves and shares
-        let new_reserve_a = reserve_a - amount_a;
-        let new_reserve_b = reserve_b - amount_b;
-        let new_total_shares = total_shares - liquidity;
-        let new_user_shares = user_shares - liquidity;
+        let from_key = (symbol_short!("LP"), from.clone());
+        let to_key = (symbol_short!("LP"), to.clone());
+        let from_shares: i128 = env.storage().instance().get(&from_key).unwrap_or(0);
+        if from_shares < amount {
+            panic_with_error!(&env, PoolError::InsufficientLiquidity);
+        }
+        let to_shares: i128 = env.storage().instance().get(&to_key).unwrap_or(0);
 
-        env.storage().instance().set(&RESERVE_A, &new_reserve_a);
-        env.storage().instance().set(&RESERVE_B, &new_reserve_b);
-        env.storage().instance().set(&TOTAL_SHARES, &new_total_shares);
-        
-        if new_user_shares > 0 {
-            env.storage().instance().set(&user_shares_key, &new_user_shares);
+        if from_shares == amount {
+            env.storage().instance().remove(&from_key);
         } else {
-            env.storage().instance().remove(&user_shares_key);
+            env.storage().instance().set(&from_key, &(from_shares - amount));
         }
+        env.storage().instance().set(&to_key, &(to_shares + amount));
 
-        // Emit event
-        env.events().publish(
-            (symbol_short!("rm_liq"), user),
-            (amount_a, amount_b)
-        );
+        let from_time: u64 = env.storage().instance()
+            .get(&(symbol_short!("LP_TIME"), from.clone()))
+            .unwrap_or(0);
+        let to_time_key = (symbol_short!("LP_TIME"), to.clone());
+        let to_time: u64 = env.storage().instance().get(&to_time_key).unwrap_or(0);
+        if from_time > to_time {
+            env.storage().instance().set(&to_time_key, &from_time);
+        }
 
-        (amount_a, amount_b)
+        env.events().publish((symbol_short!("lp_xfer"), from, to), amount);
+    }
+
+    /// Burn whatever LP shares have been transferred to the pool and pay the
+    /// underlying tokens to user (Uniswap V2 "transfer LP then burn")
+    /// Meant to be called in the same transaction as the lp_transfer into the pool
+    pub fn remove_liquidity_from_tokens(env: Env, user: Address) -> (i128, i128) {
+        let pool_address = env.current_contract_address();
+        let liquidity: i128 = env.storage().instance()
+            .get(&(symbol_short!("LP"), pool_address.clone()))
+            .unwrap_or(0);
+
+        Self::burn_liquidity(&env, &pool_address, &user, liquidity, 0, 0)
     }
 
     /// Swap token A for token B (or vice versa)
@@ -263,11 +468,28 @@ impl RealLiquidityPool {
         token_in: Address,
         amount_in: i128,
         amount_out_min: i128,
+    ) -> i128 {
+        Self::swap_referred(env, user, token_in, amount_in, amount_out_min, None)
+    }
+
+    /// Swap crediting a referrer: when a referral fee is set, that slice of the
+    /// pre-transferred input is paid to the referrer and only the rest is swapped
+    pub fn swap_referred(
+        env: Env,
+        user: Address,
+        token_in: Address,
+        amount_in: i128,
+        amount_out_min: i128,
+        referrer: Option<Address>,
     ) -> i128 {
         // NOTE: Removed user.require_auth() to allow cross-contract calls
         // The token transfers below will still check authorization
         // user.require_auth();
 
+        if Self::is_paused(env.clone()) {
+            panic_with_error!(&env, PoolError::Paused);
+        }
+
         if amount_in <= 0 {
             panic_with_error!(&env, PoolError::InsufficientAmount);
         }
@@ -293,20 +515,22 @@ impl RealLiquidityPool {
             panic_with_error!(&env, PoolError::InvalidTokenPair);
         };
 
-        // Calculate output amount with 0.3% fee
-        // amount_out = (amount_in * 997 * reserve_out) / (reserve_in * 1000 + amount_in * 997)
-        let amount_in_with_fee = amount_in.checked_mul(997)
-            .unwrap_or_else(|| panic_with_error!(&env, PoolError::InsufficientAmount));
-        
-        let numerator = amount_in_with_fee.checked_mul(reserve_out)
-            .unwrap_or_else(|| panic_with_error!(&env, PoolError::InsufficientAmount));
-        
-        let denominator = reserve_in.checked_mul(1000)
-            .and_then(|v| v.checked_add(amount_in_with_fee))
-            .unwrap_or_else(|| panic_with_error!(&env, PoolError::InsufficientAmount));
-        
-        let amount_out = numerator.checked_div(denominator)
-            .unwrap_or_else(|| panic_with_error!(&env, PoolError::InsufficientAmount));
+        // Referral cut comes off the input before any swap math
+        let referral_fee_bps = Self::get_referral_fee_bps(env.clone());
+        let referral_amount = match referrer {
+            Some(_) if referral_fee_bps > 0 => amount_in * referral_fee_bps / 10_000,
+            _ => 0,
+        };
+        let amount_swapped = amount_in - referral_amount;
+
+        // Calculate output amount with the fee tier for this swap size
+        let fee_bps = Self::fee_bps_for(env.clone(), amount_swapped);
+        let amount_out = Self::swap_amount_out(&env, amount_swapped, reserve_in, reserve_out, fee_bps, is_a_to_b);
+
+        // Tiny inputs against deep reserves truncate to 0; never take input for nothing
+        if amount_out == 0 {
+            panic_with_error!(&env, PoolError::InsufficientOutputAmount);
+        }
 
         // Check slippage
         if amount_out < amount_out_min {
@@ -323,19 +547,31 @@ impl RealLiquidityPool {
 
         // NOTE: Input tokens should already be in the pool (pre-transferred by caller)
         // We only need to transfer output tokens from pool to user
+        // Enforce that the pre-transfer actually happened: balance must cover reserve + input
+        let token_in_client = token::TokenClient::new(&env, &token_in);
+        if token_in_client.balance(&pool_address) < reserve_in + amount_in {
+            panic_with_error!(&env, PoolError::InsufficientAmount);
+        }
+
+        if let (Some(referrer), true) = (&referrer, referral_amount > 0) {
+            token_in_client.transfer(&pool_address, referrer, &referral_amount);
+            env.events().publish((symbol_short!("referral"), referrer.clone()), (token_in.clone(), referral_amount));
+        }
         
         // Transfer output token from pool to user
         token_out_client.transfer(&pool_address, &user, &amount_out);
 
-        // Update reserves
+        Self::record_fee(&env, is_a_to_b, amount_swapped, fee_bps);
+
+        // Update reserves (only the swapped part of the input joins them)
         if is_a_to_b {
-            let new_reserve_a: i128 = env.storage().instance().get(&RESERVE_A).unwrap_or(0) + amount_in;
+            let new_reserve_a: i128 = env.storage().instance().get(&RESERVE_A).unwrap_or(0) + amount_swapped;
             let new_reserve_b: i128 = env.storage().instance().get(&RESERVE_B).unwrap_or(0) - amount_out;
             env.storage().instance().set(&RESERVE_A, &new_reserve_a);
             env.storage().instance().set(&RESERVE_B, &new_reserve_b);
         } else {
             let new_reserve_a: i128 = env.storage().instance().get(&RESERVE_A).unwrap_or(0) - amount_out;
-            let new_reserve_b: i128 = env.storage().instance().get(&RESERVE_B).unwrap_or(0) + amount_in;
+            let new_reserve_b: i128 = env.storage().instance().get(&RESERVE_B).unwrap_or(0) + amount_swapped;
             env.storage().instance().set(&RESERVE_A, &new_reserve_a);
             env.storage().instance().set(&RESERVE_B, &new_reserve_b);
         }
@@ -345,113 +581,1596 @@ impl RealLiquidityPool {
             (symbol_short!("swap"), user),
             (token_in, amount_in, token_out, amount_out)
         );
+        Self::emit_reserves(&env);
 
         amount_out
     }
 
-    /// Get pool information
-    pub fn get_pool_info(env: Env) -> PoolInfo {
+    /// Swap with partial fill: only the input the pool can actually use is taken
+    /// Input is pulled from the user (not pre-transferred), capped at the smallest
+    /// amount that already yields the maximum obtainable output (reserve_out - 1)
+    /// The fee tier is picked from the requested amount_in, before any capping
+    /// Returns: (amount_in_used, amount_out)
+    pub fn swap_partial(
+        env: Env,
+        user: Address,
+        token_in: Address,
+        amount_in: i128,
+        amount_out_min: i128,
+    ) -> (i128, i128) {
+        user.require_auth();
+
+        if Self::is_paused(env.clone()) {
+            panic_with_error!(&env, PoolError::Paused);
+        }
+
+        if amount_in <= 0 {
+            panic_with_error!(&env, PoolError::InsufficientAmount);
+        }
+
+        // Get pool tokens
         let token_a: Address = env.storage().instance()
             .get(&TOKEN_A)
             .unwrap_or_else(|| panic_with_error!(&env, PoolError::NotInitialized));
         let token_b: Address = env.storage().instance()
             .get(&TOKEN_B)
             .unwrap_or_else(|| panic_with_error!(&env, PoolError::NotInitialized));
+
         let reserve_a: i128 = env.storage().instance().get(&RESERVE_A).unwrap_or(0);
         let reserve_b: i128 = env.storage().instance().get(&RESERVE_B).unwrap_or(0);
-        let total_shares: i128 = env.storage().instance().get(&TOTAL_SHARES).unwrap_or(0);
 
-        PoolInfo {
-            token_a,
-            token_b,
-            reserve_a,
-            reserve_b,
-            total_shares,
+        // Determine swap direction
+        let (token_out, reserve_in, reserve_out, is_a_to_b) = if token_in == token_a {
+            (token_b.clone(), reserve_a, reserve_b, true)
+        } else if token_in == token_b {
+            (token_a.clone(), reserve_b, reserve_a, false)
+        } else {
+            panic_with_error!(&env, PoolError::InvalidTokenPair);
+        };
+
+        if reserve_in <= 0 || reserve_out <= 1 {
+            panic_with_error!(&env, PoolError::InsufficientLiquidity);
         }
-    }
 
-    /// Get user's LP token balance
-    pub fn get_user_liquidity(env: Env, user: Address) -> i128 {
-        let user_shares_key = (symbol_short!("LP"), user);
-        env.storage().instance().get(&user_shares_key).unwrap_or(0)
-    }
+        let fee_bps = Self::fee_bps_for(env.clone(), amount_in);
 
-    /// Get reserves (for compatibility)
-    pub fn get_reserves(env: Env) -> (i128, i128) {
-        let reserve_a: i128 = env.storage().instance().get(&RESERVE_A).unwrap_or(0);
-        let reserve_b: i128 = env.storage().instance().get(&RESERVE_B).unwrap_or(0);
-        (reserve_a, reserve_b)
-    }
+        // Largest input worth taking: the input that already buys reserve_out - 1
+        // (a weighted curve never pays out its whole reserve, so it always takes the full input)
+        let amount_in_used = if Self::is_weighted(&env) {
+            amount_in
+        } else {
+            let max_amount_in = Self::get_amount_in(&env, reserve_out - 1, reserve_in, reserve_out, fee_bps);
+            if amount_in > max_amount_in { max_amount_in } else { amount_in }
+        };
 
-    /// Get token addresses
-    pub fn token_0(env: Env) -> Address {
-        env.storage().instance()
-            .get(&TOKEN_A)
-            .unwrap_or_else(|| panic_with_error!(&env, PoolError::NotInitialized))
-    }
+        let amount_out = Self::swap_amount_out(&env, amount_in_used, reserve_in, reserve_out, fee_bps, is_a_to_b);
 
-    pub fn token_1(env: Env) -> Address {
-        env.storage().instance()
-            .get(&TOKEN_B)
-            .unwrap_or_else(|| panic_with_error!(&env, PoolError::NotInitialized))
-    }
+        // Check slippage
+        if amount_out < amount_out_min {
+            panic_with_error!(&env, PoolError::SlippageExceeded);
+        }
 
-    /// Aliases for compatibility with vault contract
-    pub fn token_a(env: Env) -> Address {
-        Self::token_0(env)
-    }
+        if amount_out <= 0 || amount_out >= reserve_out {
+            panic_with_error!(&env, PoolError::InsufficientLiquidity);
+        }
 
-    pub fn token_b(env: Env) -> Address {
-        Self::token_1(env)
-    }
+        // Pull only the filled input; the unused remainder never leaves the user
+        let pool_address = env.current_contract_address();
+        let token_in_client = token::TokenClient::new(&env, &token_in);
+        let token_out_client = token::TokenClient::new(&env, &token_out);
 
-    // ========== Helper Functions ==========
+        token_in_client.transfer(&user, &pool_address, &amount_in_used);
+        token_out_client.transfer(&pool_address, &user, &amount_out);
+        Self::record_fee(&env, is_a_to_b, amount_in_used, fee_bps);
 
-    /// Calculate quote for adding liquidity
-    fn quote(amount_a: i128, reserve_a: i128, reserve_b: i128) -> i128 {
-        if reserve_a == 0 || reserve_b == 0 {
-            return amount_a;
+        // Update reserves
+        if is_a_to_b {
+            env.storage().instance().set(&RESERVE_A, &(reserve_a + amount_in_used));
+            env.storage().instance().set(&RESERVE_B, &(reserve_b - amount_out));
+        } else {
+            env.storage().instance().set(&RESERVE_A, &(reserve_a - amount_out));
+            env.storage().instance().set(&RESERVE_B, &(reserve_b + amount_in_used));
         }
-        amount_a.checked_mul(reserve_b)
-            .and_then(|v| v.checked_div(reserve_a))
-            .unwrap_or(0)
-    }
 
-    /// Integer square root (Babylonian method)
-    fn sqrt(x: i128) -> i128 {
-        if x == 0 {
+        // Emit event
+        env.events().publish(
+            (symbol_short!("swap_part"), user),
+            (token_in, amount_in_used, token_out, amount_out)
+        );
+        Self::emit_reserves(&env);
+
+        (amount_in_used, amount_out)
+    }
+
+    /// Reconcile stored reserves to the pool's actual token balances
+    /// This is the only way external transfers (donations) affect pricing
+    pub fn sync(env: Env) {
+        let token_a: Address = env.storage().instance()
+            .get(&TOKEN_A)
+            .unwrap_or_else(|| panic_with_error!(&env, PoolError::NotInitialized));
+        let token_b: Address = env.storage().instance()
+            .get(&TOKEN_B)
+            .unwrap_or_else(|| panic_with_error!(&env, PoolError::NotInitialized));
+
+        let pool_address = env.current_contract_address();
+        let balance_a = token::TokenClient::new(&env, &token_a).balance(&pool_address);
+        let balance_b = token::TokenClient::new(&env, &token_b).balance(&pool_address);
+
+        env.storage().instance().set(&RESERVE_A, &balance_a);
+        env.storage().instance().set(&RESERVE_B, &balance_b);
+
+        env.events().publish((symbol_short!("sync"),), (balance_a, balance_b));
+        Self::emit_reserves(&env);
+    }
+
+    /// Send any balance in excess of stored reserves to `to`, leaving reserves untouched
+    /// Returns: (excess_a, excess_b)
+    pub fn skim(env: Env, to: Address) -> (i128, i128) {
+        let token_a: Address = env.storage().instance()
+            .get(&TOKEN_A)
+            .unwrap_or_else(|| panic_with_error!(&env, PoolError::NotInitialized));
+        let token_b: Address = env.storage().instance()
+            .get(&TOKEN_B)
+            .unwrap_or_else(|| panic_with_error!(&env, PoolError::NotInitialized));
+
+        let reserve_a: i128 = env.storage().instance().get(&RESERVE_A).unwrap_or(0);
+        let reserve_b: i128 = env.storage().instance().get(&RESERVE_B).unwrap_or(0);
+
+        let pool_address = env.current_contract_address();
+        let token_a_client = token::TokenClient::new(&env, &token_a);
+        let token_b_client = token::TokenClient::new(&env, &token_b);
+
+        let excess_a = (token_a_client.balance(&pool_address) - reserve_a).max(0);
+        let excess_b = (token_b_client.balance(&pool_address) - reserve_b).max(0);
+
+        if excess_a > 0 {
+            token_a_client.transfer(&pool_address, &to, &excess_a);
+        }
+        if excess_b > 0 {
+            token_b_client.transfer(&pool_address, &to, &excess_b);
+        }
+
+        env.events().publish((symbol_short!("skim"), to), (excess_a, excess_b));
+
+        (excess_a, excess_b)
+    }
+
+    /// Realized price (token_in per token_out, scaled by 1e7) for swapping amount_in
+    /// Includes the fee tier for this size and the slippage, unlike the spot reserve ratio
+    pub fn effective_price(env: Env, token_in: Address, amount_in: i128) -> i128 {
+        if amount_in <= 0 {
+            panic_with_error!(&env, PoolError::InsufficientAmount);
+        }
+
+        let token_a: Address = env.storage().instance()
+            .get(&TOKEN_A)
+            .unwrap_or_else(|| panic_with_error!(&env, PoolError::NotInitialized));
+        let token_b: Address = env.storage().instance()
+            .get(&TOKEN_B)
+            .unwrap_or_else(|| panic_with_error!(&env, PoolError::NotInitialized));
+
+        let reserve_a: i128 = env.storage().instance().get(&RESERVE_A).unwrap_or(0);
+        let reserve_b: i128 = env.storage().instance().get(&RESERVE_B).unwrap_or(0);
+
+        let (reserve_in, reserve_out) = if token_in == token_a {
+            (reserve_a, reserve_b)
+        } else if token_in == token_b {
+            (reserve_b, reserve_a)
+        } else {
+            panic_with_error!(&env, PoolError::InvalidTokenPair);
+        };
+
+        let fee_bps = Self::fee_bps_for(env.clone(), amount_in);
+        let amount_out = Self::swap_amount_out(&env, amount_in, reserve_in, reserve_out, fee_bps, token_in == token_a);
+        if amount_out <= 0 {
+            panic_with_error!(&env, PoolError::InsufficientOutputAmount);
+        }
+
+        amount_in.checked_mul(10_000_000)
+            .and_then(|v| v.checked_div(amount_out))
+            .unwrap_or_else(|| panic_with_error!(&env, PoolError::InsufficientAmount))
+    }
+
+    /// Whether initialize has been called on this pool
+    pub fn is_initialized(env: Env) -> bool {
+        env.storage().instance().has(&TOKEN_A)
+    }
+
+    /// Get pool information without trapping: None if the pool isn't initialized
+    /// (named probe_ because the generated client already owns try_get_pool_info)
+    pub fn probe_pool_info(env: Env) -> Option<PoolInfo> {
+        if !Self::is_initialized(env.clone()) {
+            return None;
+        }
+        Some(Self::get_pool_info(env))
+    }
+
+    /// Get pool information
+    pub fn get_pool_info(env: Env) -> PoolInfo {
+        let token_a: Address = env.storage().instance()
+            .get(&TOKEN_A)
+            .unwrap_or_else(|| panic_with_error!(&env, PoolError::NotInitialized));
+        let token_b: Address = env.storage().instance()
+            .get(&TOKEN_B)
+            .unwrap_or_else(|| panic_with_error!(&env, PoolError::NotInitialized));
+        let reserve_a: i128 = env.storage().instance().get(&RESERVE_A).unwrap_or(0);
+        let reserve_b: i128 = env.storage().instance().get(&RESERVE_B).unwrap_or(0);
+        let total_shares: i128 = env.storage().instance().get(&TOTAL_SHARES).unwrap_or(0);
+
+        PoolInfo {
+            token_a,
+            token_b,
+            reserve_a,
+            reserve_b,
+            total_shares,
+        }
+    }
+
+    /// Get pool information plus whether stored reserves match live token balances
+    /// (within RESERVE_TOLERANCE); false means tokens moved outside the pool's functions
+    pub fn get_pool_info_checked(env: Env) -> (PoolInfo, bool) {
+        let info = Self::get_pool_info(env.clone());
+
+        let pool_address = env.current_contract_address();
+        let balance_a = token::TokenClient::new(&env, &info.token_a).balance(&pool_address);
+        let balance_b = token::TokenClient::new(&env, &info.token_b).balance(&pool_address);
+
+        let consistent = (balance_a - info.reserve_a).abs() <= RESERVE_TOLERANCE
+            && (balance_b - info.reserve_b).abs() <= RESERVE_TOLERANCE;
+
+        (info, consistent)
+    }
+
+    /// Estimate reserve growth from swap fees since the last liquidity event
+    /// Compares sqrt(k) now against sqrt(k_last); the growth share of each reserve is fee-derived
+    /// Returns: (fees_a, fees_b)
+    pub fn accrued_fees(env: Env) -> (i128, i128) {
+        let reserve_a: i128 = env.storage().instance().get(&RESERVE_A).unwrap_or(0);
+        let reserve_b: i128 = env.storage().instance().get(&RESERVE_B).unwrap_or(0);
+        let k_last: i128 = env.storage().instance().get(&K_LAST).unwrap_or(0);
+
+        if k_last == 0 {
+            return (0, 0);
+        }
+
+        let root_k = Self::sqrt(reserve_a.checked_mul(reserve_b).unwrap_or(i128::MAX));
+        let root_k_last = Self::sqrt(k_last);
+
+        if root_k <= root_k_last {
+            return (0, 0);
+        }
+
+        let growth = root_k - root_k_last;
+        let fees_a = reserve_a.checked_mul(growth)
+            .and_then(|v| v.checked_div(root_k))
+            .unwrap_or(0);
+        let fees_b = reserve_b.checked_mul(growth)
+            .and_then(|v| v.checked_div(root_k))
+            .unwrap_or(0);
+
+        (fees_a, fees_b)
+    }
+
+    /// Swap fees earned over the pool's lifetime, per input token: (fees in token_0, fees in token_1)
+    pub fn cumulative_fees(env: Env) -> (i128, i128) {
+        let fees_a: i128 = env.storage().instance().get(&FEES_A).unwrap_or(0);
+        let fees_b: i128 = env.storage().instance().get(&FEES_B).unwrap_or(0);
+        (fees_a, fees_b)
+    }
+
+    /// Value of both reserves expressed in quote_token (one of the pool's tokens) at the spot price
+    /// The quote side holds its weight's share of pool value: 2x its reserve for 50/50 pools
+    pub fn total_liquidity_value(env: Env, quote_token: Address) -> i128 {
+        let (token_0, token_1) = Self::tokens(env.clone());
+        let (weight_0, weight_1) = Self::get_weights(env.clone());
+
+        let (reserve_quote, weight_quote) = if quote_token == token_0 {
+            (env.storage().instance().get::<_, i128>(&RESERVE_A).unwrap_or(0), weight_0)
+        } else if quote_token == token_1 {
+            (env.storage().instance().get::<_, i128>(&RESERVE_B).unwrap_or(0), weight_1)
+        } else {
+            panic_with_error!(&env, PoolError::InvalidTokenPair);
+        };
+
+        reserve_quote
+            .checked_mul(TOTAL_WEIGHT as i128)
+            .map(|v| v / weight_quote as i128)
+            .unwrap_or_else(|| panic_with_error!(&env, PoolError::InsufficientAmount))
+    }
+
+    /// Get user's LP token balance
+    pub fn get_user_liquidity(env: Env, user: Address) -> i128 {
+        let user_shares_key = (symbol_short!("LP"), user);
+        env.storage().instance().get(&user_shares_key).unwrap_or(0)
+    }
+
+    /// Get reserves (for compatibility)
+    pub fn get_reserves(env: Env) -> (i128, i128) {
+        let reserve_a: i128 = env.storage().instance().get(&RESERVE_A).unwrap_or(0);
+        let reserve_b: i128 = env.storage().instance().get(&RESERVE_B).unwrap_or(0);
+        (reserve_a, reserve_b)
+    }
+
+    /// Get token addresses
+    pub fn token_0(env: Env) -> Address {
+        env.storage().instance()
+            .get(&TOKEN_A)
+            .unwrap_or_else(|| panic_with_error!(&env, PoolError::NotInitialized))
+    }
+
+    pub fn token_1(env: Env) -> Address {
+        env.storage().instance()
+            .get(&TOKEN_B)
+            .unwrap_or_else(|| panic_with_error!(&env, PoolError::NotInitialized))
+    }
+
+    /// Both token addresses in one call, as (token_0, token_1)
+    pub fn tokens(env: Env) -> (Address, Address) {
+        (Self::token_0(env.clone()), Self::token_1(env))
+    }
+
+    /// LP token name
+    pub fn lp_name(env: Env) -> String {
+        env.storage().instance()
+            .get(&LP_NAME)
+            .unwrap_or_else(|| panic_with_error!(&env, PoolError::NotInitialized))
+    }
+
+    /// LP token symbol
+    pub fn lp_symbol(env: Env) -> String {
+        env.storage().instance()
+            .get(&LP_SYMBOL)
+            .unwrap_or_else(|| panic_with_error!(&env, PoolError::NotInitialized))
+    }
+
+    /// LP token decimals
+    pub fn lp_decimals(env: Env) -> u32 {
+        env.storage().instance()
+            .get(&LP_DECIMALS)
+            .unwrap_or_else(|| panic_with_error!(&env, PoolError::NotInitialized))
+    }
+
+    /// Aliases for compatibility with vault contract
+    pub fn token_a(env: Env) -> Address {
+        Self::token_0(env)
+    }
+
+    pub fn token_b(env: Env) -> Address {
+        Self::token_1(env)
+    }
+
+    // ========== Helper Functions ==========
+
+    /// Add a swap's fee (amount_in * fee_bps / 10000, in the input token) to the lifetime totals
+    fn record_fee(env: &Env, is_a_to_b: bool, amount_in: i128, fee_bps: i128) {
+        let fee = amount_in * fee_bps / 10_000;
+        if fee <= 0 {
+            return;
+        }
+        let key = if is_a_to_b { FEES_A } else { FEES_B };
+        let total: i128 = env.storage().instance().get(&key).unwrap_or(0);
+        env.storage().instance().set(&key, &total.saturating_add(fee));
+    }
+
+    /// Burn liquidity of owner's shares and pay the proportional reserves to `to`
+    fn burn_liquidity(
+        env: &Env,
+        owner: &Address,
+        to: &Address,
+        liquidity: i128,
+        amount_a_min: i128,
+        amount_b_min: i128,
+    ) -> (i128, i128) {
+        if liquidity <= 0 {
+            panic_with_error!(env, PoolError::InsufficientLiquidity);
+        }
+
+        // Get pool info
+        let token_a: Address = env.storage().instance()
+            .get(&TOKEN_A)
+            .unwrap_or_else(|| panic_with_error!(env, PoolError::NotInitialized));
+        let token_b: Address = env.storage().instance()
+            .get(&TOKEN_B)
+            .unwrap_or_else(|| panic_with_error!(env, PoolError::NotInitialized));
+
+        let reserve_a: i128 = env.storage().instance().get(&RESERVE_A).unwrap_or(0);
+        let reserve_b: i128 = env.storage().instance().get(&RESERVE_B).unwrap_or(0);
+        // Settle the protocol's cut of fees before pricing shares
+        let total_shares = Self::mint_protocol_fee(env, reserve_a, reserve_b);
+
+        // Check user has enough shares
+        let user_shares_key = (symbol_short!("LP"), owner.clone());
+        let user_shares: i128 = env.storage().instance().get(&user_shares_key).unwrap_or(0);
+        
+        if user_shares < liquidity {
+            panic_with_error!(env, PoolError::InsufficientLiquidity);
+        }
+
+        // Deter flash liquidity: shares can't leave within min_lp_duration of the last add
+        let min_lp_duration: u64 = env.storage().instance().get(&MIN_LP_DUR).unwrap_or(0);
+        if min_lp_duration > 0 {
+            let added_at: u64 = env.storage().instance()
+                .get(&(symbol_short!("LP_TIME"), owner.clone()))
+                .unwrap_or(0);
+            if env.ledger().timestamp() < added_at.saturating_add(min_lp_duration) {
+                panic_with_error!(env, PoolError::LiquidityLocked);
+            }
+        }
+
+        // Calculate token amounts to return
+        let amount_a = liquidity.checked_mul(reserve_a)
+            .and_then(|v| v.checked_div(total_shares))
+            .unwrap_or_else(|| panic_with_error!(env, PoolError::InsufficientAmount));
+        let amount_b = liquidity.checked_mul(reserve_b)
+            .and_then(|v| v.checked_div(total_shares))
+            .unwrap_or_else(|| panic_with_error!(env, PoolError::InsufficientAmount));
+
+        // Check minimum amounts
+        if amount_a < amount_a_min || amount_b < amount_b_min {
+            panic_with_error!(env, PoolError::SlippageExceeded);
+        }
+
+        // Transfer tokens back to user
+        let pool_address = env.current_contract_address();
+        let token_a_client = token::TokenClient::new(env, &token_a);
+        let token_b_client = token::TokenClient::new(env, &token_b);
+
+        token_a_client.transfer(&pool_address, to, &amount_a);
+        token_b_client.transfer(&pool_address, to, &amount_b);
+
+        // Update reserves and shares
+        let new_reserve_a = reserve_a - amount_a;
+        let new_reserve_b = reserve_b - amount_b;
+        let new_total_shares = total_shares - liquidity;
+        let new_user_shares = user_shares - liquidity;
+
+        env.storage().instance().set(&RESERVE_A, &new_reserve_a);
+        env.storage().instance().set(&RESERVE_B, &new_reserve_b);
+        env.storage().instance().set(&TOTAL_SHARES, &new_total_shares);
+        Self::update_k_last(env, new_reserve_a, new_reserve_b);
+        
+        if new_user_shares > 0 {
+            env.storage().instance().set(&user_shares_key, &new_user_shares);
+        } else {
+            env.storage().instance().remove(&user_shares_key);
+        }
+
+        // Emit event
+        env.events().publish(
+            (symbol_short!("rm_liq"), to.clone()),
+            (amount_a, amount_b)
+        );
+        Self::emit_reserves(env);
+
+        (amount_a, amount_b)
+    }
+
+    /// Panic unless the stored admin authorized this call
+    fn require_admin(env: &Env) {
+        let admin: Address = env.storage().instance()
+            .get(&ADMIN)
+            .unwrap_or_else(|| panic_with_error!(env, PoolError::Unauthorized));
+        admin.require_auth();
+    }
+
+    /// LP (name, symbol) for a pair, e.g. ("Syft LP XLM-USDC", "XLM-USDC-LP")
+    /// Falls back to ("Syft LP", "SYFT-LP") when either token has no usable symbol
+    fn lp_metadata(env: &Env, token_a: &Address, token_b: &Address) -> (String, String) {
+        let mut symbol_a = [0u8; MAX_SYMBOL_LEN];
+        let mut symbol_b = [0u8; MAX_SYMBOL_LEN];
+        let (len_a, len_b) = match (
+            Self::read_token_symbol(env, token_a, &mut symbol_a),
+            Self::read_token_symbol(env, token_b, &mut symbol_b),
+        ) {
+            (Some(len_a), Some(len_b)) => (len_a, len_b),
+            _ => return (String::from_str(env, "Syft LP"), String::from_str(env, "SYFT-LP")),
+        };
+
+        // "Syft LP " + pair and pair + "-LP", where pair is "A-B"
+        let mut pair = [0u8; 2 * MAX_SYMBOL_LEN + 1];
+        let pair_len = len_a + 1 + len_b;
+        pair[..len_a].copy_from_slice(&symbol_a[..len_a]);
+        pair[len_a] = b'-';
+        pair[len_a + 1..pair_len].copy_from_slice(&symbol_b[..len_b]);
+
+        let mut name = [0u8; 8 + 2 * MAX_SYMBOL_LEN + 1];
+        name[..8].copy_from_slice(b"Syft LP ");
+        name[8..8 + pair_len].copy_from_slice(&pair[..pair_len]);
+
+        let mut symbol = [0u8; 2 * MAX_SYMBOL_LEN + 4];
+        symbol[..pair_len].copy_from_slice(&pair[..pair_len]);
+        symbol[pair_len..pair_len + 3].copy_from_slice(b"-LP");
+
+        (
+            String::from_bytes(env, &name[..8 + pair_len]),
+            String::from_bytes(env, &symbol[..pair_len + 3]),
+        )
+    }
+
+    /// Copy a token's symbol into out, returning its length
+    /// None if the token can't be queried or its symbol is empty or longer than MAX_SYMBOL_LEN
+    fn read_token_symbol(env: &Env, token: &Address, out: &mut [u8; MAX_SYMBOL_LEN]) -> Option<usize> {
+        let symbol = match token::Client::new(env, token).try_symbol() {
+            Ok(Ok(symbol)) => symbol,
+            _ => return None,
+        };
+        let len = symbol.len() as usize;
+        if len == 0 || len > MAX_SYMBOL_LEN {
+            return None;
+        }
+        symbol.copy_into_slice(&mut out[..len]);
+        Some(len)
+    }
+
+    /// Publish the post-operation reserves so indexers can track the curve
+    fn emit_reserves(env: &Env) {
+        let reserve_a: i128 = env.storage().instance().get(&RESERVE_A).unwrap_or(0);
+        let reserve_b: i128 = env.storage().instance().get(&RESERVE_B).unwrap_or(0);
+        env.events().publish((symbol_short!("reserves"),), (reserve_a, reserve_b));
+    }
+
+    /// Mint 1/6 of fee growth since k_last to fee_to as LP shares (Uniswap V2 style)
+    /// No-op when fee_to is unset; returns the (possibly increased) total shares
+    fn mint_protocol_fee(env: &Env, reserve_a: i128, reserve_b: i128) -> i128 {
+        let total_shares: i128 = env.storage().instance().get(&TOTAL_SHARES).unwrap_or(0);
+
+        let fee_to: Address = match env.storage().instance().get(&FEE_TO) {
+            Some(addr) => addr,
+            None => return total_shares,
+        };
+
+        let k_last: i128 = env.storage().instance().get(&K_LAST).unwrap_or(0);
+        if k_last == 0 || total_shares == 0 {
+            return total_shares;
+        }
+
+        let root_k = Self::sqrt(reserve_a.checked_mul(reserve_b).unwrap_or(i128::MAX));
+        let root_k_last = Self::sqrt(k_last);
+        if root_k <= root_k_last {
+            return total_shares;
+        }
+
+        // liquidity = total_shares * (root_k - root_k_last) / (5 * root_k + root_k_last)
+        let numerator = total_shares.checked_mul(root_k - root_k_last);
+        let denominator = root_k.checked_mul(5).and_then(|v| v.checked_add(root_k_last));
+        let liquidity = match (numerator, denominator) {
+            (Some(n), Some(d)) if d > 0 => n / d,
+            _ => 0,
+        };
+        if liquidity <= 0 {
+            return total_shares;
+        }
+
+        let fee_to_key = (symbol_short!("LP"), fee_to.clone());
+        let fee_to_shares: i128 = env.storage().instance().get(&fee_to_key).unwrap_or(0);
+        env.storage().instance().set(&fee_to_key, &(fee_to_shares + liquidity));
+
+        let new_total_shares = total_shares + liquidity;
+        env.storage().instance().set(&TOTAL_SHARES, &new_total_shares);
+
+        env.events().publish((symbol_short!("fee_mint"), fee_to), liquidity);
+
+        new_total_shares
+    }
+
+    /// Record the reserve product at a liquidity event (baseline for accrued_fees)
+    fn update_k_last(env: &Env, reserve_a: i128, reserve_b: i128) {
+        let k_last = reserve_a.checked_mul(reserve_b).unwrap_or(i128::MAX);
+        env.storage().instance().set(&K_LAST, &k_last);
+    }
+
+    /// Calculate quote for adding liquidity
+    fn quote(amount_a: i128, reserve_a: i128, reserve_b: i128) -> i128 {
+        if reserve_a == 0 || reserve_b == 0 {
+            return amount_a;
+        }
+        amount_a.checked_mul(reserve_b)
+            .and_then(|v| v.checked_div(reserve_a))
+            .unwrap_or(0)
+    }
+
+    /// Output for an exact input with an arbitrary fee in bps
+    /// amount_out = (amount_in * (10000 - fee) * reserve_out) / (reserve_in * 10000 + amount_in * (10000 - fee))
+    fn get_amount_out_with_fee(env: &Env, amount_in: i128, reserve_in: i128, reserve_out: i128, fee_bps: i128) -> i128 {
+        let amount_in_with_fee = amount_in.checked_mul(10_000 - fee_bps)
+            .unwrap_or_else(|| panic_with_error!(env, PoolError::InsufficientAmount));
+
+        let numerator = amount_in_with_fee.checked_mul(reserve_out)
+            .unwrap_or_else(|| panic_with_error!(env, PoolError::InsufficientAmount));
+
+        let denominator = reserve_in.checked_mul(10_000)
+            .and_then(|v| v.checked_add(amount_in_with_fee))
+            .unwrap_or_else(|| panic_with_error!(env, PoolError::InsufficientAmount));
+
+        numerator.checked_div(denominator)
+            .unwrap_or_else(|| panic_with_error!(env, PoolError::InsufficientAmount))
+    }
+
+    /// Whether the pool uses weights other than 50/50
+    fn is_weighted(env: &Env) -> bool {
+        env.storage().instance().has(&WEIGHT_A)
+    }
+
+    /// Output for an exact input on this pool's curve: constant product for 50/50 pools,
+    /// the weighted invariant otherwise
+    fn swap_amount_out(env: &Env, amount_in: i128, reserve_in: i128, reserve_out: i128, fee_bps: i128, is_a_to_b: bool) -> i128 {
+        if !Self::is_weighted(env) {
+            return Self::get_amount_out_with_fee(env, amount_in, reserve_in, reserve_out, fee_bps);
+        }
+
+        let (weight_a, weight_b) = Self::get_weights(env.clone());
+        let (weight_in, weight_out) = if is_a_to_b { (weight_a, weight_b) } else { (weight_b, weight_a) };
+        Self::get_weighted_amount_out(env, amount_in, reserve_in, reserve_out, fee_bps, weight_in, weight_out)
+    }
+
+    /// Output for an exact input under x^w_in * y^w_out = k with a fee in bps
+    /// amount_out = reserve_out * (1 - (reserve_in / (reserve_in + amount_in_after_fee))^(w_in / w_out))
+    /// The power is rounded up so integer error always favours the pool
+    fn get_weighted_amount_out(
+        env: &Env,
+        amount_in: i128,
+        reserve_in: i128,
+        reserve_out: i128,
+        fee_bps: i128,
+        weight_in: u32,
+        weight_out: u32,
+    ) -> i128 {
+        if reserve_in <= 0 || reserve_out <= 0 {
+            panic_with_error!(env, PoolError::InsufficientLiquidity);
+        }
+
+        let amount_in_with_fee = amount_in.checked_mul(10_000 - fee_bps)
+            .map(|v| v / 10_000)
+            .unwrap_or_else(|| panic_with_error!(env, PoolError::InsufficientAmount));
+        let new_reserve_in = reserve_in.checked_add(amount_in_with_fee)
+            .unwrap_or_else(|| panic_with_error!(env, PoolError::InsufficientAmount));
+
+        // ratio = reserve_in / new_reserve_in in (0, 1], rounded up
+        let ratio = reserve_in.checked_mul(WEIGHT_SCALE)
+            .map(|v| (v + new_reserve_in - 1) / new_reserve_in)
+            .unwrap_or_else(|| panic_with_error!(env, PoolError::InsufficientAmount));
+
+        // ratio^(w_in / w_out) as the q-th root of ratio^p, with p/q the reduced weight ratio
+        let divisor = Self::gcd(weight_in, weight_out);
+        let powered = Self::pow_fixed_up(ratio, weight_in / divisor);
+        let factor = Self::root_fixed_up(powered, weight_out / divisor);
+
+        reserve_out.checked_mul(WEIGHT_SCALE - factor)
+            .map(|v| v / WEIGHT_SCALE)
+            .unwrap_or_else(|| panic_with_error!(env, PoolError::InsufficientAmount))
+    }
+
+    /// a * b in WEIGHT_SCALE fixed point, rounded up (both inputs in [0, 1])
+    fn mul_fixed_up(a: i128, b: i128) -> i128 {
+        (a * b + WEIGHT_SCALE - 1) / WEIGHT_SCALE
+    }
+
+    /// base^exp in fixed point by square-and-multiply, rounded up
+    fn pow_fixed_up(base: i128, exp: u32) -> i128 {
+        let mut result = WEIGHT_SCALE;
+        let mut base = base;
+        let mut exp = exp;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = Self::mul_fixed_up(result, base);
+            }
+            base = Self::mul_fixed_up(base, base);
+            exp >>= 1;
+        }
+        result
+    }
+
+    /// Smallest fixed-point y in [0, 1] with y^n >= value (bisection; y^n is monotonic)
+    fn root_fixed_up(value: i128, n: u32) -> i128 {
+        if n == 1 {
+            return value;
+        }
+        let (mut lo, mut hi) = (0i128, WEIGHT_SCALE);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if Self::pow_fixed_up(mid, n) >= value {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        lo
+    }
+
+    fn gcd(a: u32, b: u32) -> u32 {
+        if b == 0 { a } else { Self::gcd(b, a % b) }
+    }
+
+    /// Input required for an exact output (inverse of the constant-product get_amount_out_with_fee)
+    /// amount_in = (reserve_in * amount_out * 10000) / ((reserve_out - amount_out) * (10000 - fee)) + 1
+    fn get_amount_in(env: &Env, amount_out: i128, reserve_in: i128, reserve_out: i128, fee_bps: i128) -> i128 {
+        if amount_out >= reserve_out {
+            panic_with_error!(env, PoolError::InsufficientLiquidity);
+        }
+
+        let numerator = reserve_in.checked_mul(amount_out)
+            .and_then(|v| v.checked_mul(10_000))
+            .unwrap_or_else(|| panic_with_error!(env, PoolError::InsufficientAmount));
+
+        let denominator = (reserve_out - amount_out).checked_mul(10_000 - fee_bps)
+            .unwrap_or_else(|| panic_with_error!(env, PoolError::InsufficientAmount));
+
+        numerator.checked_div(denominator)
+            .and_then(|v| v.checked_add(1))
+            .unwrap_or_else(|| panic_with_error!(env, PoolError::InsufficientAmount))
+    }
+
+    /// Integer square root (Babylonian method)
+    fn sqrt(x: i128) -> i128 {
+        if x == 0 {
             return 0;
         }
-        let mut z = x;
-        let mut y = (x + 1) / 2;
-        while y < z {
-            z = y;
-            y = (x / y + y) / 2;
+        let mut z = x;
+        let mut y = (x + 1) / 2;
+        while y < z {
+            z = y;
+            y = (x / y + y) / 2;
+        }
+        z
+    }
+}
+
+/// Test-only helpers; never compiled into a deployed pool
+#[cfg(any(test, feature = "testutils"))]
+#[contractimpl]
+impl RealLiquidityPool {
+    /// Set the reserves of an empty pool directly and mint the initial LP shares to `to`
+    /// No tokens move: the pool's balances of both tokens must back the seeded reserves
+    /// (swaps check the input side against reserve_in), so callers mint them to the pool
+    pub fn seed_reserves(env: Env, reserve_a: i128, reserve_b: i128, to: Address) -> i128 {
+        if !Self::is_initialized(env.clone()) {
+            panic_with_error!(&env, PoolError::NotInitialized);
+        }
+        if reserve_a <= 0 || reserve_b <= 0 {
+            panic_with_error!(&env, PoolError::InsufficientAmount);
+        }
+        let total_shares: i128 = env.storage().instance().get(&TOTAL_SHARES).unwrap_or(0);
+        if total_shares != 0 {
+            panic_with_error!(&env, PoolError::AlreadyInitialized);
+        }
+
+        let liquidity = reserve_a.checked_mul(reserve_b)
+            .map(Self::sqrt)
+            .unwrap_or_else(|| panic_with_error!(&env, PoolError::InsufficientAmount));
+
+        env.storage().instance().set(&RESERVE_A, &reserve_a);
+        env.storage().instance().set(&RESERVE_B, &reserve_b);
+        env.storage().instance().set(&TOTAL_SHARES, &liquidity);
+        env.storage().instance().set(&(symbol_short!("LP"), to), &liquidity);
+        Self::update_k_last(&env, reserve_a, reserve_b);
+        Self::emit_reserves(&env);
+
+        liquidity
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use soroban_sdk::{testutils::{Address as _, Events, Ledger}, token, Address, Env, IntoVal};
+
+    fn create_token<'a>(env: &Env, admin: &Address) -> (token::Client<'a>, token::StellarAssetClient<'a>) {
+        let sac = env.register_stellar_asset_contract_v2(admin.clone());
+        (
+            token::Client::new(env, &sac.address()),
+            token::StellarAssetClient::new(env, &sac.address()),
+        )
+    }
+
+    /// Deploy a pool over two fresh tokens and seed it with the given reserves
+    fn setup_pool<'a>(
+        env: &Env,
+        reserve_a: i128,
+        reserve_b: i128,
+    ) -> (RealLiquidityPoolClient<'a>, token::Client<'a>, token::Client<'a>, token::StellarAssetClient<'a>, token::StellarAssetClient<'a>) {
+        let admin = Address::generate(env);
+        let (token_a, token_a_admin) = create_token(env, &admin);
+        let (token_b, token_b_admin) = create_token(env, &admin);
+
+        let contract_id = env.register_contract(None, RealLiquidityPool);
+        let client = RealLiquidityPoolClient::new(env, &contract_id);
+        client.initialize(&Address::generate(env), &token_a.address, &token_b.address);
+
+        // Match the pool's canonical ordering so token_a is always token_0
+        let (token_a, token_b, token_a_admin, token_b_admin) = if client.token_0() == token_a.address {
+            (token_a, token_b, token_a_admin, token_b_admin)
+        } else {
+            (token_b, token_a, token_b_admin, token_a_admin)
+        };
+
+        let provider = Address::generate(env);
+        token_a_admin.mint(&provider, &reserve_a);
+        token_b_admin.mint(&provider, &reserve_b);
+        client.add_liquidity(&provider, &reserve_a, &reserve_b, &0, &0);
+
+        (client, token_a, token_b, token_a_admin, token_b_admin)
+    }
+
+    #[test]
+    fn test_pool_initialization() {
+        let env = Env::default();
+        let contract_id = env.register_contract(None, RealLiquidityPool);
+        let client = RealLiquidityPoolClient::new(&env, &contract_id);
+        
+        let token_a = Address::generate(&env);
+        let token_b = Address::generate(&env);
+        
+        client.initialize(&Address::generate(&env), &token_a, &token_b);
+        
+        let (token_0, token_1) = if token_a < token_b { (token_a, token_b) } else { (token_b, token_a) };
+        let pool_info = client.get_pool_info();
+        assert_eq!(pool_info.token_a, token_0);
+        assert_eq!(pool_info.token_b, token_1);
+        assert_eq!(pool_info.reserve_a, 0);
+        assert_eq!(pool_info.reserve_b, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #6)")]
+    fn test_initialize_rejects_identical_tokens() {
+        let env = Env::default();
+        let contract_id = env.register_contract(None, RealLiquidityPool);
+        let client = RealLiquidityPoolClient::new(&env, &contract_id);
+
+        let token = Address::generate(&env);
+        client.initialize(&Address::generate(&env), &token, &token);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #7)")]
+    fn test_add_liquidity_reverts_below_min_after_price_moves() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, token_a, token_b, token_a_admin, token_b_admin) = setup_pool(&env, 10_000, 10_000);
+
+        // Provider quotes a 1:1 add and sets tight minimums
+        let provider = Address::generate(&env);
+        token_a_admin.mint(&provider, &1_000);
+        token_b_admin.mint(&provider, &1_000);
+        let (reserve_a, reserve_b) = client.get_reserves();
+        let quoted_b = 1_000 * reserve_b / reserve_a;
+
+        // A swap lands between the quote and the add, shifting the ratio
+        let trader = Address::generate(&env);
+        token_a_admin.mint(&trader, &2_000);
+        token_a.transfer(&trader, &client.address, &2_000);
+        client.swap(&trader, &token_a.address, &2_000, &0);
+        assert!(token_b.balance(&trader) > 0);
+
+        client.add_liquidity(&provider, &1_000, &1_000, &1_000, &(quoted_b * 99 / 100));
+    }
+
+    #[test]
+    fn test_add_liquidity_within_slippage_succeeds() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, _, _, token_a_admin, token_b_admin) = setup_pool(&env, 10_000, 10_000);
+
+        let provider = Address::generate(&env);
+        token_a_admin.mint(&provider, &1_000);
+        token_b_admin.mint(&provider, &1_000);
+
+        let (_, amount_a, amount_b) = client.add_liquidity(&provider, &1_000, &1_000, &990, &990);
+        assert_eq!((amount_a, amount_b), (1_000, 1_000));
+    }
+
+    #[test]
+    fn test_referred_swap_credits_referrer() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let (token_a, token_a_admin) = create_token(&env, &admin);
+        let (token_b, token_b_admin) = create_token(&env, &admin);
+
+        let contract_id = env.register_contract(None, RealLiquidityPool);
+        let client = RealLiquidityPoolClient::new(&env, &contract_id);
+        let fee_to = Address::generate(&env);
+        client.initialize_with_fee_to(&Address::generate(&env), &token_a.address, &token_b.address, &fee_to);
+        client.set_referral_fee_bps(&10);
+
+        let provider = Address::generate(&env);
+        token_a_admin.mint(&provider, &1_000_000);
+        token_b_admin.mint(&provider, &1_000_000);
+        client.add_liquidity(&provider, &1_000_000, &1_000_000, &0, &0);
+
+        let trader = Address::generate(&env);
+        let referrer = Address::generate(&env);
+        token_a_admin.mint(&trader, &20_000);
+
+        // Unreferred swap pays nobody
+        token_a.transfer(&trader, &client.address, &10_000);
+        client.swap(&trader, &token_a.address, &10_000, &0);
+        assert_eq!(token_a.balance(&referrer), 0);
+
+        // Referred swap pays 0.1% of the input to the referrer
+        let (reserve_a_before, _) = if client.token_0() == token_a.address {
+            client.get_reserves()
+        } else {
+            let (r0, r1) = client.get_reserves();
+            (r1, r0)
+        };
+        token_a.transfer(&trader, &client.address, &10_000);
+        client.swap_referred(&trader, &token_a.address, &10_000, &0, &Some(referrer.clone()));
+        assert_eq!(token_a.balance(&referrer), 10);
+
+        let (reserve_a_after, _) = if client.token_0() == token_a.address {
+            client.get_reserves()
+        } else {
+            let (r0, r1) = client.get_reserves();
+            (r1, r0)
+        };
+        assert_eq!(reserve_a_after - reserve_a_before, 9_990);
+    }
+
+    #[test]
+    fn test_lp_token_metadata() {
+        let env = Env::default();
+        let contract_id = env.register_contract(None, RealLiquidityPool);
+        let client = RealLiquidityPoolClient::new(&env, &contract_id);
+
+        client.initialize(&Address::generate(&env), &Address::generate(&env), &Address::generate(&env));
+
+        // Plain addresses have no symbol to name the pair after
+        assert_eq!(client.lp_name(), String::from_str(&env, "Syft LP"));
+        assert_eq!(client.lp_symbol(), String::from_str(&env, "SYFT-LP"));
+        assert_eq!(client.lp_decimals(), 7);
+    }
+
+    /// Token exposing only a symbol, enough for LP metadata
+    #[contract]
+    struct NamedToken;
+
+    #[contractimpl]
+    impl NamedToken {
+        pub fn set_symbol(env: Env, symbol: String) {
+            env.storage().instance().set(&LP_SYMBOL, &symbol);
+        }
+
+        pub fn symbol(env: Env) -> String {
+            env.storage().instance().get(&LP_SYMBOL).unwrap()
         }
-        z
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use soroban_sdk::{testutils::Address as _, Address, Env};
+    #[test]
+    fn test_lp_metadata_names_the_pair() {
+        let env = Env::default();
+        let xlm = env.register_contract(None, NamedToken);
+        NamedTokenClient::new(&env, &xlm).set_symbol(&String::from_str(&env, "XLM"));
+        let usdc = env.register_contract(None, NamedToken);
+        NamedTokenClient::new(&env, &usdc).set_symbol(&String::from_str(&env, "USDC"));
+
+        let contract_id = env.register_contract(None, RealLiquidityPool);
+        let client = RealLiquidityPoolClient::new(&env, &contract_id);
+        client.initialize(&Address::generate(&env), &xlm, &usdc);
+
+        // Named in the pool's canonical token order
+        let (name, symbol) = if client.token_0() == xlm {
+            ("Syft LP XLM-USDC", "XLM-USDC-LP")
+        } else {
+            ("Syft LP USDC-XLM", "USDC-XLM-LP")
+        };
+        assert_eq!(client.lp_name(), String::from_str(&env, name));
+        assert_eq!(client.lp_symbol(), String::from_str(&env, symbol));
+    }
 
     #[test]
-    fn test_pool_initialization() {
+    fn test_large_swap_pays_higher_fee_tier() {
         let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let (token_a, token_a_admin) = create_token(&env, &admin);
+        let (token_b, token_b_admin) = create_token(&env, &admin);
+
         let contract_id = env.register_contract(None, RealLiquidityPool);
         let client = RealLiquidityPoolClient::new(&env, &contract_id);
-        
+        let tiers = soroban_sdk::vec![
+            &env,
+            FeeTier { min_amount_in: 0, fee_bps: 30 },
+            FeeTier { min_amount_in: 10_000, fee_bps: 100 },
+        ];
+        client.initialize_with_fee_tiers(&Address::generate(&env), &token_a.address, &token_b.address, &tiers);
+
+        let provider = Address::generate(&env);
+        token_a_admin.mint(&provider, &1_000_000);
+        token_b_admin.mint(&provider, &1_000_000);
+        client.add_liquidity(&provider, &1_000_000, &1_000_000, &0, &0);
+
+        assert_eq!(client.fee_bps_for(&1_000), 30);
+        assert_eq!(client.fee_bps_for(&50_000), 100);
+
+        // Quote both tiers for the large swap; the pool must charge the higher one
+        let amount_in = 50_000i128;
+        let quote = |fee_bps: i128| {
+            let with_fee = amount_in * (10_000 - fee_bps);
+            with_fee * 1_000_000 / (1_000_000 * 10_000 + with_fee)
+        };
+
+        let trader = Address::generate(&env);
+        token_a_admin.mint(&trader, &amount_in);
+        token_a.transfer(&trader, &client.address, &amount_in);
+        let amount_out = client.swap(&trader, &token_a.address, &amount_in, &0);
+
+        assert_eq!(amount_out, quote(100));
+        assert!(amount_out < quote(30));
+    }
+
+    #[test]
+    fn test_fee_tiers_apply_to_partial_swaps_and_quotes() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let (token_a, token_a_admin) = create_token(&env, &admin);
+        let (token_b, token_b_admin) = create_token(&env, &admin);
+
+        let contract_id = env.register_contract(None, RealLiquidityPool);
+        let client = RealLiquidityPoolClient::new(&env, &contract_id);
+        let tiers = soroban_sdk::vec![
+            &env,
+            FeeTier { min_amount_in: 0, fee_bps: 30 },
+            FeeTier { min_amount_in: 10_000, fee_bps: 100 },
+        ];
+        client.initialize_with_fee_tiers(&Address::generate(&env), &token_a.address, &token_b.address, &tiers);
+
+        let provider = Address::generate(&env);
+        token_a_admin.mint(&provider, &1_000_000);
+        token_b_admin.mint(&provider, &1_000_000);
+        client.add_liquidity(&provider, &1_000_000, &1_000_000, &0, &0);
+
+        let amount_in = 50_000i128;
+        let with_fee = amount_in * (10_000 - 100);
+        let tier_out = with_fee * 1_000_000 / (1_000_000 * 10_000 + with_fee);
+
+        // The quote is priced at the large swap's tier, not the default fee
+        assert_eq!(client.effective_price(&token_a.address, &amount_in), amount_in * 10_000_000 / tier_out);
+
+        let trader = Address::generate(&env);
+        token_a_admin.mint(&trader, &amount_in);
+        let (amount_in_used, amount_out) = client.swap_partial(&trader, &token_a.address, &amount_in, &0);
+
+        assert_eq!(amount_in_used, amount_in);
+        assert_eq!(amount_out, tier_out);
+    }
+
+    #[test]
+    fn test_probe_uninitialized_pool() {
+        let env = Env::default();
+        let contract_id = env.register_contract(None, RealLiquidityPool);
+        let client = RealLiquidityPoolClient::new(&env, &contract_id);
+
+        assert!(!client.is_initialized());
+        assert_eq!(client.probe_pool_info(), None);
+
+        client.initialize(&Address::generate(&env), &Address::generate(&env), &Address::generate(&env));
+        assert!(client.is_initialized());
+        assert!(client.probe_pool_info().is_some());
+    }
+
+    #[test]
+    fn test_swap_partial_fills_oversized_swap() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, token_a, token_b, token_a_admin, _) = setup_pool(&env, 1000, 1000);
+
+        let trader = Address::generate(&env);
+        token_a_admin.mint(&trader, &10_000_000);
+
+        let (amount_in_used, amount_out) = client.swap_partial(&trader, &token_a.address, &10_000_000, &0);
+
+        // Only the input needed for the maximum obtainable output is taken
+        assert!(amount_in_used < 10_000_000);
+        assert_eq!(amount_out, 999);
+        assert_eq!(token_a.balance(&trader), 10_000_000 - amount_in_used);
+        assert_eq!(token_b.balance(&trader), 999);
+
+        let pool_info = client.get_pool_info();
+        assert_eq!(pool_info.reserve_a, 1000 + amount_in_used);
+        assert_eq!(pool_info.reserve_b, 1);
+    }
+
+    #[test]
+    fn test_swap_rejects_zero_output() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, token_a, _, token_a_admin, _) = setup_pool(&env, 1_000_000_000, 1_000);
+
+        // 1 unit in against a billion-deep reserve rounds down to nothing out
+        let trader = Address::generate(&env);
+        token_a_admin.mint(&trader, &1);
+
+        let result = client.try_swap(&trader, &token_a.address, &1, &0);
+        assert_eq!(result, Err(Ok(PoolError::InsufficientOutputAmount.into())));
+        assert_eq!(token_a.balance(&trader), 1);
+    }
+
+    #[test]
+    fn test_reserves_event_after_swap() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, token_a, _, token_a_admin, _) = setup_pool(&env, 10_000, 10_000);
+
+        // Transfer-first swap, as the vault does
+        let trader = Address::generate(&env);
+        token_a_admin.mint(&trader, &1_000);
+        token_a.transfer(&trader, &client.address, &1_000);
+        client.swap(&trader, &token_a.address, &1_000, &0);
+
+        let reserves_event = env.events().all().iter()
+            .filter(|(contract, topics, _)| {
+                contract == &client.address
+                    && topics == &(symbol_short!("reserves"),).into_val(&env)
+            })
+            .last()
+            .expect("reserves event");
+
+        let (reserve_a, reserve_b): (i128, i128) = reserves_event.2.into_val(&env);
+        let pool_info = client.get_pool_info();
+        assert_eq!(reserve_a, pool_info.reserve_a);
+        assert_eq!(reserve_b, pool_info.reserve_b);
+        assert_eq!(reserve_a, 11_000);
+    }
+
+    #[test]
+    fn test_pool_info_checked_flags_drift_until_sync() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, token_a, _, token_a_admin, _) = setup_pool(&env, 10_000, 10_000);
+        assert!(client.get_pool_info_checked().1);
+
+        // Out-of-band transfer leaves stored reserves behind the balance
+        let donor = Address::generate(&env);
+        token_a_admin.mint(&donor, &500);
+        token_a.transfer(&donor, &client.address, &500);
+
+        let (info, consistent) = client.get_pool_info_checked();
+        assert!(!consistent);
+        assert_eq!(info.reserve_a, 10_000);
+
+        client.sync();
+        assert!(client.get_pool_info_checked().1);
+    }
+
+    #[test]
+    fn test_donation_does_not_move_price_until_sync() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, token_a, token_b, token_a_admin, _) = setup_pool(&env, 10_000, 10_000);
+
+        // Donate token A directly to the pool
+        let donor = Address::generate(&env);
+        token_a_admin.mint(&donor, &5_000);
+        token_a.transfer(&donor, &client.address, &5_000);
+
+        assert_eq!(client.get_reserves(), (10_000, 10_000));
+
+        // A swap right after the donation is priced off the stored reserves
+        let trader = Address::generate(&env);
+        token_a_admin.mint(&trader, &2_000);
+        token_a.transfer(&trader, &client.address, &1_000);
+        let out_before_sync = client.swap(&trader, &token_a.address, &1_000, &0);
+        assert_eq!(out_before_sync, 906);
+        assert_eq!(client.get_reserves(), (11_000, 10_000 - 906));
+
+        // Sync adopts the donated balance and only then moves the price
+        client.sync();
+        assert_eq!(client.get_reserves(), (16_000, 10_000 - 906));
+        assert_eq!(token_b.balance(&client.address), 10_000 - 906);
+
+        token_a.transfer(&trader, &client.address, &1_000);
+        let out_after_sync = client.swap(&trader, &token_a.address, &1_000, &0);
+        assert!(out_after_sync < out_before_sync);
+    }
+
+    #[test]
+    fn test_accrued_fees_after_swaps() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, token_a, token_b, token_a_admin, token_b_admin) = setup_pool(&env, 1_000_000, 1_000_000);
+
+        // No swaps yet: nothing accrued since the seeding liquidity event
+        assert_eq!(client.accrued_fees(), (0, 0));
+
+        let trader = Address::generate(&env);
+        token_a_admin.mint(&trader, &1_000_000);
+        token_b_admin.mint(&trader, &1_000_000);
+
+        // Swap back and forth a few times
+        for _ in 0..3 {
+            token_a.transfer(&trader, &client.address, &100_000);
+            let out_b = client.swap(&trader, &token_a.address, &100_000, &0);
+            token_b.transfer(&trader, &client.address, &out_b);
+            client.swap(&trader, &token_b.address, &out_b, &0);
+        }
+
+        let (fees_a, fees_b) = client.accrued_fees();
+        assert!(fees_a > 0);
+        assert!(fees_b > 0);
+    }
+
+    /// Swap back and forth to accrue fees into reserves
+    fn churn(env: &Env, client: &RealLiquidityPoolClient, token_a: &token::Client, token_b: &token::Client, token_a_admin: &token::StellarAssetClient) {
+        let trader = Address::generate(env);
+        token_a_admin.mint(&trader, &1_000_000);
+        for _ in 0..3 {
+            token_a.transfer(&trader, &client.address, &100_000);
+            let out_b = client.swap(&trader, &token_a.address, &100_000, &0);
+            token_b.transfer(&trader, &client.address, &out_b);
+            client.swap(&trader, &token_b.address, &out_b, &0);
+        }
+    }
+
+    #[test]
+    fn test_protocol_fee_minted_to_fee_to() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let (token_a, token_a_admin) = create_token(&env, &admin);
+        let (token_b, token_b_admin) = create_token(&env, &admin);
+        let fee_to = Address::generate(&env);
+
+        let client = RealLiquidityPoolClient::new(&env, &env.register_contract(None, RealLiquidityPool));
+        client.initialize_with_fee_to(&Address::generate(&env), &token_a.address, &token_b.address, &fee_to);
+        let (token_a, token_b, token_a_admin, token_b_admin) = if client.token_0() == token_a.address {
+            (token_a, token_b, token_a_admin, token_b_admin)
+        } else {
+            (token_b, token_a, token_b_admin, token_a_admin)
+        };
+
+        let provider = Address::generate(&env);
+        token_a_admin.mint(&provider, &2_000_000);
+        token_b_admin.mint(&provider, &2_000_000);
+        client.add_liquidity(&provider, &1_000_000, &1_000_000, &0, &0);
+        assert_eq!(client.get_user_liquidity(&fee_to), 0);
+
+        churn(&env, &client, &token_a, &token_b, &token_a_admin);
+
+        // The next liquidity event settles the protocol's cut
+        client.add_liquidity(&provider, &1_000, &1_000, &0, &0);
+        let fee_shares = client.get_user_liquidity(&fee_to);
+        assert!(fee_shares > 0);
+        assert_eq!(
+            client.get_pool_info().total_shares,
+            client.get_user_liquidity(&provider) + fee_shares
+        );
+    }
+
+    #[test]
+    fn test_no_protocol_fee_without_fee_to() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, token_a, token_b, token_a_admin, token_b_admin) = setup_pool(&env, 1_000_000, 1_000_000);
+        churn(&env, &client, &token_a, &token_b, &token_a_admin);
+
+        let provider = Address::generate(&env);
+        token_a_admin.mint(&provider, &10_000);
+        token_b_admin.mint(&provider, &10_000);
+        let shares_before = client.get_pool_info().total_shares;
+        let (minted, _, _) = client.add_liquidity(&provider, &1_000, &1_000, &0, &0);
+
+        assert_eq!(client.get_fee_to(), None);
+        assert_eq!(client.get_pool_info().total_shares, shares_before + minted);
+    }
+
+    #[test]
+    fn test_effective_price_worsens_with_size() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, token_a, _, _, _) = setup_pool(&env, 1_000_000, 1_000_000);
+
+        // Spot price of B in A with equal reserves, same 1e7 scale
+        let (reserve_a, reserve_b) = client.get_reserves();
+        let spot_price = reserve_a * 10_000_000 / reserve_b;
+
+        let small = client.effective_price(&token_a.address, &1_000);
+        let large = client.effective_price(&token_a.address, &100_000);
+
+        assert!(small > spot_price);
+        assert!(large > small);
+    }
+
+    #[test]
+    fn test_initialize_normalizes_token_order() {
+        let env = Env::default();
+
+        let token_x = Address::generate(&env);
+        let token_y = Address::generate(&env);
+
+        let pool_xy = RealLiquidityPoolClient::new(&env, &env.register_contract(None, RealLiquidityPool));
+        pool_xy.initialize(&Address::generate(&env), &token_x, &token_y);
+
+        let pool_yx = RealLiquidityPoolClient::new(&env, &env.register_contract(None, RealLiquidityPool));
+        pool_yx.initialize(&Address::generate(&env), &token_y, &token_x);
+
+        assert_eq!(pool_xy.token_0(), pool_yx.token_0());
+        assert_eq!(pool_xy.token_1(), pool_yx.token_1());
+        assert!(pool_xy.token_0() < pool_xy.token_1());
+    }
+
+    #[test]
+    fn test_tokens_returns_initialized_pair() {
+        let env = Env::default();
+        let contract_id = env.register_contract(None, RealLiquidityPool);
+        let client = RealLiquidityPoolClient::new(&env, &contract_id);
+
         let token_a = Address::generate(&env);
         let token_b = Address::generate(&env);
-        
-        client.initialize(&token_a, &token_b);
-        
-        let pool_info = client.get_pool_info();
-        assert_eq!(pool_info.token_a, token_a);
-        assert_eq!(pool_info.token_b, token_b);
-        assert_eq!(pool_info.reserve_a, 0);
-        assert_eq!(pool_info.reserve_b, 0);
+        client.initialize(&Address::generate(&env), &token_a, &token_b);
+
+        let (token_0, token_1) = if token_a < token_b { (token_a, token_b) } else { (token_b, token_a) };
+        assert_eq!(client.tokens(), (token_0, token_1));
+        assert_eq!(client.tokens(), (client.token_0(), client.token_1()));
+    }
+
+    #[test]
+    fn test_min_lp_duration_locks_removal() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let (token_a, token_a_admin) = create_token(&env, &admin);
+        let (token_b, token_b_admin) = create_token(&env, &admin);
+
+        let contract_id = env.register_contract(None, RealLiquidityPool);
+        let client = RealLiquidityPoolClient::new(&env, &contract_id);
+        client.initialize_with_min_lp_duration(&Address::generate(&env), &token_a.address, &token_b.address, &3_600);
+        assert_eq!(client.get_min_lp_duration(), 3_600);
+
+        let provider = Address::generate(&env);
+        token_a_admin.mint(&provider, &10_000);
+        token_b_admin.mint(&provider, &10_000);
+        let (liquidity, _, _) = client.add_liquidity(&provider, &10_000, &10_000, &0, &0);
+
+        // Removing right away is rejected
+        let result = client.try_remove_liquidity(&provider, &liquidity, &0, &0);
+        assert_eq!(result, Err(Ok(PoolError::LiquidityLocked.into())));
+
+        // Once the window has passed the shares come out
+        env.ledger().with_mut(|li| li.timestamp += 3_600);
+        let (amount_a, amount_b) = client.remove_liquidity(&provider, &liquidity, &0, &0);
+        assert_eq!((amount_a, amount_b), (10_000, 10_000));
+    }
+
+    #[test]
+    fn test_paused_pool_rejects_swaps() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let (token_a, token_a_admin) = create_token(&env, &admin);
+        let (token_b, token_b_admin) = create_token(&env, &admin);
+
+        let contract_id = env.register_contract(None, RealLiquidityPool);
+        let client = RealLiquidityPoolClient::new(&env, &contract_id);
+        // A plain pool has no fee_to, but its admin can still pause it
+        client.initialize(&admin, &token_a.address, &token_b.address);
+        assert_eq!(client.get_fee_to(), None);
+
+        let provider = Address::generate(&env);
+        token_a_admin.mint(&provider, &10_000);
+        token_b_admin.mint(&provider, &10_000);
+        client.add_liquidity(&provider, &10_000, &10_000, &0, &0);
+
+        client.set_paused(&true);
+        assert_eq!(env.auths()[0].0, admin);
+        assert!(client.is_paused());
+
+        let trader = Address::generate(&env);
+        token_a_admin.mint(&trader, &100);
+        let result = client.try_swap(&trader, &token_a.address, &100, &0);
+        assert_eq!(result, Err(Ok(PoolError::Paused.into())));
+
+        client.set_paused(&false);
+        assert!(!client.is_paused());
+        token_a.transfer(&trader, &client.address, &100);
+        assert!(client.swap(&trader, &token_a.address, &100, &0) > 0);
+    }
+
+    #[test]
+    fn test_clearing_fee_to_keeps_admin() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let fee_to = Address::generate(&env);
+        let contract_id = env.register_contract(None, RealLiquidityPool);
+        let client = RealLiquidityPoolClient::new(&env, &contract_id);
+        client.initialize_with_fee_to(&admin, &Address::generate(&env), &Address::generate(&env), &fee_to);
+
+        client.set_fee_to(&None);
+        assert_eq!(env.auths()[0].0, admin);
+        assert_eq!(client.get_fee_to(), None);
+
+        client.set_referral_fee_bps(&10);
+        client.set_paused(&true);
+        assert_eq!(client.get_admin(), admin);
+        assert_eq!(client.get_referral_fee_bps(), 10);
+        assert!(client.is_paused());
+    }
+
+    #[test]
+    fn test_weighted_pool_prices_differently() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let (token_a, token_a_admin) = create_token(&env, &admin);
+        let (token_b, token_b_admin) = create_token(&env, &admin);
+        let (token_0, token_1) = if token_a.address < token_b.address {
+            (token_a.address.clone(), token_b.address.clone())
+        } else {
+            (token_b.address.clone(), token_a.address.clone())
+        };
+
+        let deploy = |weight_0: u32, weight_1: u32| {
+            let client = RealLiquidityPoolClient::new(&env, &env.register_contract(None, RealLiquidityPool));
+            client.initialize_with_weights(&Address::generate(&env), &token_0, &token_1, &weight_0, &weight_1);
+            let provider = Address::generate(&env);
+            token_a_admin.mint(&provider, &100_000);
+            token_b_admin.mint(&provider, &100_000);
+            client.add_liquidity(&provider, &100_000, &100_000, &0, &0);
+            client
+        };
+
+        let plain = deploy(50, 50);
+        let weighted = deploy(80, 20);
+        assert_eq!(plain.get_weights(), (50, 50));
+        assert_eq!(weighted.get_weights(), (80, 20));
+        assert_eq!(plain.get_reserves(), weighted.get_reserves());
+
+        // 50/50 keeps the constant-product quote: 10_000 * 9970 * 100_000 / (100_000 * 10_000 + 10_000 * 9970)
+        let expected_plain: i128 = 10_000 * 9970 * 100_000 / (100_000 * 10_000 + 10_000 * 9970);
+        assert_eq!(plain.effective_price(&token_0, &10_000), 10_000 * 10_000_000 / expected_plain);
+
+        // Selling the heavy token into the light side yields more than constant product,
+        // selling the light token yields less
+        assert!(weighted.effective_price(&token_0, &10_000) < plain.effective_price(&token_0, &10_000));
+        assert!(weighted.effective_price(&token_1, &10_000) > plain.effective_price(&token_1, &10_000));
+
+        // Actual swap on the weighted pool matches the weighted quote and leaves reserves consistent
+        let trader = Address::generate(&env);
+        let token_0_client = token::Client::new(&env, &token_0);
+        token::StellarAssetClient::new(&env, &token_0).mint(&trader, &10_000);
+        token_0_client.transfer(&trader, &weighted.address, &10_000);
+        let amount_out = weighted.swap(&trader, &token_0, &10_000, &0);
+        assert!(amount_out > expected_plain);
+        assert_eq!(weighted.get_reserves(), (110_000, 100_000 - amount_out));
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #12)")]
+    fn test_weights_must_sum_to_100() {
+        let env = Env::default();
+        let client = RealLiquidityPoolClient::new(&env, &env.register_contract(None, RealLiquidityPool));
+        client.initialize_with_weights(&Address::generate(&env), &Address::generate(&env), &Address::generate(&env), &80, &30);
+    }
+
+    #[test]
+    fn test_cumulative_fees_sum_per_swap_fees() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, token_a, token_b, token_a_admin, token_b_admin) = setup_pool(&env, 1_000_000, 1_000_000);
+        assert_eq!(client.cumulative_fees(), (0, 0));
+
+        let trader = Address::generate(&env);
+        token_a_admin.mint(&trader, &100_000);
+        token_b_admin.mint(&trader, &100_000);
+
+        // Default 0.3% fee, charged in the input token
+        for amount in [10_000i128, 25_000] {
+            token_a.transfer(&trader, &client.address, &amount);
+            client.swap(&trader, &token_a.address, &amount, &0);
+        }
+        for amount in [7_000i128, 40_000] {
+            token_b.transfer(&trader, &client.address, &amount);
+            client.swap(&trader, &token_b.address, &amount, &0);
+        }
+
+        let fee = |amount: i128| amount * 30 / 10_000;
+        assert_eq!(client.cumulative_fees(), (fee(10_000) + fee(25_000), fee(7_000) + fee(40_000)));
+    }
+
+    #[test]
+    fn test_remove_liquidity_from_transferred_lp_tokens() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, token_a, token_b, token_a_admin, token_b_admin) = setup_pool(&env, 10_000, 10_000);
+
+        let user = Address::generate(&env);
+        token_a_admin.mint(&user, &1_000);
+        token_b_admin.mint(&user, &1_000);
+        let (shares, _, _) = client.add_liquidity(&user, &1_000, &1_000, &0, &0);
+
+        // Transfer half the shares to the pool, then burn them for the underlying
+        client.lp_transfer(&user, &client.address, &(shares / 2));
+        let (amount_a, amount_b) = client.remove_liquidity_from_tokens(&user);
+
+        assert_eq!((amount_a, amount_b), (500, 500));
+        assert_eq!(token_a.balance(&user), 500);
+        assert_eq!(token_b.balance(&user), 500);
+        assert_eq!(client.get_user_liquidity(&user), shares - shares / 2);
+        assert_eq!(client.get_user_liquidity(&client.address), 0);
+    }
+
+    #[test]
+    fn test_lp_self_transfer_does_not_mint_shares() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, _, _, token_a_admin, token_b_admin) = setup_pool(&env, 10_000, 10_000);
+
+        let user = Address::generate(&env);
+        token_a_admin.mint(&user, &1_000);
+        token_b_admin.mint(&user, &1_000);
+        let (shares, _, _) = client.add_liquidity(&user, &1_000, &1_000, &0, &0);
+
+        client.lp_transfer(&user, &user, &shares);
+        assert_eq!(client.get_user_liquidity(&user), shares);
+    }
+
+    #[test]
+    fn test_swap_against_seeded_reserves() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let (token_a, token_a_admin) = create_token(&env, &admin);
+        let (token_b, token_b_admin) = create_token(&env, &admin);
+
+        let contract_id = env.register_contract(None, RealLiquidityPool);
+        let client = RealLiquidityPoolClient::new(&env, &contract_id);
+        client.initialize(&Address::generate(&env), &token_a.address, &token_b.address);
+
+        let (token_in, token_in_admin, token_out, token_out_admin) = if client.token_0() == token_a.address {
+            (token_a, token_a_admin, token_b, token_b_admin)
+        } else {
+            (token_b, token_b_admin, token_a, token_a_admin)
+        };
+
+        let shares = client.seed_reserves(&1_000_000, &4_000_000, &admin);
+        assert_eq!(shares, 2_000_000);
+        assert_eq!(client.get_user_liquidity(&admin), 2_000_000);
+
+        // Seeding moves no tokens, so back both reserves with real balances
+        token_in_admin.mint(&client.address, &1_000_000);
+        token_out_admin.mint(&client.address, &4_000_000);
+        let trader = Address::generate(&env);
+        token_in_admin.mint(&trader, &10_000);
+        token_in.transfer(&trader, &client.address, &10_000);
+
+        let amount_out = client.swap(&trader, &token_in.address, &10_000, &0);
+        let amount_in_with_fee = 10_000 * 997;
+        assert_eq!(amount_out, amount_in_with_fee * 4_000_000 / (1_000_000 * 1000 + amount_in_with_fee));
+        assert_eq!(token_out.balance(&trader), amount_out);
+    }
+
+    #[test]
+    fn test_total_liquidity_value_in_quote_token() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (client, token_a, token_b, _, _) = setup_pool(&env, 1000, 2000);
+
+        assert_eq!(client.total_liquidity_value(&token_b.address), 2000 * 2);
+        assert_eq!(client.total_liquidity_value(&token_a.address), 1000 * 2);
     }
 }
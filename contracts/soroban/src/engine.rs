@@ -22,10 +22,17 @@ fn evaluate_single_rule(env: &Env, rule: &RebalanceRule) -> bool {
     use soroban_sdk::String;
     
     // Time-based condition: Check if enough time has passed since last rebalance
-    if rule.condition_type == String::from_str(env, "time") {
+    if rule.condition_type == String::from_str(env, "time")
+        || rule.condition_type == String::from_str(env, "time_elapsed")
+    {
         return evaluate_time_condition(env, rule);
     }
     
+    // Drift condition: Check if live allocation drifted from the rule's target by threshold bps
+    if rule.condition_type == String::from_str(env, "drift") {
+        return evaluate_drift_condition(env, rule);
+    }
+    
     // APY threshold condition: Check if APY meets threshold
     if rule.condition_type == String::from_str(env, "apy") {
         return evaluate_apy_condition(env, rule);
@@ -96,6 +103,47 @@ fn evaluate_allocation_condition(env: &Env, _rule: &RebalanceRule) -> bool {
     state.total_value > 0
 }
 
+/// Evaluate drift condition against live vault balances
+/// Fires when any asset's share of the vault is off its target by at least threshold bps.
+/// Shares are taken from raw token balances with no price conversion, so targets only
+/// track value for assets priced close to each other
+fn evaluate_drift_condition(env: &Env, rule: &RebalanceRule) -> bool {
+    let config: crate::types::VaultConfig = match env.storage().instance().get(&symbol_short!("CONFIG")) {
+        Some(cfg) => cfg,
+        None => return false,
+    };
+    
+    if rule.target_allocation.len() != config.assets.len() {
+        return false;
+    }
+    
+    let mut balances: Vec<i128> = Vec::new(env);
+    let mut total: i128 = 0;
+    for asset in config.assets.iter() {
+        let balance = crate::token_client::get_vault_balance(env, &asset);
+        balances.push_back(balance);
+        total = total.saturating_add(balance);
+    }
+    
+    // Nothing held, nothing to drift
+    if total <= 0 {
+        return false;
+    }
+    
+    for i in 0..balances.len() {
+        if let (Some(balance), Some(target)) = (balances.get(i), rule.target_allocation.get(i)) {
+            // Allocations use 100_0000 = 100%; divide by 100 to get bps
+            let current = balance.saturating_mul(100_0000) / total;
+            let drift_bps = (current - target).abs() / 100;
+            if drift_bps >= rule.threshold {
+                return true;
+            }
+        }
+    }
+    
+    false
+}
+
 /// Evaluate price-based condition
 fn evaluate_price_condition(_env: &Env, rule: &RebalanceRule) -> bool {
     // Price movement detection
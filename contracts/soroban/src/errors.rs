@@ -24,4 +24,11 @@ pub enum VaultError {
     RouterNotSet = 17,
     TrustlineRequired = 18,
     FactoryNotSet = 19,
+    Reentrancy = 20,
+    RouterCallFailed = 21,
+    ConfigLocked = 22,
+    NoRouteFound = 23,
+    DepositCapExceeded = 24,
+    WithdrawalCooldown = 25,
+    PoolPaused = 26,
 }
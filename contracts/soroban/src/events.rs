@@ -1,18 +1,48 @@
 // Event emissions for vault actions
-use soroban_sdk::{symbol_short, Address, Env, Symbol, String};
+use soroban_sdk::{symbol_short, Address, Env, Symbol, String, Vec};
+use crate::types::AssetBalance;
 
 const DEPOSIT: Symbol = symbol_short!("deposit");
+const DEPOSIT_TOKEN: Symbol = symbol_short!("dep_token");
 const WITHDRAW: Symbol = symbol_short!("withdraw");
 const REBALANCE: Symbol = symbol_short!("rebalance");
+const WD_QUEUED: Symbol = symbol_short!("wd_queued");
+const WD_FILLED: Symbol = symbol_short!("wd_filled");
+const WD_IN_KIND: Symbol = symbol_short!("wd_inkind");
 
+/// Topics ("deposit", user) let indexers filter one user's activity; data is (amount, shares)
 pub fn emit_deposit(env: &Env, user: &Address, amount: i128, shares: i128) {
     env.events().publish((DEPOSIT, user), (amount, shares));
 }
 
+/// Companion to emit_deposit recording what was actually deposited and what it became
+pub fn emit_deposit_token(env: &Env, user: &Address, deposit_token: &Address, amount: i128, final_amount: i128) {
+    env.events().publish((DEPOSIT_TOKEN, user), (deposit_token.clone(), amount, final_amount));
+}
+
+/// Topics ("withdraw", user); data is (shares, amount)
 pub fn emit_withdraw(env: &Env, user: &Address, shares: i128, amount: i128) {
     env.events().publish((WITHDRAW, user), (shares, amount));
 }
 
+pub fn emit_withdraw_queued(env: &Env, user: &Address, shares: i128, amount_owed: i128) {
+    env.events().publish((WD_QUEUED, user), (shares, amount_owed));
+}
+
+pub fn emit_withdraw_filled(env: &Env, user: &Address, paid: i128, remaining: i128) {
+    env.events().publish((WD_FILLED, user), (paid, remaining));
+}
+
+/// Per-asset breakdown of an in-kind withdrawal; at most max_entries (the asset count) are published
+pub fn emit_withdraw_in_kind(env: &Env, user: &Address, shares: i128, paid: &Vec<AssetBalance>, max_entries: u32) {
+    let breakdown = if paid.len() > max_entries {
+        paid.slice(0..max_entries)
+    } else {
+        paid.clone()
+    };
+    env.events().publish((WD_IN_KIND, user), (shares, breakdown));
+}
+
 pub fn emit_rebalance(env: &Env, timestamp: u64) {
     env.events().publish((REBALANCE,), timestamp);
 }
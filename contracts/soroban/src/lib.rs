@@ -10,9 +10,11 @@ mod errors;
 mod token_client;
 mod swap_router;
 mod soroswap_router;
+mod phoenix_router;
 mod pool_client;  // Direct pool interaction
 mod real_pool_client;  // Custom token pool interaction
 mod staking_client;  // Liquid staking integration
+mod oracle_client;  // External price feeds
 mod liquidity_router;  // Liquidity provision
 // mod factory;  // Factory should be a separate contract
 // mod vault_nft;  // VaultNFT should be a separate contract
@@ -24,3 +26,5 @@ pub use vault::*;
 // Export types and errors for external use
 pub use types::*;
 pub use errors::*;
+
+mod test;
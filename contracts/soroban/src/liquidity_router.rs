@@ -1,6 +1,15 @@
 // Liquidity Pool interface for liquidity provision
 // This handles adding and removing liquidity from DEX pools
-use soroban_sdk::{contractclient, Address, Env};
+use soroban_sdk::{contractclient, contracttype, Address, Env};
+
+/// Pool-wide snapshot returned by get_pool_info (mirrors MockLiquidityPool's MockPoolInfo)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolLiquidityInfo {
+    pub reserve_a: i128,
+    pub reserve_b: i128,
+    pub total_lp: i128,
+}
 
 /// Liquidity Pool interface
 /// Interface for pool liquidity operations
@@ -34,6 +43,9 @@ pub trait LiquidityPoolInterface {
         deadline: u64,
     ) -> (i128, i128);
     
+    /// Get reserves and total LP tokens
+    fn get_pool_info(env: Env) -> PoolLiquidityInfo;
+    
     /// Get optimal amounts for adding liquidity
     /// Returns optimal amount_b for given amount_a
     fn quote(
@@ -44,6 +56,97 @@ pub trait LiquidityPoolInterface {
     ) -> i128;
 }
 
+/// Worst-case amounts accepted for a deposit of (amount_a, amount_b) at slippage_percent
+/// Exposed so UIs can show users the minimums the pool will enforce
+pub fn compute_min_amounts(
+    amount_a: i128,
+    amount_b: i128,
+    slippage_percent: i128, // e.g., 5 for 5% slippage
+) -> Result<(i128, i128), crate::errors::VaultError> {
+    use crate::errors::VaultError;
+    
+    if !(0..=100).contains(&slippage_percent) {
+        return Err(VaultError::InvalidConfiguration);
+    }
+    
+    let amount_a_min = amount_a
+        .checked_mul(100 - slippage_percent)
+        .and_then(|v| v.checked_div(100))
+        .ok_or(VaultError::InvalidAmount)?;
+    
+    let amount_b_min = amount_b
+        .checked_mul(100 - slippage_percent)
+        .and_then(|v| v.checked_div(100))
+        .ok_or(VaultError::InvalidAmount)?;
+    
+    Ok((amount_a_min, amount_b_min))
+}
+
+/// Amounts a pool with these reserves actually takes from (amount_a, amount_b)
+/// Mirrors the pool: the first deposit is taken as-is, later ones are trimmed to the
+/// reserve ratio on whichever side is in excess
+fn optimal_amounts(
+    pool_info: &PoolLiquidityInfo,
+    amount_a: i128,
+    amount_b: i128,
+) -> Result<(i128, i128), crate::errors::VaultError> {
+    use crate::errors::VaultError;
+
+    if pool_info.reserve_a == 0 && pool_info.reserve_b == 0 {
+        return Ok((amount_a, amount_b));
+    }
+    if pool_info.reserve_a <= 0 || pool_info.reserve_b <= 0 {
+        return Err(VaultError::InsufficientLiquidity);
+    }
+
+    let amount_b_optimal = amount_a
+        .checked_mul(pool_info.reserve_b)
+        .map(|v| v / pool_info.reserve_a)
+        .ok_or(VaultError::InvalidAmount)?;
+    if amount_b_optimal <= amount_b {
+        return Ok((amount_a, amount_b_optimal));
+    }
+
+    let amount_a_optimal = amount_b
+        .checked_mul(pool_info.reserve_a)
+        .map(|v| v / pool_info.reserve_b)
+        .ok_or(VaultError::InvalidAmount)?;
+    Ok((amount_a_optimal, amount_b))
+}
+
+/// LP tokens add_liquidity_to_pool would mint for (amount_a, amount_b), without executing
+/// The desired amounts are first trimmed to the pool's reserve ratio, then minted one LP
+/// token per unit actually deposited, as the pool does
+pub fn preview_add_liquidity(
+    env: &Env,
+    pool_address: &Address,
+    amount_a: i128,
+    amount_b: i128,
+) -> Result<i128, crate::errors::VaultError> {
+    use crate::errors::VaultError;
+    
+    if amount_a <= 0 || amount_b <= 0 {
+        return Err(VaultError::InvalidAmount);
+    }
+
+    let pool_client = LiquidityPoolClient::new(env, pool_address);
+    let pool_info = match pool_client.try_get_pool_info() {
+        Ok(Ok(info)) => info,
+        _ => return Err(VaultError::PoolNotFound),
+    };
+    let (used_a, used_b) = optimal_amounts(&pool_info, amount_a, amount_b)?;
+    
+    // Minting must not push total LP past i128
+    let lp_tokens = used_a
+        .checked_add(used_b)
+        .ok_or(VaultError::InvalidAmount)?;
+    pool_info.total_lp
+        .checked_add(lp_tokens)
+        .ok_or(VaultError::InvalidAmount)?;
+    
+    Ok(lp_tokens)
+}
+
 /// Add liquidity to a liquidity pool
 /// This adds both tokens to the pool and receives LP tokens
 #[allow(dead_code)]
@@ -70,15 +173,13 @@ pub fn add_liquidity_to_pool(
     let vault_address = env.current_contract_address();
     
     // Calculate minimum amounts based on slippage tolerance
-    let amount_a_min = amount_a
-        .checked_mul(100 - slippage_percent)
-        .and_then(|v| v.checked_div(100))
-        .ok_or(VaultError::InvalidAmount)?;
-    
-    let amount_b_min = amount_b
-        .checked_mul(100 - slippage_percent)
-        .and_then(|v| v.checked_div(100))
-        .ok_or(VaultError::InvalidAmount)?;
+    // Minimums apply to what the pool will take after trimming to its ratio, not the desired amounts
+    let pool_info = match pool_client.try_get_pool_info() {
+        Ok(Ok(info)) => info,
+        _ => return Err(VaultError::PoolNotFound),
+    };
+    let (expected_a, expected_b) = optimal_amounts(&pool_info, amount_a, amount_b)?;
+    let (amount_a_min, amount_b_min) = compute_min_amounts(expected_a, expected_b, slippage_percent)?;
     
     // Set deadline to 1 hour from now
     let deadline = env.ledger().timestamp() + 3600;
@@ -105,14 +206,13 @@ pub fn add_liquidity_to_pool(
 
 /// Remove liquidity from a liquidity pool
 /// This burns LP tokens and receives both tokens back
-#[allow(dead_code)]
 pub fn remove_liquidity_from_pool(
     env: &Env,
     pool_address: &Address,
     token_a: &Address,
     token_b: &Address,
     lp_tokens: i128,
-    slippage_percent: i128,
+    slippage_bps: i128, // e.g., 50 for 0.5% slippage
 ) -> Result<(i128, i128), crate::errors::VaultError> {
     use crate::errors::VaultError;
     
@@ -120,7 +220,7 @@ pub fn remove_liquidity_from_pool(
         return Err(VaultError::InvalidAmount);
     }
     
-    if slippage_percent < 0 || slippage_percent > 100 {
+    if !(0..=10_000).contains(&slippage_bps) {
         return Err(VaultError::InvalidConfiguration);
     }
 
@@ -128,9 +228,30 @@ pub fn remove_liquidity_from_pool(
     let vault_address = env.current_contract_address();
     
     // Calculate minimum amounts based on slippage tolerance
-    // Using 0 for maximum flexibility
-    let amount_a_min = 0;
-    let amount_b_min = 0;
+    // Expected payout is our LP share of current reserves; a sandwiched pool
+    // that would underdeliver makes the pool revert instead of paying out less
+    let pool_info = match pool_client.try_get_pool_info() {
+        Ok(Ok(info)) => info,
+        _ => return Err(VaultError::PoolNotFound),
+    };
+    
+    if pool_info.total_lp <= 0 {
+        return Err(VaultError::InsufficientLiquidity);
+    }
+    
+    let amount_a_min = lp_tokens
+        .checked_mul(pool_info.reserve_a)
+        .and_then(|v| v.checked_div(pool_info.total_lp))
+        .and_then(|v| v.checked_mul(10_000 - slippage_bps))
+        .and_then(|v| v.checked_div(10_000))
+        .ok_or(VaultError::InvalidAmount)?;
+    
+    let amount_b_min = lp_tokens
+        .checked_mul(pool_info.reserve_b)
+        .and_then(|v| v.checked_div(pool_info.total_lp))
+        .and_then(|v| v.checked_mul(10_000 - slippage_bps))
+        .and_then(|v| v.checked_div(10_000))
+        .ok_or(VaultError::InvalidAmount)?;
     
     // Set deadline to 1 hour from now
     let deadline = env.ledger().timestamp() + 3600;
@@ -150,6 +271,11 @@ pub fn remove_liquidity_from_pool(
         return Err(VaultError::InvalidAmount);
     }
     
+    // Don't rely on every pool enforcing the minimums itself
+    if amount_a < amount_a_min || amount_b < amount_b_min {
+        return Err(VaultError::SlippageTooHigh);
+    }
+    
     Ok((amount_a, amount_b))
 }
 
@@ -0,0 +1,27 @@
+// External price oracle interface
+// Lets a vault value assets independently of (manipulable) thin pool reserves
+use soroban_sdk::{contractclient, Address, Env};
+
+use crate::pool_client::PRICE_SCALE;
+
+/// Price oracle interface
+#[contractclient(name = "PriceOracleClient")]
+#[allow(dead_code)]
+pub trait PriceOracleInterface {
+    /// Price of one unit of asset in quote terms, scaled by 1e7 (1.0 = 10_000_000)
+    fn price(env: Env, asset: Address, quote: Address) -> i128;
+}
+
+/// Ask the oracle for asset's price in quote, scaled by PRICE_SCALE
+/// Returns None if the oracle can't be queried or has no positive price for the pair
+pub fn get_price(env: &Env, oracle: &Address, asset: &Address, quote: &Address) -> Option<i128> {
+    if asset == quote {
+        return Some(PRICE_SCALE);
+    }
+
+    let client = PriceOracleClient::new(env, oracle);
+    match client.try_price(asset, quote) {
+        Ok(Ok(price)) if price > 0 => Some(price),
+        _ => None,
+    }
+}
@@ -0,0 +1,27 @@
+// Phoenix Multihop interface for swap execution
+use soroban_sdk::{contractclient, contracttype, Address, Env, Vec};
+
+/// One hop of a Phoenix multihop swap: sell offer_asset for ask_asset
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PhoenixSwap {
+    pub offer_asset: Address,
+    pub ask_asset: Address,
+}
+
+/// Phoenix Multihop interface
+/// Unlike Soroswap, hops are explicit operations and the call returns nothing;
+/// the output has to be read from the recipient's balance
+#[contractclient(name = "PhoenixMultihopClient")]
+#[allow(dead_code)]
+pub trait PhoenixMultihopInterface {
+    /// Swap `amount` of the first offer_asset through every operation in order,
+    /// sending the final ask_asset to recipient
+    fn swap(
+        env: Env,
+        recipient: Address,
+        operations: Vec<PhoenixSwap>,
+        max_spread_bps: Option<i64>,
+        amount: i128,
+    );
+}
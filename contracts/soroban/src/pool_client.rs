@@ -1,6 +1,6 @@
 // Direct liquidity pool interface for token swaps
 // This bypasses the router and interacts directly with liquidity pools
-use soroban_sdk::{contractclient, Address, Env};
+use soroban_sdk::{contractclient, Address, Env, Vec};
 
 /// Soroswap Liquidity Pool interface
 /// Based on Uniswap V2 Pair interface
@@ -200,59 +200,147 @@ pub fn calculate_swap_output(
     Ok(amount_out)
 }
 
-/// Calculate required input for a desired output from a swap
-/// This uses the constant product formula solved for amount_in
-pub fn calculate_swap_input(
+/// Get the input required to receive exactly `amount_out` of `token_out` from a pool
+/// Inverse of the constant product output formula, including the 0.3% fee
+pub fn get_amount_in(
     env: &Env,
     pool_address: &Address,
-    from_token: &Address,
-    _to_token: &Address,
-    amount_out_desired: i128,
+    token_out: &Address,
+    amount_out: i128,
 ) -> Result<i128, crate::errors::VaultError> {
     use crate::errors::VaultError;
     
-    if amount_out_desired <= 0 {
+    if amount_out <= 0 {
         return Err(VaultError::InvalidAmount);
     }
 
     let pool_client = LiquidityPoolClient::new(env, pool_address);
     
-    // Get pool token addresses to determine which is token0 and token1
     let token0 = pool_client.token_0();
     let token1 = pool_client.token_1();
     
-    // Determine which token we're swapping from
-    let is_token0_in = if from_token == &token0 {
+    // Determine which side of the pool we're buying
+    let is_token0_out = if token_out == &token0 {
         true
-    } else if from_token == &token1 {
+    } else if token_out == &token1 {
         false
     } else {
         return Err(VaultError::InvalidConfiguration);
     };
     
-    // Get current reserves
     let (reserve0, reserve1) = pool_client.get_reserves();
     
-    // Calculate input amount using constant product formula (solved for amount_in)
-    // Formula: amount_in = (reserve_in * amount_out * 1000) / ((reserve_out - amount_out) * 997) + 1
-    let (reserve_in, reserve_out) = if is_token0_in {
-        (reserve0, reserve1)
-    } else {
+    let (reserve_in, reserve_out) = if is_token0_out {
         (reserve1, reserve0)
+    } else {
+        (reserve0, reserve1)
     };
     
+    amount_in_for_output(amount_out, reserve_in, reserve_out)
+}
+
+/// Get a pool's reserves ordered as (reserve of token_a, reserve of token_b)
+/// Works for any pool exposing token_0/token_1/get_reserves, whatever its internal order
+pub fn get_reserves_for_pair(
+    env: &Env,
+    pool_address: &Address,
+    token_a: &Address,
+    token_b: &Address,
+) -> Result<(i128, i128), crate::errors::VaultError> {
+    use crate::errors::VaultError;
+    
+    let pool_client = LiquidityPoolClient::new(env, pool_address);
+    
+    let token0 = match pool_client.try_token_0() {
+        Ok(Ok(addr)) => addr,
+        _ => return Err(VaultError::PoolNotFound),
+    };
+    let token1 = match pool_client.try_token_1() {
+        Ok(Ok(addr)) => addr,
+        _ => return Err(VaultError::PoolNotFound),
+    };
+    let (reserve0, reserve1) = match pool_client.try_get_reserves() {
+        Ok(Ok(reserves)) => reserves,
+        _ => return Err(VaultError::PoolNotFound),
+    };
+    
+    if token_a == &token0 && token_b == &token1 {
+        Ok((reserve0, reserve1))
+    } else if token_a == &token1 && token_b == &token0 {
+        Ok((reserve1, reserve0))
+    } else {
+        Err(VaultError::InvalidConfiguration)
+    }
+}
+
+/// Fixed-point scale for spot prices (1.0 = 10_000_000)
+pub const PRICE_SCALE: i128 = 10_000_000;
+
+/// Spot price of each pool's non-quote token in quote_token terms, scaled by PRICE_SCALE
+/// Results follow the order of `pools`; pools without quote_token (or that can't be read) yield 0
+pub fn get_spot_prices(
+    env: &Env,
+    pools: &Vec<Address>,
+    quote_token: &Address,
+) -> Vec<i128> {
+    let mut prices: Vec<i128> = Vec::new(env);
+    
+    for pool_address in pools.iter() {
+        let pool_client = LiquidityPoolClient::new(env, &pool_address);
+        
+        let price = match (
+            pool_client.try_token_0(),
+            pool_client.try_token_1(),
+            pool_client.try_get_reserves(),
+        ) {
+            (Ok(Ok(token0)), Ok(Ok(token1)), Ok(Ok((reserve0, reserve1)))) => {
+                // price = reserve of quote / reserve of the other token
+                let (reserve_base, reserve_quote) = if &token1 == quote_token {
+                    (reserve0, reserve1)
+                } else if &token0 == quote_token {
+                    (reserve1, reserve0)
+                } else {
+                    (0, 0)
+                };
+                if reserve_base > 0 {
+                    reserve_quote
+                        .checked_mul(PRICE_SCALE)
+                        .and_then(|v| v.checked_div(reserve_base))
+                        .unwrap_or(0)
+                } else {
+                    0
+                }
+            },
+            _ => 0,
+        };
+        
+        prices.push_back(price);
+    }
+    
+    prices
+}
+
+/// Constant product formula solved for amount_in
+/// Formula: amount_in = (reserve_in * amount_out * 1000) / ((reserve_out - amount_out) * 997) + 1
+fn amount_in_for_output(
+    amount_out: i128,
+    reserve_in: i128,
+    reserve_out: i128,
+) -> Result<i128, crate::errors::VaultError> {
+    use crate::errors::VaultError;
+    
     // Make sure we're not trying to drain the pool
-    if amount_out_desired >= reserve_out {
+    if amount_out >= reserve_out {
         return Err(VaultError::InvalidAmount);
     }
     
     let numerator = reserve_in
-        .checked_mul(amount_out_desired)
+        .checked_mul(amount_out)
         .and_then(|v| v.checked_mul(1000))
         .ok_or(VaultError::InvalidAmount)?;
     
     let denominator = reserve_out
-        .checked_sub(amount_out_desired)
+        .checked_sub(amount_out)
         .and_then(|v| v.checked_mul(997))
         .ok_or(VaultError::InvalidAmount)?;
     
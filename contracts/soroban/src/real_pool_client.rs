@@ -5,6 +5,9 @@ use soroban_sdk::{contractclient, Address, Env, Symbol, symbol_short};
 // Storage key for custom token pool mappings
 const CUSTOM_POOL: Symbol = symbol_short!("CUST_POOL");
 
+/// Fee charged by pools without fee tiers (0.3%)
+const DEFAULT_FEE_BPS: i128 = 30;
+
 /// Real Liquidity Pool interface
 /// Our custom AMM pool with simplified interface
 #[contractclient(name = "RealPoolClient")]
@@ -32,6 +35,19 @@ pub trait RealPoolInterface {
     
     /// Get token 1 address
     fn token_1(env: Env) -> Address;
+    
+    /// Get both token addresses in one call
+    /// Returns (token_0, token_1)
+    fn tokens(env: Env) -> (Address, Address);
+    
+    /// Whether the pool has been initialized with a token pair
+    fn is_initialized(env: Env) -> bool;
+    
+    /// Whether swaps are currently halted
+    fn is_paused(env: Env) -> bool;
+    
+    /// Fee (bps) the pool charges a swap of amount_in
+    fn fee_bps_for(env: Env, amount_in: i128) -> i128;
 }
 
 /// Execute a swap through our real liquidity pool
@@ -57,9 +73,22 @@ pub fn swap_via_real_pool(
     let pool_client = RealPoolClient::new(env, pool_address);
     let vault_address = env.current_contract_address();
     
+    // Probe pool health before any transfer so funds can't be stranded in a dead pool
+    match pool_client.try_is_initialized() {
+        Ok(Ok(true)) => {},
+        _ => {
+            log!(env, "Pool is not initialized");
+            return Err(VaultError::PoolNotFound);
+        }
+    }
+    // Pools predating pausing don't expose is_paused and are treated as live
+    if let Ok(Ok(true)) = pool_client.try_is_paused() {
+        log!(env, "Pool is paused");
+        return Err(VaultError::PoolPaused);
+    }
+    
     // Get pool token addresses to verify this is the correct pool
-    let token_a = pool_client.token_0();
-    let token_b = pool_client.token_1();
+    let (token_a, token_b) = pool_client.tokens();
     
     // Verify tokens match
     if (from_token != &token_a && from_token != &token_b) || 
@@ -120,8 +149,18 @@ pub fn swap_via_real_pool(
     Ok(amount_out)
 }
 
+/// Fee (bps) a custom pool charges a swap of amount_in
+/// Pools predating fee tiers don't expose fee_bps_for and charge the flat 0.3%
+pub fn pool_fee_bps(env: &Env, pool_address: &Address, amount_in: i128) -> i128 {
+    let pool_client = RealPoolClient::new(env, pool_address);
+    match pool_client.try_fee_bps_for(&amount_in) {
+        Ok(Ok(fee_bps)) if (0..10_000).contains(&fee_bps) => fee_bps,
+        _ => DEFAULT_FEE_BPS,
+    }
+}
+
 /// Calculate expected output for a swap without executing it
-/// This uses the constant product formula (x * y = k) with 0.3% fee
+/// This uses the constant product formula (x * y = k) with the pool's fee tier for this size
 pub fn calculate_real_pool_output(
     env: &Env,
     pool_address: &Address,
@@ -138,8 +177,7 @@ pub fn calculate_real_pool_output(
     let pool_client = RealPoolClient::new(env, pool_address);
     
     // Get pool token addresses to determine which is token_a and token_b
-    let token_a = pool_client.token_0();
-    let token_b = pool_client.token_1();
+    let (token_a, token_b) = pool_client.tokens();
     
     // Determine which token we're swapping from
     let is_token_a_in = if from_token == &token_a {
@@ -154,15 +192,21 @@ pub fn calculate_real_pool_output(
     let (reserve_a, reserve_b) = pool_client.get_reserves();
     
     // Calculate output amount using constant product formula
-    // Formula: amount_out = (amount_in * 997 * reserve_out) / (reserve_in * 1000 + amount_in * 997)
+    // Formula: amount_out = (amount_in * (10000 - fee) * reserve_out) / (reserve_in * 10000 + amount_in * (10000 - fee))
     let (reserve_in, reserve_out) = if is_token_a_in {
         (reserve_a, reserve_b)
     } else {
         (reserve_b, reserve_a)
     };
-    
+
+    // An empty pool has no price - refuse to quote rather than return a bogus amount
+    if reserve_in <= 0 || reserve_out <= 0 {
+        return Err(VaultError::InsufficientLiquidity);
+    }
+
+    let fee_bps = pool_fee_bps(env, pool_address, amount_in);
     let amount_in_with_fee = amount_in
-        .checked_mul(997)
+        .checked_mul(10_000 - fee_bps)
         .ok_or(VaultError::InvalidAmount)?;
     
     let numerator = amount_in_with_fee
@@ -170,7 +214,7 @@ pub fn calculate_real_pool_output(
         .ok_or(VaultError::InvalidAmount)?;
     
     let denominator = reserve_in
-        .checked_mul(1000)
+        .checked_mul(10_000)
         .and_then(|v| v.checked_add(amount_in_with_fee))
         .ok_or(VaultError::InvalidAmount)?;
     
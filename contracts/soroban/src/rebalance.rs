@@ -4,6 +4,38 @@ use crate::errors::VaultError;
 
 const CONFIG: Symbol = symbol_short!("CONFIG");
 const STATE: Symbol = symbol_short!("STATE");
+const PAUSED: Symbol = symbol_short!("PAUSED");
+
+/// Balances below this (stroops) are too small to rebalance and count as dust
+const DUST_THRESHOLD: i128 = 1000;
+
+/// Whether the owner has excluded this asset from rebalancing (neither bought nor sold)
+pub fn is_asset_paused(env: &Env, asset: &Address) -> bool {
+    env.storage().instance()
+        .get::<_, Vec<Address>>(&PAUSED)
+        .is_some_and(|paused| paused.contains(asset))
+}
+
+/// Check a target allocation has one entry per asset, each within 0..=100%, summing to
+/// exactly 100% (100_0000); an all-zero allocation is rejected like any other bad sum
+pub fn validate_allocation(target_allocation: &Vec<i128>, len: u32) -> Result<(), VaultError> {
+    if target_allocation.len() != len {
+        return Err(VaultError::InvalidConfiguration);
+    }
+
+    let mut total_allocation: i128 = 0;
+    for alloc in target_allocation.iter() {
+        if !(0..=100_0000).contains(&alloc) {
+            return Err(VaultError::InvalidConfiguration);
+        }
+        total_allocation += alloc;
+    }
+
+    if total_allocation != 100_0000 {
+        return Err(VaultError::InvalidConfiguration);
+    }
+    Ok(())
+}
 
 /// Execute rebalancing of vault assets according to rules
 #[allow(dead_code)]
@@ -267,37 +299,12 @@ fn execute_rebalance_action(
         (assets.len(), total_value)
     );
     
-    // Validate target allocation matches number of assets
-    if rule.target_allocation.len() != assets.len() {
+    if let Err(err) = validate_allocation(&rule.target_allocation, assets.len()) {
         env.events().publish(
-            (symbol_short!("err_len"),),
+            (symbol_short!("bad_alloc"),),
             (rule.target_allocation.len(), assets.len())
         );
-        return Err(VaultError::InvalidConfiguration);
-    }
-    
-    // Validate allocations sum to 100% (represented as 100_0000 for 2 decimal precision)
-    let mut total_allocation: i128 = 0;
-    for i in 0..rule.target_allocation.len() {
-        if let Some(alloc) = rule.target_allocation.get(i) {
-            total_allocation = total_allocation.checked_add(alloc)
-                .ok_or(VaultError::InvalidConfiguration)?;
-        }
-    }
-    
-    // Log total allocation for debugging
-    env.events().publish(
-        (symbol_short!("tot_alloc"),),
-        total_allocation
-    );
-    
-    // Allow 100% allocation (100_0000 in our precision)
-    if total_allocation != 100_0000 && total_allocation != 0 {
-        env.events().publish(
-            (symbol_short!("bad_sum"),),
-            total_allocation
-        );
-        return Err(VaultError::InvalidConfiguration);
+        return Err(err);
     }
 
     // Get router address from config
@@ -380,6 +387,11 @@ fn execute_rebalance_action(
                 continue;
             }
             
+            // Paused assets are left exactly as they are
+            if is_asset_paused(env, &asset) {
+                continue;
+            }
+            
             if diff > 0 {
                 // Need to buy more of this asset
                 // Log what we're trying to buy
@@ -388,12 +400,17 @@ fn execute_rebalance_action(
                     (asset.clone(), diff)
                 );
                 
-                // Find an asset we have excess of to sell
-                for j in 0..assets.len() {
-                    if i == j {
-                        continue;
-                    }
-                    
+                // Find an asset we have excess of to sell, tried in the configured strategy's order
+                let source_order = order_rebalance_sources(
+                    env,
+                    config.source_strategy,
+                    assets,
+                    &current_balances,
+                    &target_amounts,
+                    i,
+                    diff,
+                );
+                for j in source_order.iter() {
                     if let (Some(source_asset), Some(source_current), Some(source_target)) = (
                         assets.get(j),
                         current_balances.get(j),
@@ -411,9 +428,7 @@ fn execute_rebalance_action(
                             
                             // Calculate how much of source asset we need to sell to get the target amount
                             // We want to buy 'diff' amount of target asset
-                            // Due to AMM mechanics, we need to estimate the input amount
-                            // For now, use a simple approximation: we need roughly 'diff' worth of source asset
-                            // In reality, this should use the pool's price ratio
+                            // Due to AMM mechanics, the input is derived from the pool's reserves
                             
                             // Get the factory address to find the pool for price calculation
                             let factory_address = crate::swap_router::get_soroswap_factory_address_internal(env);
@@ -436,10 +451,9 @@ fn execute_rebalance_action(
                             };
                             
                             // Calculate how much source asset we need to sell to get 'diff' of target asset
-                            let amount_to_swap = match crate::pool_client::calculate_swap_input(
+                            let amount_to_swap = match crate::pool_client::get_amount_in(
                                 env,
                                 &pool_address,
-                                &source_asset,
                                 &asset,
                                 diff, // How much we want to receive
                             ) {
@@ -517,7 +531,7 @@ fn execute_rebalance_action(
                             
                             // Execute swap through router
                             // Note: If this fails, the entire transaction will fail
-                            let amount_out = match crate::swap_router::swap_via_router(
+                            let amount_out = match swap_with_pool_fallback(
                                 env,
                                 &router_address,
                                 &source_asset,
@@ -613,6 +627,7 @@ fn execute_stake_action(
         staked_amount: stake_amount,
         st_token_amount: st_tokens_received,
         timestamp: env.ledger().timestamp(),
+        current_value: stake_amount,
     };
     
     // Save position to storage
@@ -743,7 +758,7 @@ fn swap_tokens(
     )?;
     
     // Execute swap through Soroswap/Phoenix router
-    let amount_out = crate::swap_router::swap_via_router(
+    let amount_out = swap_with_pool_fallback(
         env,
         &router_address,
         from_token,
@@ -801,32 +816,16 @@ fn execute_amm_swap(
 
 /// Force rebalance vault assets to target allocation (used by force_rebalance)
 /// This bypasses rule checks and immediately rebalances to target percentages
+/// The outcome tells keepers whether a follow-up call is needed
 pub fn force_rebalance_to_allocation(
     env: &Env,
     assets: &Vec<Address>,
     target_allocation: &Vec<i128>,
     total_value: i128,
-) -> Result<(), VaultError> {
+) -> Result<crate::types::RebalanceOutcome, VaultError> {
     use soroban_sdk::symbol_short;
     
-    // Validate target allocation matches number of assets
-    if target_allocation.len() != assets.len() {
-        return Err(VaultError::InvalidConfiguration);
-    }
-    
-    // Validate allocations sum to 100% (represented as 100_0000 for 2 decimal precision)
-    let mut total_allocation: i128 = 0;
-    for i in 0..target_allocation.len() {
-        if let Some(alloc) = target_allocation.get(i) {
-            total_allocation = total_allocation.checked_add(alloc)
-                .ok_or(VaultError::InvalidConfiguration)?;
-        }
-    }
-    
-    // Allow 100% allocation (100_0000 in our precision)
-    if total_allocation != 100_0000 && total_allocation != 0 {
-        return Err(VaultError::InvalidConfiguration);
-    }
+    validate_allocation(target_allocation, assets.len())?;
     
     env.events().publish(
         (symbol_short!("force_reb"),),
@@ -876,6 +875,7 @@ pub fn force_rebalance_to_allocation(
     // Limit to maximum 3 swaps per rebalance to avoid budget exceeded
     let max_swaps = 3u32;
     let mut swap_count = 0u32;
+    let mut hit_budget_cap = false;
     
     for i in 0..assets.len() {
         if swap_count >= max_swaps {
@@ -883,6 +883,7 @@ pub fn force_rebalance_to_allocation(
                 (symbol_short!("max_swap"),),
                 swap_count
             );
+            hit_budget_cap = true;
             break; // Stop after max swaps to conserve budget
         }
         
@@ -894,7 +895,7 @@ pub fn force_rebalance_to_allocation(
             let diff = target - current;
             
             // Skip if difference is negligible (increased threshold to reduce swaps)
-            if diff.abs() < 1000 {
+            if diff.abs() < DUST_THRESHOLD {
                 continue;
             }
             
@@ -918,7 +919,7 @@ pub fn force_rebalance_to_allocation(
                         current_balances.get(j),
                         target_amounts.get(j)
                     ) {
-                        if source_current > source_target + 1000 { // Add threshold check
+                        if source_current > source_target + DUST_THRESHOLD {
                             // This asset has excess, use it as source
                             let excess = source_current - source_target;
                             
@@ -947,7 +948,7 @@ pub fn force_rebalance_to_allocation(
                                 }
                             };
                             
-                            let _pool_address = match pool_address {
+                            let pool_address = match pool_address {
                                 Some(addr) => addr,
                                 None => {
                                     // No custom pool found - skip this pair to conserve budget
@@ -959,13 +960,18 @@ pub fn force_rebalance_to_allocation(
                                 }
                             };
                             
-                            // Use simplified swap amount calculation (no get_reserves calls)
-                            // Assume we need roughly same amount of source as target (1:1 ratio estimate)
-                            // This avoids expensive pool queries
-                            let amount_to_swap = if diff > excess {
+                            // Size the swap from pool reserves: input needed to receive 'diff' of target
+                            // Falls back to selling the whole excess if the pool can't cover 'diff'
+                            let amount_needed = crate::pool_client::get_amount_in(
+                                env,
+                                &pool_address,
+                                &asset,
+                                diff,
+                            ).unwrap_or(excess);
+                            let amount_to_swap = if amount_needed > excess {
                                 excess
                             } else {
-                                diff
+                                amount_needed
                             };
                             
                             env.events().publish(
@@ -974,7 +980,7 @@ pub fn force_rebalance_to_allocation(
                             );
                             
                             // Skip if amount is negligible
-                            if amount_to_swap < 1000 {
+                            if amount_to_swap < DUST_THRESHOLD {
                                 env.events().publish(
                                     (symbol_short!("skip_amt"),),
                                     amount_to_swap
@@ -1004,7 +1010,7 @@ pub fn force_rebalance_to_allocation(
                             );
                             
                             // Execute swap through router
-                            let amount_out = match crate::swap_router::swap_via_router(
+                            let amount_out = match swap_with_pool_fallback(
                                 env,
                                 &router_address,
                                 &source_asset,
@@ -1042,12 +1048,59 @@ pub fn force_rebalance_to_allocation(
         }
     }
     
+    // Converged when every tracked balance sits within the same threshold used to skip swaps
+    let mut converged = true;
+    for i in 0..assets.len() {
+        if let (Some(current), Some(target)) = (current_balances.get(i), target_amounts.get(i)) {
+            if (target - current).abs() >= DUST_THRESHOLD {
+                converged = false;
+                break;
+            }
+        }
+    }
+    
     env.events().publish(
         (symbol_short!("swap_done"),),
-        swap_count
+        (swap_count, hit_budget_cap, converged)
     );
     
-    Ok(())
+    // Optionally hand leftover dust to the fee recipient as protocol revenue
+    if config.sweep_dust {
+        if let Some(recipient) = config.fee_recipient.clone() {
+            sweep_dust(env, assets, &recipient)?;
+        }
+    }
+    
+    Ok(crate::types::RebalanceOutcome {
+        swaps_executed: swap_count,
+        hit_budget_cap,
+        converged,
+    })
+}
+
+/// Send every non-zero balance below DUST_THRESHOLD to `recipient`
+/// Paused assets are left alone; returns the number of assets swept
+fn sweep_dust(env: &Env, assets: &Vec<Address>, recipient: &Address) -> Result<u32, VaultError> {
+    let vault_address = env.current_contract_address();
+    let mut swept = 0u32;
+    
+    for asset in assets.iter() {
+        if is_asset_paused(env, &asset) {
+            continue;
+        }
+        
+        let balance = crate::token_client::get_vault_balance(env, &asset);
+        if balance > 0 && balance < DUST_THRESHOLD {
+            crate::token_client::transfer_tokens(env, &asset, &vault_address, recipient, balance)?;
+            env.events().publish(
+                (symbol_short!("dust"), recipient.clone()),
+                (asset.clone(), balance)
+            );
+            swept += 1;
+        }
+    }
+    
+    Ok(swept)
 }
 
 /// Calculate a rebalance plan without executing it
@@ -1061,23 +1114,7 @@ pub fn calculate_rebalance_plan(
 ) -> Result<crate::types::RebalancePlan, VaultError> {
     use soroban_sdk::symbol_short;
     
-    // Validate target allocation matches number of assets
-    if target_allocation.len() != assets.len() {
-        return Err(VaultError::InvalidConfiguration);
-    }
-    
-    // Validate allocations sum to 100%
-    let mut total_allocation: i128 = 0;
-    for i in 0..target_allocation.len() {
-        if let Some(alloc) = target_allocation.get(i) {
-            total_allocation = total_allocation.checked_add(alloc)
-                .ok_or(VaultError::InvalidConfiguration)?;
-        }
-    }
-    
-    if total_allocation != 100_0000 && total_allocation != 0 {
-        return Err(VaultError::InvalidConfiguration);
-    }
+    validate_allocation(target_allocation, assets.len())?;
     
     env.events().publish(
         (symbol_short!("calc_plan"),),
@@ -1122,7 +1159,7 @@ pub fn calculate_rebalance_plan(
         
         // We have funds in the deposit token that need to be swapped to target assets
         // Create swap steps from deposit_token to each target asset based on allocation
-        if let Some(source_token) = deposit_token {
+        if let Some(source_token) = deposit_token.filter(|token| !is_asset_paused(env, token)) {
             let mut steps: Vec<crate::types::RebalanceStep> = Vec::new(env);
             let min_swap_threshold = 1000i128;
             
@@ -1132,8 +1169,8 @@ pub fn calculate_rebalance_plan(
                     assets.get(i),
                     target_amounts.get(i)
                 ) {
-                    // Skip if target amount is negligible
-                    if target_amount < min_swap_threshold {
+                    // Skip if target amount is negligible or the asset is paused
+                    if target_amount < min_swap_threshold || is_asset_paused(env, &target_asset) {
                         continue;
                     }
                     
@@ -1177,6 +1214,7 @@ pub fn calculate_rebalance_plan(
                         amount_in: amount_to_swap,
                         min_amount_out,
                         pool_address: pool_address.clone(),
+                        path: soroban_sdk::vec![env, source_token.clone(), target_asset.clone()],
                     };
                     
                     steps.push_back(step);
@@ -1188,6 +1226,9 @@ pub fn calculate_rebalance_plan(
                 }
             }
             
+            // Deepest pools first so the biggest swaps see the least slippage
+            let steps = order_steps_by_pool_depth(env, &steps);
+
             // Return plan with deposit token swap steps
             return Ok(crate::types::RebalancePlan {
                 steps: steps.clone(),
@@ -1208,8 +1249,8 @@ pub fn calculate_rebalance_plan(
         ) {
             let diff = target - current;
             
-            // Skip if difference is negligible
-            if diff.abs() < min_swap_threshold {
+            // Skip if difference is negligible or the asset is paused
+            if diff.abs() < min_swap_threshold || is_asset_paused(env, &asset) {
                 continue;
             }
             
@@ -1231,7 +1272,9 @@ pub fn calculate_rebalance_plan(
                         current_balances.get(j),
                         target_amounts.get(j)
                     ) {
-                        if source_current > source_target + min_swap_threshold {
+                        if source_current > source_target + min_swap_threshold
+                            && !is_asset_paused(env, &source_asset)
+                        {
                             let excess = source_current - source_target;
                             
                             // Find pool for this pair
@@ -1275,6 +1318,7 @@ pub fn calculate_rebalance_plan(
                                 amount_in: amount_to_swap,
                                 min_amount_out,
                                 pool_address: pool_address.clone(),
+                                path: soroban_sdk::vec![env, source_asset.clone(), asset.clone()],
                             };
                             
                             steps.push_back(step);
@@ -1296,8 +1340,10 @@ pub fn calculate_rebalance_plan(
         }
     }
     
+    // Deepest pools first so the biggest swaps see the least slippage
+    let steps = order_steps_by_pool_depth(env, &steps);
     let total_steps = steps.len();
-    
+
     env.events().publish(
         (symbol_short!("plan_done"),),
         total_steps
@@ -1311,6 +1357,173 @@ pub fn calculate_rebalance_plan(
     Ok(plan)
 }
 
+/// Order plan steps by descending liquidity of the pool they trade through
+/// Reserves are queried once per pool; steps without a known pool sort last
+fn order_steps_by_pool_depth(
+    env: &Env,
+    steps: &Vec<crate::types::RebalanceStep>,
+) -> Vec<crate::types::RebalanceStep> {
+    use soroban_sdk::Map;
+
+    // Cache of pool -> (token_0, reserve_0, reserve_1)
+    let mut pool_reserves: Map<Address, (Address, i128, i128)> = Map::new(env);
+    let mut depths: Vec<i128> = Vec::new(env);
+
+    for i in 0..steps.len() {
+        let mut depth: i128 = 0;
+
+        if let Some(step) = steps.get(i) {
+            if !pool_reserves.contains_key(step.pool_address.clone()) {
+                if let Some(reserves) = query_pool_reserves(env, &step) {
+                    pool_reserves.set(step.pool_address.clone(), reserves);
+                }
+            }
+
+            // Depth is the reserve of the token the swap draws out of the pool
+            if let Some((token_0, reserve_0, reserve_1)) = pool_reserves.get(step.pool_address.clone()) {
+                depth = if step.to_token == token_0 { reserve_0 } else { reserve_1 };
+            }
+        }
+
+        depths.push_back(depth);
+    }
+
+    // Stable insertion sort (descending depth) - plans are only a handful of steps
+    let mut ordered: Vec<crate::types::RebalanceStep> = Vec::new(env);
+    let mut ordered_depths: Vec<i128> = Vec::new(env);
+
+    for i in 0..steps.len() {
+        if let (Some(step), Some(depth)) = (steps.get(i), depths.get(i)) {
+            let mut position = ordered_depths.len();
+            for j in 0..ordered_depths.len() {
+                if let Some(existing) = ordered_depths.get(j) {
+                    if depth > existing {
+                        position = j;
+                        break;
+                    }
+                }
+            }
+
+            ordered.insert(position, step);
+            ordered_depths.insert(position, depth);
+        }
+    }
+
+    ordered
+}
+
+/// Indices of overweight assets that could fund buying `diff` of assets[target_index],
+/// best first under the given strategy (stable, so ties keep config order)
+fn order_rebalance_sources(
+    env: &Env,
+    strategy: crate::types::SourceStrategy,
+    assets: &Vec<Address>,
+    current_balances: &Vec<i128>,
+    target_amounts: &Vec<i128>,
+    target_index: u32,
+    diff: i128,
+) -> Vec<u32> {
+    use crate::types::SourceStrategy;
+
+    let mut ordered: Vec<u32> = Vec::new(env);
+    let mut ordered_scores: Vec<i128> = Vec::new(env);
+
+    let target_asset = match assets.get(target_index) {
+        Some(asset) => asset,
+        None => return ordered,
+    };
+    let factory_address = crate::swap_router::get_soroswap_factory_address_internal(env);
+
+    for j in 0..assets.len() {
+        if j == target_index {
+            continue;
+        }
+
+        let (source_asset, source_current, source_target) = match (
+            assets.get(j),
+            current_balances.get(j),
+            target_amounts.get(j),
+        ) {
+            (Some(a), Some(c), Some(t)) => (a, c, t),
+            _ => continue,
+        };
+        if source_current <= source_target || is_asset_paused(env, &source_asset) {
+            continue;
+        }
+
+        // Higher score = better source; sources without a readable pool go last
+        let score = if strategy == SourceStrategy::FirstExcess {
+            0
+        } else {
+            let pool_and_reserves = crate::pool_client::get_pool_for_pair(env, &factory_address, &source_asset, &target_asset)
+                .ok()
+                .and_then(|pool| {
+                    crate::pool_client::get_reserves_for_pair(env, &pool, &source_asset, &target_asset)
+                        .ok()
+                        .map(|reserves| (pool, reserves))
+                });
+
+            match (strategy, pool_and_reserves) {
+                (SourceStrategy::DeepestPool, Some((_, (_, reserve_target)))) => reserve_target,
+                (SourceStrategy::LeastImpact, Some((pool, (reserve_source, _)))) => {
+                    // Impact of the input actually needed, capped at the excess like the swap itself
+                    let excess = source_current - source_target;
+                    let amount_in = crate::pool_client::get_amount_in(env, &pool, &target_asset, diff)
+                        .unwrap_or(excess)
+                        .min(excess);
+                    let impact_bps = amount_in.checked_mul(10_000)
+                        .and_then(|v| v.checked_div(reserve_source + amount_in))
+                        .unwrap_or(10_000);
+                    -impact_bps
+                },
+                _ => i128::MIN,
+            }
+        };
+
+        // Stable insertion sort (descending score) - at most MAX_ASSETS candidates
+        let mut position = ordered_scores.len();
+        for k in 0..ordered_scores.len() {
+            if let Some(existing) = ordered_scores.get(k) {
+                if score > existing {
+                    position = k;
+                    break;
+                }
+            }
+        }
+
+        ordered.insert(position, j);
+        ordered_scores.insert(position, score);
+    }
+
+    ordered
+}
+
+/// Read (token_0, reserve_0, reserve_1) for a step's pool
+/// Only registered custom pools are queried; router placeholders return None
+fn query_pool_reserves(
+    env: &Env,
+    step: &crate::types::RebalanceStep,
+) -> Option<(Address, i128, i128)> {
+    let is_custom_pool = crate::real_pool_client::get_custom_token_pool(env, &step.from_token) == Some(step.pool_address.clone())
+        || crate::real_pool_client::get_custom_token_pool(env, &step.to_token) == Some(step.pool_address.clone());
+
+    if !is_custom_pool {
+        return None;
+    }
+
+    let pool_client = crate::real_pool_client::RealPoolClient::new(env, &step.pool_address);
+
+    let token_0 = match pool_client.try_token_0() {
+        Ok(Ok(addr)) => addr,
+        _ => return None,
+    };
+
+    match pool_client.try_get_reserves() {
+        Ok(Ok((reserve_0, reserve_1))) => Some((token_0, reserve_0, reserve_1)),
+        _ => None,
+    }
+}
+
 /// Execute a single rebalance step from a plan
 /// This allows batch processing where each step is a separate transaction
 pub fn execute_rebalance_step(
@@ -1342,6 +1555,11 @@ pub fn execute_rebalance_step(
         return Err(VaultError::InsufficientBalance);
     }
     
+    // Multi-hop route: swap hop-by-hop along the explicit path
+    if step.path.len() > 2 {
+        return execute_step_along_path(env, step, &router_address);
+    }
+    
     // Approve router to spend tokens
     crate::token_client::approve_router(
         env,
@@ -1356,7 +1574,7 @@ pub fn execute_rebalance_step(
     );
     
     // Execute swap through router
-    let amount_out = crate::swap_router::swap_via_router(
+    let amount_out = swap_with_pool_fallback(
         env,
         &router_address,
         &step.from_token,
@@ -1372,3 +1590,107 @@ pub fn execute_rebalance_step(
     
     Ok(amount_out)
 }
+
+/// Execute a rebalance step along its explicit path (e.g. A -> XLM -> B)
+/// Intermediate hops take any output; the step's min_amount_out applies to the final hop
+fn execute_step_along_path(
+    env: &Env,
+    step: &crate::types::RebalanceStep,
+    router_address: &Address,
+) -> Result<i128, VaultError> {
+    use soroban_sdk::symbol_short;
+    
+    // Path must start and end on the step's tokens
+    if step.path.first() != Some(step.from_token.clone())
+        || step.path.last() != Some(step.to_token.clone())
+    {
+        return Err(VaultError::InvalidConfiguration);
+    }
+    
+    let last_hop = step.path.len() - 2;
+    let mut amount = step.amount_in;
+    for i in 0..=last_hop {
+        let hop_from = step.path.get(i).ok_or(VaultError::InvalidConfiguration)?;
+        let hop_to = step.path.get(i + 1).ok_or(VaultError::InvalidConfiguration)?;
+        let hop_min_out = if i == last_hop { step.min_amount_out } else { 0 };
+        
+        crate::token_client::approve_router(env, &hop_from, router_address, amount)?;
+        
+        amount = swap_with_pool_fallback(
+            env,
+            router_address,
+            &hop_from,
+            &hop_to,
+            amount,
+            hop_min_out,
+        )?;
+    }
+    
+    env.events().publish(
+        (symbol_short!("step_done"),),
+        amount
+    );
+    
+    Ok(amount)
+}
+
+/// Swap through the router first; if it can't route the pair, fall back to a direct
+/// pool found via the factory. Emits which path filled the swap
+fn swap_with_pool_fallback(
+    env: &Env,
+    router_address: &Address,
+    from_token: &Address,
+    to_token: &Address,
+    amount_in: i128,
+    min_amount_out: i128,
+) -> Result<i128, VaultError> {
+    use soroban_sdk::symbol_short;
+    
+    let router_err = match crate::swap_router::swap_via_router(
+        env,
+        router_address,
+        from_token,
+        to_token,
+        amount_in,
+        min_amount_out,
+    ) {
+        Ok(amount_out) => {
+            env.events().publish(
+                (symbol_short!("swap_path"),),
+                (symbol_short!("router"), from_token.clone(), to_token.clone())
+            );
+            return Ok(amount_out);
+        }
+        Err(e) => e,
+    };
+    
+    // Router has no route: look for a direct pool
+    let config: crate::types::VaultConfig = env.storage().instance()
+        .get(&CONFIG)
+        .ok_or(VaultError::NotInitialized)?;
+    let factory_address = match config.factory_address {
+        Some(addr) => addr,
+        None => crate::swap_router::get_soroswap_factory_address_internal(env),
+    };
+    let pool_address = match crate::pool_client::get_pool_for_pair(env, &factory_address, from_token, to_token) {
+        Ok(addr) => addr,
+        // No direct pool either: report the router's failure
+        Err(_) => return Err(router_err),
+    };
+    
+    let amount_out = crate::pool_client::swap_via_pool(
+        env,
+        &pool_address,
+        from_token,
+        to_token,
+        amount_in,
+        min_amount_out,
+    )?;
+    
+    env.events().publish(
+        (symbol_short!("swap_path"),),
+        (symbol_short!("pool"), from_token.clone(), to_token.clone())
+    );
+    
+    Ok(amount_out)
+}
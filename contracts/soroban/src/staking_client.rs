@@ -29,6 +29,41 @@ pub trait StakingPoolInterface {
     
     /// Get total staked amount for an address
     fn get_staked_balance(env: Env, user: Address) -> i128;
+    
+    /// Pay out rewards accrued to sender
+    /// Returns the amount of reward tokens transferred
+    fn claim_rewards(
+        env: Env,
+        sender: Address,
+    ) -> i128;
+    
+    /// Get the token rewards are paid in
+    fn get_reward_token(env: Env) -> Address;
+}
+
+/// Fixed-point scale for exchange rates (1.0 = 10_000_000)
+pub const RATE_SCALE: i128 = 10_000_000;
+
+/// Get the pool's redemption rate: underlying tokens per staking token, scaled by RATE_SCALE
+/// Falls back to 1:1 if the pool can't be queried
+pub fn get_exchange_rate(env: &Env, pool_address: &Address) -> i128 {
+    let pool_client = StakingPoolClient::new(env, pool_address);
+    match pool_client.try_get_exchange_rate() {
+        Ok(Ok((underlying, st_tokens))) if underlying > 0 && st_tokens > 0 => underlying
+            .checked_mul(RATE_SCALE)
+            .and_then(|v| v.checked_div(st_tokens))
+            .unwrap_or(RATE_SCALE),
+        _ => RATE_SCALE,
+    }
+}
+
+/// Value of st_tokens in the underlying token at the pool's live rate
+pub fn current_staking_value(env: &Env, pool_address: &Address, st_tokens: i128) -> i128 {
+    let rate = get_exchange_rate(env, pool_address);
+    st_tokens
+        .checked_mul(rate)
+        .and_then(|v| v.checked_div(RATE_SCALE))
+        .unwrap_or(0)
 }
 
 /// Stake tokens through a liquid staking pool
@@ -102,6 +137,35 @@ pub fn unstake_tokens(
     Ok(tokens_received)
 }
 
+/// Claim rewards accrued to the vault's staking position
+/// Returns Some((reward_token, amount claimed)), or None for pools without reward support
+pub fn claim_staking_rewards(
+    env: &Env,
+    pool_address: &Address,
+) -> Result<Option<(Address, i128)>, crate::errors::VaultError> {
+    use crate::errors::VaultError;
+    
+    let pool_client = StakingPoolClient::new(env, pool_address);
+    let vault_address = env.current_contract_address();
+    
+    let reward_token = match pool_client.try_get_reward_token() {
+        Ok(Ok(token)) => token,
+        _ => return Ok(None),
+    };
+    
+    // A pool that names a reward token but can't pay it out is broken, not reward-less
+    let claimed = match pool_client.try_claim_rewards(&vault_address) {
+        Ok(Ok(amount)) => amount,
+        _ => return Err(VaultError::TransferFailed),
+    };
+    
+    if claimed < 0 {
+        return Err(VaultError::InvalidAmount);
+    }
+    
+    Ok(Some((reward_token, claimed)))
+}
+
 /// Get the current staking exchange rate
 /// Returns (base_amount, st_token_amount) ratio
 #[allow(dead_code)]
@@ -2,6 +2,8 @@
 use soroban_sdk::{Address, Env, Vec, BytesN};
 use crate::errors::VaultError;
 use crate::soroswap_router::SoroswapRouterClient;
+use crate::phoenix_router::{PhoenixMultihopClient, PhoenixSwap};
+use crate::types::{RouterKind, VaultConfig};
 
 /// Interface for Soroswap Aggregator Router
 /// Allows swapping tokens through multiple liquidity sources
@@ -119,16 +121,41 @@ fn swap_via_router_fallback(
     path.push_back(from_token.clone());
     path.push_back(to_token.clone());
     
+    swap_path_via_router(env, router_address, &path, amount_in, min_amount_out)
+}
+
+/// Execute a multi-hop swap along `path` in a single router call
+/// path[0] is sold, path[last] is bought; intermediate tokens never settle in the vault
+/// The router is called with the Soroswap or Phoenix shape chosen by config.router_kind
+pub fn swap_path_via_router(
+    env: &Env,
+    router_address: &Address,
+    path: &Vec<Address>,
+    amount_in: i128,
+    min_amount_out: i128,
+) -> Result<i128, VaultError> {
+    use soroban_sdk::log;
+    
+    if amount_in <= 0 {
+        return Err(VaultError::InvalidAmount);
+    }
+    
+    if path.len() < 2 {
+        return Err(VaultError::InvalidConfiguration);
+    }
+    
+    let from_token = path.get(0).ok_or(VaultError::InvalidConfiguration)?;
+    
     // Set deadline to 5 minutes from now
     let deadline = env.ledger().timestamp() + 300;
     
     // Get vault address
     let vault_address = env.current_contract_address();
     
-    // Approve the router to spend from_token
-    crate::token_client::approve_router(
+    // Approve the router to spend from_token, unless a live approval already covers it
+    crate::token_client::ensure_allowance(
         env,
-        from_token,
+        &from_token,
         router_address,
         amount_in,
     )?;
@@ -137,21 +164,66 @@ fn swap_via_router_fallback(
     // For SDK 21.7.0, pass an empty Vec (NOT vec![env])
     env.authorize_as_current_contract(soroban_sdk::Vec::new(env));
     
-    // Execute swap through Soroswap router
-    let router_client = SoroswapRouterClient::new(env, router_address);
-    
-    // Call swap_exact_tokens_for_tokens
-    let amounts = router_client.swap_exact_tokens_for_tokens(
-        &amount_in,
-        &min_amount_out,
-        &path,
-        &vault_address,
-        &deadline,
-    );
+    // Dispatch on the configured router's call shape
+    let router_kind = env.storage().instance()
+        .get::<_, VaultConfig>(&soroban_sdk::symbol_short!("CONFIG"))
+        .map(|config| config.router_kind)
+        .unwrap_or(RouterKind::Soroswap);
     
-    // Get the output amount (last element in the amounts array)
-    let amount_out = amounts.get(amounts.len() - 1)
-        .ok_or(VaultError::InvalidAmount)?;
+    let amount_out = match router_kind {
+        RouterKind::Soroswap => {
+            let router_client = SoroswapRouterClient::new(env, router_address);
+            
+            // Call swap_exact_tokens_for_tokens with the full path; the router chains the hops
+            // Use try_ so a misconfigured router (wrong address, wrong interface) surfaces
+            // as a typed error instead of trapping the whole transaction
+            let amounts = match router_client.try_swap_exact_tokens_for_tokens(
+                &amount_in,
+                &min_amount_out,
+                path,
+                &vault_address,
+                &deadline,
+            ) {
+                Ok(Ok(amounts)) => amounts,
+                _ => {
+                    log!(env, "Router call failed: router {}, {} hops, amount: {}", router_address, path.len() - 1, amount_in);
+                    return Err(VaultError::RouterCallFailed);
+                }
+            };
+            
+            // Get the output amount (last element in the amounts array)
+            amounts.get(amounts.len() - 1)
+                .ok_or(VaultError::InvalidAmount)?
+        },
+        RouterKind::Phoenix => {
+            let router_client = PhoenixMultihopClient::new(env, router_address);
+            
+            // Phoenix takes one operation per hop instead of a token path
+            let mut operations: Vec<PhoenixSwap> = Vec::new(env);
+            for i in 0..path.len() - 1 {
+                operations.push_back(PhoenixSwap {
+                    offer_asset: path.get(i).ok_or(VaultError::InvalidConfiguration)?,
+                    ask_asset: path.get(i + 1).ok_or(VaultError::InvalidConfiguration)?,
+                });
+            }
+            
+            // No return value: measure the output as the change in our balance of the last token
+            let to_token = path.get(path.len() - 1).ok_or(VaultError::InvalidConfiguration)?;
+            let balance_before = crate::token_client::get_vault_balance(env, &to_token);
+            
+            if !matches!(
+                router_client.try_swap(&vault_address, &operations, &None, &amount_in),
+                Ok(Ok(()))
+            ) {
+                log!(env, "Phoenix router call failed: router {}, {} hops, amount: {}", router_address, path.len() - 1, amount_in);
+                return Err(VaultError::RouterCallFailed);
+            }
+            
+            let balance_after = crate::token_client::get_vault_balance(env, &to_token);
+            balance_after.checked_sub(balance_before)
+                .ok_or(VaultError::InvalidAmount)?
+        },
+    };
     
     // Verify we got at least the minimum
     if amount_out < min_amount_out {
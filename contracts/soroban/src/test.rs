@@ -0,0 +1,2780 @@
+#![cfg(test)]
+
+extern crate std;
+
+use soroban_sdk::{
+    contract, contracterror, contractimpl, symbol_short, testutils::{Address as _, Events, Ledger}, token, vec, Address,
+    Env, IntoVal, String, Symbol, TryFromVal, Val, Vec,
+};
+
+use crate::{RebalanceRule, RebalanceStep, RouterKind, SourceStrategy, UnstakeMode, VaultConfig, VaultContract, VaultContractClient, VaultError};
+
+/// Soroswap testnet factory some vault paths always use; the mock factory is deployed here
+const SOROSWAP_FACTORY: &str = "CDJTMBYKNUGINFQALHDMPLZYNGUV42GPN4B7QOYTWHRC4EE5IYJM6AES";
+
+const TOKEN_0: Symbol = symbol_short!("TOKEN_0");
+const TOKEN_1: Symbol = symbol_short!("TOKEN_1");
+const RESERVES: Symbol = symbol_short!("RESERVES");
+const BROKEN: Symbol = symbol_short!("BROKEN");
+
+/// Soroswap-shaped pair: swap pays out first, then checks the 0.3%-fee k invariant
+/// against whatever input was transferred in beforehand
+#[contract]
+pub struct MockPair;
+
+#[contractimpl]
+impl MockPair {
+    pub fn init(env: Env, token_0: Address, token_1: Address) {
+        env.storage().instance().set(&TOKEN_0, &token_0);
+        env.storage().instance().set(&TOKEN_1, &token_1);
+        env.storage().instance().set(&RESERVES, &(0i128, 0i128));
+    }
+
+    pub fn token_0(env: Env) -> Address {
+        env.storage().instance().get(&TOKEN_0).unwrap()
+    }
+
+    pub fn token_1(env: Env) -> Address {
+        env.storage().instance().get(&TOKEN_1).unwrap()
+    }
+
+    pub fn get_reserves(env: Env) -> (i128, i128) {
+        env.storage().instance().get(&RESERVES).unwrap()
+    }
+
+    /// Set reserves to the pair's current token balances
+    pub fn sync(env: Env) {
+        let pair = env.current_contract_address();
+        let balance_0 = token::Client::new(&env, &Self::token_0(env.clone())).balance(&pair);
+        let balance_1 = token::Client::new(&env, &Self::token_1(env.clone())).balance(&pair);
+        env.storage().instance().set(&RESERVES, &(balance_0, balance_1));
+    }
+
+    /// Make every later swap trap, like a pair whose contract has been bricked
+    pub fn set_broken(env: Env) {
+        env.storage().instance().set(&BROKEN, &true);
+    }
+
+    pub fn swap(env: Env, amount0_out: i128, amount1_out: i128, to: Address) {
+        assert!(!env.storage().instance().has(&BROKEN), "pair is broken");
+        let (reserve_0, reserve_1) = Self::get_reserves(env.clone());
+        let pair = env.current_contract_address();
+        let token_0 = token::Client::new(&env, &Self::token_0(env.clone()));
+        let token_1 = token::Client::new(&env, &Self::token_1(env.clone()));
+
+        if amount0_out > 0 {
+            token_0.transfer(&pair, &to, &amount0_out);
+        }
+        if amount1_out > 0 {
+            token_1.transfer(&pair, &to, &amount1_out);
+        }
+
+        let balance_0 = token_0.balance(&pair);
+        let balance_1 = token_1.balance(&pair);
+        let amount0_in = (balance_0 - (reserve_0 - amount0_out)).max(0);
+        let amount1_in = (balance_1 - (reserve_1 - amount1_out)).max(0);
+
+        let adjusted_0 = balance_0 * 1000 - amount0_in * 3;
+        let adjusted_1 = balance_1 * 1000 - amount1_in * 3;
+        assert!(adjusted_0 * adjusted_1 >= reserve_0 * reserve_1 * 1_000_000, "k invariant");
+
+        env.storage().instance().set(&RESERVES, &(balance_0, balance_1));
+    }
+}
+
+mod custom_pool {
+    use soroban_sdk::{contract, contractimpl, token, Address, Env};
+
+    use super::{RESERVES, TOKEN_0, TOKEN_1};
+
+    /// Custom-token pool in the real liquidity pool's shape: input is transferred in before
+    /// swap, which pays out at the constant-product price less a 0.3% fee
+    #[contract]
+    pub struct MockCustomPool;
+
+    #[contractimpl]
+    impl MockCustomPool {
+        pub fn init(env: Env, token_0: Address, token_1: Address) {
+            env.storage().instance().set(&TOKEN_0, &token_0);
+            env.storage().instance().set(&TOKEN_1, &token_1);
+            env.storage().instance().set(&RESERVES, &(0i128, 0i128));
+        }
+
+        pub fn token_0(env: Env) -> Address {
+            env.storage().instance().get(&TOKEN_0).unwrap()
+        }
+
+        pub fn token_1(env: Env) -> Address {
+            env.storage().instance().get(&TOKEN_1).unwrap()
+        }
+
+        pub fn tokens(env: Env) -> (Address, Address) {
+            (Self::token_0(env.clone()), Self::token_1(env))
+        }
+
+        pub fn get_reserves(env: Env) -> (i128, i128) {
+            env.storage().instance().get(&RESERVES).unwrap()
+        }
+
+        pub fn sync(env: Env) {
+            let pool = env.current_contract_address();
+            let balance_0 = token::Client::new(&env, &Self::token_0(env.clone())).balance(&pool);
+            let balance_1 = token::Client::new(&env, &Self::token_1(env.clone())).balance(&pool);
+            env.storage().instance().set(&RESERVES, &(balance_0, balance_1));
+        }
+
+        pub fn is_initialized(env: Env) -> bool {
+            env.storage().instance().has(&TOKEN_0)
+        }
+
+        pub fn is_paused(_env: Env) -> bool {
+            false
+        }
+
+        pub fn fee_bps_for(_env: Env, _amount_in: i128) -> i128 {
+            30
+        }
+
+        pub fn swap(env: Env, user: Address, token_in: Address, amount_in: i128, amount_out_min: i128) -> i128 {
+            let (reserve_0, reserve_1) = Self::get_reserves(env.clone());
+            let zero_in = token_in == Self::token_0(env.clone());
+            let (reserve_in, reserve_out) = if zero_in { (reserve_0, reserve_1) } else { (reserve_1, reserve_0) };
+
+            let in_with_fee = amount_in * 9_970;
+            let amount_out = in_with_fee * reserve_out / (reserve_in * 10_000 + in_with_fee);
+            assert!(amount_out >= amount_out_min, "slippage");
+
+            let token_out = if zero_in { Self::token_1(env.clone()) } else { Self::token_0(env.clone()) };
+            token::Client::new(&env, &token_out).transfer(&env.current_contract_address(), &user, &amount_out);
+            Self::sync(env);
+            amount_out
+        }
+    }
+}
+
+use custom_pool::{MockCustomPool, MockCustomPoolClient};
+
+mod routers {
+    use soroban_sdk::{contract, contractimpl, symbol_short, token, vec, Address, Env, Symbol, Vec};
+
+    use crate::phoenix_router::PhoenixSwap;
+
+    const CALLED: Symbol = symbol_short!("CALLED");
+    const PATH: Symbol = symbol_short!("PATH");
+
+    /// Soroswap-shaped router: pulls the input with transfer_from and pays the last
+    /// path token out 1:1, recording that it was called
+    #[contract]
+    pub struct MockSoroswapRouter;
+
+    #[contractimpl]
+    impl MockSoroswapRouter {
+        pub fn swap_exact_tokens_for_tokens(
+            env: Env,
+            amount_in: i128,
+            _amount_out_min: i128,
+            path: Vec<Address>,
+            to: Address,
+            _deadline: u64,
+        ) -> Vec<i128> {
+            let router = env.current_contract_address();
+            token::Client::new(&env, &path.first().unwrap()).transfer_from(&router, &to, &router, &amount_in);
+            token::Client::new(&env, &path.last().unwrap()).transfer(&router, &to, &amount_in);
+            env.storage().instance().set(&CALLED, &true);
+            env.storage().instance().set(&PATH, &path);
+            vec![&env, amount_in, amount_in]
+        }
+
+        pub fn soroswap_called(env: Env) -> bool {
+            env.storage().instance().has(&CALLED)
+        }
+
+        pub fn last_path(env: Env) -> Vec<Address> {
+            env.storage().instance().get(&PATH).unwrap_or(Vec::new(&env))
+        }
+    }
+
+    /// Phoenix multihop-shaped router: same 1:1 fill, but through the operations call shape
+    #[contract]
+    pub struct MockPhoenixRouter;
+
+    #[contractimpl]
+    impl MockPhoenixRouter {
+        pub fn swap(
+            env: Env,
+            recipient: Address,
+            operations: Vec<PhoenixSwap>,
+            _max_spread_bps: Option<i64>,
+            amount: i128,
+        ) {
+            let router = env.current_contract_address();
+            let offer = operations.first().unwrap().offer_asset;
+            let ask = operations.last().unwrap().ask_asset;
+            token::Client::new(&env, &offer).transfer_from(&router, &recipient, &router, &amount);
+            token::Client::new(&env, &ask).transfer(&router, &recipient, &amount);
+            env.storage().instance().set(&CALLED, &true);
+        }
+
+        pub fn phoenix_called(env: Env) -> bool {
+            env.storage().instance().has(&CALLED)
+        }
+    }
+}
+
+use routers::{MockPhoenixRouter, MockPhoenixRouterClient, MockSoroswapRouter, MockSoroswapRouterClient};
+
+mod reentrant_token {
+    use soroban_sdk::{contract, contractimpl, symbol_short, Address, Env, Symbol};
+
+    use crate::VaultContractClient;
+
+    const VAULT: Symbol = symbol_short!("VAULT");
+    const REENTRY: Symbol = symbol_short!("REENTRY");
+
+    /// Token whose transfer calls back into the vault's deposit and records how the nested
+    /// call failed: the vault's error code, u32::MAX if the host refused it, 0 if it went through
+    #[contract]
+    pub struct ReentrantToken;
+
+    #[contractimpl]
+    impl ReentrantToken {
+        pub fn set_vault(env: Env, vault: Address) {
+            env.storage().instance().set(&VAULT, &vault);
+        }
+
+        pub fn transfer(env: Env, _from: Address, _to: Address, _amount: i128) {
+            if env.storage().instance().has(&REENTRY) {
+                return;
+            }
+            env.storage().instance().set(&REENTRY, &0u32);
+
+            let vault: Address = env.storage().instance().get(&VAULT).unwrap();
+            let code = match VaultContractClient::new(&env, &vault).try_deposit(&env.current_contract_address(), &5_000) {
+                Err(Ok(error)) => error as u32,
+                Ok(Ok(_)) => 0,
+                // The host refused the nested call before it reached the vault
+                _ => u32::MAX,
+            };
+            env.storage().instance().set(&REENTRY, &code);
+        }
+
+        pub fn balance(_env: Env, _id: Address) -> i128 {
+            0
+        }
+
+        pub fn reentry_error(env: Env) -> u32 {
+            env.storage().instance().get(&REENTRY).unwrap_or(0)
+        }
+    }
+}
+
+use reentrant_token::{ReentrantToken, ReentrantTokenClient};
+
+mod lp_pool {
+    use soroban_sdk::{contract, contractimpl, symbol_short, token, Address, Env, Symbol};
+
+    use crate::liquidity_router::PoolLiquidityInfo;
+
+    const POOL_INFO: Symbol = symbol_short!("INFO");
+    const SKIM: Symbol = symbol_short!("SKIM");
+
+    /// Liquidity pool in MockLiquidityPool's shape: adds are trimmed to the reserve ratio
+    /// and mint one LP token per unit deposited
+    #[contract]
+    pub struct MockLpPool;
+
+    #[contractimpl]
+    impl MockLpPool {
+        pub fn get_pool_info(env: Env) -> PoolLiquidityInfo {
+            env.storage().instance().get(&POOL_INFO).unwrap_or(PoolLiquidityInfo {
+                reserve_a: 0,
+                reserve_b: 0,
+                total_lp: 0,
+            })
+        }
+
+        pub fn quote(_env: Env, amount_a: i128, reserve_a: i128, reserve_b: i128) -> i128 {
+            if reserve_a == 0 || reserve_b == 0 {
+                return amount_a;
+            }
+            amount_a * reserve_b / reserve_a
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        pub fn add_liquidity(
+            env: Env,
+            user: Address,
+            token_a: Address,
+            token_b: Address,
+            amount_a_desired: i128,
+            amount_b_desired: i128,
+            amount_a_min: i128,
+            amount_b_min: i128,
+            _deadline: u64,
+        ) -> (i128, i128, i128) {
+            user.require_auth();
+            let mut info = Self::get_pool_info(env.clone());
+            let (amount_a, amount_b) = if info.reserve_a == 0 && info.reserve_b == 0 {
+                (amount_a_desired, amount_b_desired)
+            } else {
+                let amount_b_optimal = Self::quote(env.clone(), amount_a_desired, info.reserve_a, info.reserve_b);
+                if amount_b_optimal <= amount_b_desired {
+                    (amount_a_desired, amount_b_optimal)
+                } else {
+                    (Self::quote(env.clone(), amount_b_desired, info.reserve_b, info.reserve_a), amount_b_desired)
+                }
+            };
+            assert!(amount_a >= amount_a_min && amount_b >= amount_b_min, "Insufficient amounts");
+
+            let pool = env.current_contract_address();
+            token::Client::new(&env, &token_a).transfer(&user, &pool, &amount_a);
+            token::Client::new(&env, &token_b).transfer(&user, &pool, &amount_b);
+
+            let lp_tokens = amount_a + amount_b;
+            info.reserve_a += amount_a;
+            info.reserve_b += amount_b;
+            info.total_lp += lp_tokens;
+            env.storage().instance().set(&POOL_INFO, &info);
+            (lp_tokens, amount_a, amount_b)
+        }
+
+        /// Short every later removal by skim_bps of its pro-rata payout
+        pub fn set_skim(env: Env, skim_bps: i128) {
+            env.storage().instance().set(&SKIM, &skim_bps);
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        pub fn remove_liquidity(
+            env: Env,
+            user: Address,
+            token_a: Address,
+            token_b: Address,
+            lp_tokens: i128,
+            amount_a_min: i128,
+            amount_b_min: i128,
+            _deadline: u64,
+        ) -> (i128, i128) {
+            user.require_auth();
+            let mut info = Self::get_pool_info(env.clone());
+            let amount_a = lp_tokens * info.reserve_a / info.total_lp;
+            let amount_b = lp_tokens * info.reserve_b / info.total_lp;
+            assert!(amount_a >= amount_a_min && amount_b >= amount_b_min, "Insufficient amounts");
+
+            // A manipulated pool pays out less than the minimums it just checked
+            let skim_bps: i128 = env.storage().instance().get(&SKIM).unwrap_or(0);
+            let amount_a = amount_a - amount_a * skim_bps / 10_000;
+            let amount_b = amount_b - amount_b * skim_bps / 10_000;
+
+            let pool = env.current_contract_address();
+            token::Client::new(&env, &token_a).transfer(&pool, &user, &amount_a);
+            token::Client::new(&env, &token_b).transfer(&pool, &user, &amount_b);
+
+            info.reserve_a -= amount_a;
+            info.reserve_b -= amount_b;
+            info.total_lp -= lp_tokens;
+            env.storage().instance().set(&POOL_INFO, &info);
+            (amount_a, amount_b)
+        }
+    }
+}
+
+use lp_pool::{MockLpPool, MockLpPoolClient};
+
+mod staking_pool {
+    use soroban_sdk::{contract, contractimpl, contracttype, token, Address, Env};
+
+    #[contracttype]
+    enum DataKey {
+        Token,
+        Rate,
+        Staked(Address),
+        Rewards(Address),
+    }
+
+    /// Liquid staking pool in MockStakingPool's shape, with a settable exchange rate
+    /// (underlying, st_tokens) that deposits and withdrawals are priced at
+    #[contract]
+    pub struct MockStakingPool;
+
+    #[contractimpl]
+    impl MockStakingPool {
+        pub fn init(env: Env, token: Address) {
+            env.storage().instance().set(&DataKey::Token, &token);
+        }
+
+        pub fn set_rate(env: Env, underlying: i128, st_tokens: i128) {
+            env.storage().instance().set(&DataKey::Rate, &(underlying, st_tokens));
+        }
+
+        pub fn get_exchange_rate(env: Env) -> (i128, i128) {
+            env.storage().instance().get(&DataKey::Rate).unwrap_or((1, 1))
+        }
+
+        pub fn get_staked_balance(env: Env, user: Address) -> i128 {
+            env.storage().instance().get(&DataKey::Staked(user)).unwrap_or(0)
+        }
+
+        /// Underlying is transferred in before deposit, as the vault does
+        pub fn deposit(env: Env, sender: Address, amount: i128) -> i128 {
+            let (underlying, st_tokens) = Self::get_exchange_rate(env.clone());
+            let minted = amount * st_tokens / underlying;
+            let staked = Self::get_staked_balance(env.clone(), sender.clone());
+            env.storage().instance().set(&DataKey::Staked(sender), &(staked + minted));
+            minted
+        }
+
+        pub fn withdraw(env: Env, sender: Address, amount: i128) -> i128 {
+            let staked = Self::get_staked_balance(env.clone(), sender.clone());
+            assert!(staked >= amount, "insufficient stake");
+            env.storage().instance().set(&DataKey::Staked(sender.clone()), &(staked - amount));
+
+            let (underlying, st_tokens) = Self::get_exchange_rate(env.clone());
+            let paid = amount * underlying / st_tokens;
+            let token: Address = env.storage().instance().get(&DataKey::Token).unwrap();
+            token::Client::new(&env, &token).transfer(&env.current_contract_address(), &sender, &paid);
+            paid
+        }
+
+        pub fn get_reward_token(env: Env) -> Address {
+            env.storage().instance().get(&DataKey::Token).unwrap()
+        }
+
+        pub fn accrue_rewards(env: Env, funder: Address, user: Address, amount: i128) {
+            funder.require_auth();
+            let reward_token = Self::get_reward_token(env.clone());
+            token::Client::new(&env, &reward_token).transfer(&funder, &env.current_contract_address(), &amount);
+            let pending: i128 = env.storage().instance().get(&DataKey::Rewards(user.clone())).unwrap_or(0);
+            env.storage().instance().set(&DataKey::Rewards(user), &(pending + amount));
+        }
+
+        pub fn claim_rewards(env: Env, sender: Address) -> i128 {
+            let pending: i128 = env.storage().instance().get(&DataKey::Rewards(sender.clone())).unwrap_or(0);
+            if pending > 0 {
+                let reward_token = Self::get_reward_token(env.clone());
+                token::Client::new(&env, &reward_token).transfer(&env.current_contract_address(), &sender, &pending);
+                env.storage().instance().remove(&DataKey::Rewards(sender));
+            }
+            pending
+        }
+    }
+}
+
+use staking_pool::{MockStakingPool, MockStakingPoolClient};
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum MockFactoryError {
+    PairDoesNotExist = 4,
+}
+
+/// Soroswap-shaped factory over pairs registered with set_pair
+#[contract]
+pub struct MockFactory;
+
+#[contractimpl]
+impl MockFactory {
+    pub fn set_pair(env: Env, token_a: Address, token_b: Address, pair: Address) {
+        env.storage().instance().set(&(token_a.clone(), token_b.clone()), &pair);
+        env.storage().instance().set(&(token_b, token_a), &pair);
+    }
+
+    pub fn get_pair(env: Env, token_a: Address, token_b: Address) -> Result<Address, MockFactoryError> {
+        env.storage().instance().get(&(token_a, token_b))
+            .ok_or(MockFactoryError::PairDoesNotExist)
+    }
+}
+
+/// Price oracle answering only for pairs given a price; unset pairs fail the call
+#[contract]
+pub struct MockOracle;
+
+#[contractimpl]
+impl MockOracle {
+    pub fn set_price(env: Env, asset: Address, quote: Address, price: i128) {
+        env.storage().instance().set(&(asset, quote), &price);
+    }
+
+    pub fn price(env: Env, asset: Address, quote: Address) -> i128 {
+        env.storage().instance().get(&(asset, quote)).expect("no price")
+    }
+}
+
+fn setup_env() -> Env {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.budget().reset_unlimited();
+    env
+}
+
+fn create_token(env: &Env) -> Address {
+    let admin = Address::generate(env);
+    env.register_stellar_asset_contract_v2(admin).address()
+}
+
+fn mint(env: &Env, token: &Address, to: &Address, amount: i128) {
+    token::StellarAssetClient::new(env, token).mint(to, &amount);
+}
+
+fn balance(env: &Env, token: &Address, owner: &Address) -> i128 {
+    token::Client::new(env, token).balance(owner)
+}
+
+/// Deploy the mock factory at the hardcoded Soroswap address
+fn register_factory(env: &Env) -> Address {
+    let address = Address::from_string(&String::from_str(env, SOROSWAP_FACTORY));
+    env.register_contract(Some(&address), MockFactory);
+    address
+}
+
+/// Deploy a factory pair holding the given reserves
+fn create_pair(
+    env: &Env,
+    factory: &Address,
+    token_a: &Address,
+    reserve_a: i128,
+    token_b: &Address,
+    reserve_b: i128,
+) -> Address {
+    let pair = env.register_contract(None, MockPair);
+    let client = MockPairClient::new(env, &pair);
+    client.init(token_a, token_b);
+    mint(env, token_a, &pair, reserve_a);
+    mint(env, token_b, &pair, reserve_b);
+    client.sync();
+
+    MockFactoryClient::new(env, factory).set_pair(token_a, token_b, &pair);
+    pair
+}
+
+/// Deploy a custom pool holding the given reserves and register it with the vault for `token`
+fn create_custom_pool(
+    env: &Env,
+    vault: &VaultContractClient,
+    owner: &Address,
+    token: &Address,
+    reserve_token: i128,
+    quote: &Address,
+    reserve_quote: i128,
+) -> Address {
+    let pool = env.register_contract(None, MockCustomPool);
+    let client = MockCustomPoolClient::new(env, &pool);
+    client.init(token, quote);
+    mint(env, token, &pool, reserve_token);
+    mint(env, quote, &pool, reserve_quote);
+    client.sync();
+
+    vault.register_custom_pool(owner, token, &pool);
+    pool
+}
+
+/// Config with every optional feature off
+fn test_config(env: &Env, owner: &Address, assets: Vec<Address>, factory: &Address) -> VaultConfig {
+    VaultConfig {
+        owner: owner.clone(),
+        name: String::from_str(env, "Test Vault"),
+        assets,
+        rules: Vec::new(env),
+        router_address: Some(Address::generate(env)),
+        liquidity_pool_address: None,
+        staking_pool_address: None,
+        factory_address: Some(factory.clone()),
+        auto_rebalance_on_deposit: false,
+        max_deposit_impact_bps: 0,
+        max_liquidation_impact_bps: 0,
+        unstake_mode: UnstakeMode::Full,
+        min_deposit: 0,
+        max_total_value: None,
+        source_strategy: SourceStrategy::FirstExcess,
+        withdrawal_cooldown: 0,
+        router_kind: RouterKind::Soroswap,
+        fee_recipient: None,
+        sweep_dust: false,
+        price_oracle: None,
+        min_rebalance_nav: 0,
+    }
+}
+
+fn rebalance_rule(env: &Env, target_allocation: Vec<i128>) -> RebalanceRule {
+    RebalanceRule {
+        condition_type: String::from_str(env, "time"),
+        threshold: 0,
+        action: String::from_str(env, "rebalance"),
+        target_allocation,
+    }
+}
+
+fn create_vault<'a>(env: &Env, config: &VaultConfig, base_token: &Address, native_token: &Address) -> VaultContractClient<'a> {
+    let vault_id = env.register_contract(None, VaultContract);
+    let client = VaultContractClient::new(env, &vault_id);
+    client.initialize(config, base_token, native_token, &false);
+    client
+}
+
+#[test]
+fn test_whale_deposit_into_thin_pool_is_rejected() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+    create_pair(&env, &factory, &tkn, 10_000_000, &xlm, 10_000_000);
+
+    let mut config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    config.max_deposit_impact_bps = 500;
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    // Half the pool's depth would move the price by a third
+    let whale = Address::generate(&env);
+    mint(&env, &tkn, &whale, 5_000_000);
+    let result = vault.try_deposit_with_token(&whale, &5_000_000, &tkn);
+    assert_eq!(result, Err(Ok(VaultError::SlippageTooHigh)));
+    assert_eq!(balance(&env, &tkn, &whale), 5_000_000);
+
+    // 1% of the pool stays under the 5% cap
+    assert!(vault.deposit_with_token(&whale, &100_000, &tkn) > 0);
+    assert_eq!(balance(&env, &tkn, &vault.address), 100_000);
+}
+
+#[test]
+fn test_paused_asset_is_untouched_by_rebalance() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let aqx = create_token(&env);
+    let slx = create_token(&env);
+    let factory = register_factory(&env);
+    create_pair(&env, &factory, &aqx, 100_000_000, &xlm, 100_000_000);
+    create_pair(&env, &factory, &slx, 100_000_000, &xlm, 100_000_000);
+
+    let mut config = test_config(&env, &owner, vec![&env, xlm.clone(), aqx.clone(), slx.clone()], &factory);
+    config.rules = vec![&env, rebalance_rule(&env, vec![&env, 20_0000, 40_0000, 40_0000])];
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 1_000_000);
+    vault.deposit(&user, &1_000_000);
+
+    vault.pause_asset(&owner, &slx);
+    vault.trigger_rebalance();
+
+    // SLX is neither bought nor used as a source; AQX still reaches its target
+    assert_eq!(balance(&env, &slx, &vault.address), 0);
+    let aqx_held = balance(&env, &aqx, &vault.address);
+    assert!((399_000..=400_000).contains(&aqx_held), "aqx {}", aqx_held);
+    assert!(balance(&env, &xlm, &vault.address) < 600_000);
+}
+
+#[test]
+fn test_nav_prefers_oracle_over_pool_price() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+    create_pair(&env, &factory, &tkn, 10_000_000, &xlm, 10_000_000);
+
+    let oracle = env.register_contract(None, MockOracle);
+    let mut config = test_config(&env, &owner, vec![&env, tkn.clone(), xlm.clone()], &factory);
+    config.price_oracle = Some(oracle.clone());
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 1_000_000);
+    vault.deposit(&user, &1_000_000);
+    mint(&env, &tkn, &vault.address, 500_000);
+    // Staking leg in TKN, valued at the 1:1 fallback redemption rate
+    vault.recover_staking_position(&owner, &Address::generate(&env), &100_000, &100_000);
+
+    // Oracle has no TKN price yet: every TKN leg falls back to the 1:1 pool, still in XLM units
+    assert_eq!(vault.nav(), 1_600_000);
+    assert_eq!(vault.get_asset_price(&tkn), 10_000_000);
+
+    // Oracle says TKN is worth 2 XLM while the pool still trades 1:1
+    MockOracleClient::new(&env, &oracle).set_price(&tkn, &xlm, &20_000_000);
+    assert_eq!(vault.get_asset_price(&tkn), 20_000_000);
+    assert_eq!(vault.nav(), 1_000_000 + 1_000_000 + 200_000);
+}
+
+#[test]
+fn test_deposit_cap_measures_deposits_in_base_value() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+    // One TKN trades for two XLM
+    create_pair(&env, &factory, &tkn, 5_000_000, &xlm, 10_000_000);
+
+    let mut config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    config.max_total_value = Some(1_000_000);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 600_000);
+    mint(&env, &tkn, &user, 300_000);
+    vault.deposit(&user, &600_000);
+    assert_eq!(vault.remaining_capacity(), Some(400_000));
+
+    // 200_001 raw TKN fits under 400_000, but it is worth 400_002 XLM
+    let result = vault.try_deposit_with_token(&user, &200_001, &tkn);
+    assert_eq!(result, Err(Ok(VaultError::DepositCapExceeded)));
+
+    // Exactly at the cap is accepted; shares still follow the raw amount
+    assert_eq!(vault.deposit_with_token(&user, &200_000, &tkn), 200_000);
+    assert_eq!(vault.remaining_capacity(), Some(0));
+    assert_eq!(vault.get_state().total_value, 800_000);
+    assert_eq!(vault.nav(), 1_000_000);
+}
+
+#[test]
+fn test_reconcile_values_assets_in_base() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let orphan = create_token(&env);
+    let factory = register_factory(&env);
+    // One TKN trades for two XLM; the orphan token has no pool and no oracle
+    create_pair(&env, &factory, &tkn, 1_000_000, &xlm, 2_000_000);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone(), orphan.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 1_000_000);
+    mint(&env, &tkn, &user, 100_000);
+    vault.deposit(&user, &1_000_000);
+    vault.deposit_with_token(&user, &100_000, &tkn);
+    assert_eq!(vault.get_state().total_value, 1_100_000);
+
+    // Fees eat 30_000 XLM of the vault's balance
+    token::Client::new(&env, &xlm).burn(&vault.address, &30_000);
+    assert_eq!(vault.reconcile(&owner), 1_170_000);
+    assert_eq!(vault.get_state().total_value, vault.nav());
+
+    // A held balance nothing can price is refused rather than written off
+    mint(&env, &orphan, &vault.address, 50_000);
+    assert_eq!(vault.try_reconcile(&owner), Err(Ok(VaultError::PoolNotFound)));
+    assert_eq!(vault.get_state().total_value, 1_170_000);
+}
+
+#[test]
+fn test_simulate_withdrawal_matches_capped_withdraw() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+    create_pair(&env, &factory, &tkn, 1_000_000, &xlm, 1_000_000);
+
+    let mut config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    config.max_liquidation_impact_bps = 1_000;
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let user = Address::generate(&env);
+    mint(&env, &tkn, &user, 400_000);
+    let shares = vault.deposit_with_token(&user, &400_000, &tkn);
+
+    // The 10% impact cap lets withdraw sell only 111_111 of the 400_000 TKN
+    let breakdown = vault.simulate_withdrawal(&user, &shares);
+    let total_shares = vault.get_state().total_shares;
+    let (_, tkn_liquidated, tkn_xlm) = breakdown.get(1).unwrap();
+    assert_eq!(breakdown.get(0).unwrap(), (xlm.clone(), 0, 0));
+    assert_eq!(tkn_liquidated, 111_111 * shares / total_shares);
+    assert_eq!(vault.max_withdrawable(&user), tkn_xlm);
+
+    let paid = vault.withdraw(&user, &shares);
+    assert_eq!(balance(&env, &tkn, &vault.address), 400_000 - 111_111);
+    // withdraw requests one unit less than the quote to keep the pair's invariant safe
+    assert!((tkn_xlm - paid).abs() <= 1, "simulated {} paid {}", tkn_xlm, paid);
+    assert_eq!(balance(&env, &xlm, &user), paid);
+}
+
+#[test]
+fn test_emergency_withdraw_respects_withdrawal_cooldown() {
+    let env = setup_env();
+    env.ledger().with_mut(|l| l.timestamp = 1_000);
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let factory = register_factory(&env);
+
+    let mut config = test_config(&env, &owner, vec![&env, xlm.clone()], &factory);
+    config.withdrawal_cooldown = 3_600;
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 100_000);
+    vault.deposit(&user, &100_000);
+
+    vault.emergency_withdraw(&user, &10_000);
+    assert_eq!(
+        vault.try_emergency_withdraw(&user, &10_000),
+        Err(Ok(VaultError::WithdrawalCooldown))
+    );
+
+    env.ledger().with_mut(|l| l.timestamp += 3_600);
+    vault.emergency_withdraw(&user, &10_000);
+    assert_eq!(balance(&env, &xlm, &user), 20_000);
+}
+
+#[test]
+fn test_queued_withdrawal_is_reserved_and_paid_once() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+    create_pair(&env, &factory, &tkn, 1_000_000, &xlm, 1_000_000);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let queued = Address::generate(&env);
+    let other = Address::generate(&env);
+    let holder = Address::generate(&env);
+    mint(&env, &xlm, &queued, 100_000);
+    mint(&env, &xlm, &other, 100_000);
+    mint(&env, &tkn, &holder, 100_000);
+    let queued_shares = vault.deposit(&queued, &100_000);
+    let other_shares = vault.deposit(&other, &100_000);
+    vault.deposit_with_token(&holder, &100_000, &tkn);
+
+    let owed = vault.request_withdrawal(&queued, &queued_shares);
+
+    // An ordinary withdrawal can't spend the native token earmarked for the queue
+    vault.withdraw(&other, &other_shares);
+    assert!(balance(&env, &xlm, &vault.address) >= owed);
+
+    // The reserve covers the claim, so nothing else in the vault is sold
+    let tkn_before = balance(&env, &tkn, &vault.address);
+    assert_eq!(vault.fulfill_withdrawal(&queued), owed);
+    assert_eq!(balance(&env, &xlm, &queued), owed);
+    assert_eq!(balance(&env, &tkn, &vault.address), tkn_before);
+    assert_eq!(vault.get_pending_withdrawal(&queued), None);
+
+    assert_eq!(vault.try_fulfill_withdrawal(&queued), Err(Ok(VaultError::InvalidAmount)));
+}
+
+#[test]
+fn test_fulfill_withdrawal_sells_only_the_shortfall() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+    create_pair(&env, &factory, &tkn, 1_000_000, &xlm, 1_000_000);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 20_000);
+    mint(&env, &tkn, &user, 80_000);
+    let xlm_shares = vault.deposit(&user, &20_000);
+    let tkn_shares = vault.deposit_with_token(&user, &80_000, &tkn);
+
+    let owed = vault.request_withdrawal(&user, &((xlm_shares + tkn_shares) / 2));
+    let shortfall = owed - 20_000;
+    vault.fulfill_withdrawal(&user);
+
+    // Only the shortfall's worth of TKN is sold, not the whole balance
+    assert_eq!(balance(&env, &tkn, &vault.address), 80_000 - shortfall);
+    // The sale's fee and price impact leave part of the claim queued
+    let paid = balance(&env, &xlm, &user);
+    let remaining = vault.get_pending_withdrawal(&user).unwrap().amount_owed;
+    assert!(paid > owed * 95 / 100, "owed {} paid {}", owed, paid);
+    assert_eq!(paid + remaining, owed);
+}
+
+#[test]
+fn test_rebalance_plan_trades_deepest_pool_first() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let shallow = create_token(&env);
+    let deep = create_token(&env);
+    let factory = register_factory(&env);
+
+    let mut config = test_config(&env, &owner, vec![&env, xlm.clone(), shallow.clone(), deep.clone()], &factory);
+    config.rules = vec![&env, rebalance_rule(&env, vec![&env, 34_0000, 33_0000, 33_0000])];
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+    create_custom_pool(&env, &vault, &owner, &shallow, 50_000, &xlm, 50_000);
+    create_custom_pool(&env, &vault, &owner, &deep, 5_000_000, &xlm, 5_000_000);
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 100_000);
+    vault.deposit(&user, &100_000);
+
+    // Config order buys the shallow asset first; the plan moves the deep pool's swap ahead
+    let plan = vault.get_rebalance_plan();
+    assert_eq!(plan.total_steps, 2);
+    assert_eq!(plan.steps.get(0).unwrap().to_token, deep);
+    assert_eq!(plan.steps.get(1).unwrap().to_token, shallow);
+}
+
+#[test]
+fn test_rebalance_step_routes_through_intermediate_hop() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let token_a = create_token(&env);
+    let token_b = create_token(&env);
+    let factory = register_factory(&env);
+    // No direct A/B pair: the step has to go A -> XLM -> B
+    create_pair(&env, &factory, &token_a, 1_000_000, &xlm, 1_000_000);
+    create_pair(&env, &factory, &xlm, 1_000_000, &token_b, 1_000_000);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone(), token_a.clone(), token_b.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let user = Address::generate(&env);
+    mint(&env, &token_a, &user, 50_000);
+    vault.deposit_with_token(&user, &50_000, &token_a);
+
+    let step = RebalanceStep {
+        from_token: token_a.clone(),
+        to_token: token_b.clone(),
+        amount_in: 10_000,
+        min_amount_out: 9_000,
+        pool_address: Address::generate(&env),
+        path: vec![&env, token_a.clone(), xlm.clone(), token_b.clone()],
+    };
+    let amount_out = vault.execute_rebalance_step(&step);
+
+    assert!((9_000..10_000).contains(&amount_out));
+    assert_eq!(balance(&env, &token_a, &vault.address), 40_000);
+    assert_eq!(balance(&env, &token_b, &vault.address), amount_out);
+    // Only dust from the intermediate hop's safety margin stays behind in XLM
+    assert!(balance(&env, &xlm, &vault.address) <= 1);
+}
+
+/// Vault holding 50_000 of `token_a` whose only route to XLM is the configured router:
+/// no custom pool and no factory pair. The router is funded with XLM; returns (vault, xlm, token_a)
+fn router_only_vault<'a>(env: &Env, router: &Address, router_kind: RouterKind) -> (VaultContractClient<'a>, Address, Address) {
+    let owner = Address::generate(env);
+    let xlm = create_token(env);
+    let token_a = create_token(env);
+    let factory = register_factory(env);
+
+    let mut config = test_config(env, &owner, vec![env, xlm.clone(), token_a.clone()], &factory);
+    config.router_address = Some(router.clone());
+    config.router_kind = router_kind;
+    let vault = create_vault(env, &config, &xlm, &xlm);
+
+    // Without a pool the deposit path has no route for token_a, so fund the vault directly
+    mint(env, &token_a, &vault.address, 50_000);
+    mint(env, &xlm, router, 1_000_000);
+
+    (vault, xlm, token_a)
+}
+
+fn direct_step(env: &Env, from: &Address, to: &Address, amount_in: i128) -> RebalanceStep {
+    RebalanceStep {
+        from_token: from.clone(),
+        to_token: to.clone(),
+        amount_in,
+        min_amount_out: amount_in,
+        pool_address: Address::generate(env),
+        path: vec![env, from.clone(), to.clone()],
+    }
+}
+
+#[test]
+fn test_soroswap_router_kind_calls_swap_exact_tokens_for_tokens() {
+    let env = setup_env();
+    let router = env.register_contract(None, MockSoroswapRouter);
+    let (vault, xlm, token_a) = router_only_vault(&env, &router, RouterKind::Soroswap);
+
+    let amount_out = vault.execute_rebalance_step(&direct_step(&env, &token_a, &xlm, 10_000));
+
+    assert!(MockSoroswapRouterClient::new(&env, &router).soroswap_called());
+    assert_eq!(amount_out, 10_000);
+    assert_eq!(balance(&env, &token_a, &router), 10_000);
+    assert_eq!(balance(&env, &xlm, &vault.address), 10_000);
+}
+
+#[test]
+fn test_phoenix_router_kind_calls_multihop_swap() {
+    let env = setup_env();
+    let router = env.register_contract(None, MockPhoenixRouter);
+    let (vault, xlm, token_a) = router_only_vault(&env, &router, RouterKind::Phoenix);
+
+    let amount_out = vault.execute_rebalance_step(&direct_step(&env, &token_a, &xlm, 10_000));
+
+    assert!(MockPhoenixRouterClient::new(&env, &router).phoenix_called());
+    assert_eq!(amount_out, 10_000);
+    assert_eq!(balance(&env, &token_a, &router), 10_000);
+    assert_eq!(balance(&env, &xlm, &vault.address), 10_000);
+}
+
+#[test]
+fn test_auto_rebalance_on_deposit_reaches_target_mix() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+
+    let mut config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    config.rules = vec![&env, rebalance_rule(&env, vec![&env, 70_0000, 30_0000])];
+    config.auto_rebalance_on_deposit = true;
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+    create_custom_pool(&env, &vault, &owner, &tkn, 1_000_000, &xlm, 1_000_000);
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 100_000);
+    vault.deposit(&user, &100_000);
+
+    let xlm_held = balance(&env, &xlm, &vault.address);
+    let tkn_held = balance(&env, &tkn, &vault.address);
+    assert!(xlm_held > 0 && tkn_held > 0, "xlm {} tkn {}", xlm_held, tkn_held);
+    assert!((tkn_held - 30_000).abs() < 1_000, "tkn {}", tkn_held);
+}
+
+#[test]
+fn test_health_check_reports_drift_between_book_and_live_value() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+    create_pair(&env, &factory, &tkn, 1_000_000, &xlm, 1_000_000);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 100_000);
+    vault.deposit(&user, &100_000);
+
+    let report = vault.health_check();
+    assert!(report.solvent);
+    assert!(!report.has_missing_pools);
+    assert_eq!(report.value_drift_bps, 0);
+
+    // A tenth of the backing disappears without total_value hearing about it
+    token::Client::new(&env, &xlm).burn(&vault.address, &10_000);
+
+    let report = vault.health_check();
+    assert!(report.solvent);
+    assert_eq!(report.value_drift_bps, 1_000);
+}
+
+#[test]
+fn test_deposit_protected_rejects_a_manipulated_share_price() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+    let pair = create_pair(&env, &factory, &tkn, 1_000_000, &xlm, 1_000_000);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let seed = Address::generate(&env);
+    mint(&env, &xlm, &seed, 100_000);
+    vault.deposit(&seed, &100_000);
+
+    let user = Address::generate(&env);
+    mint(&env, &tkn, &user, 40_000);
+
+    // At the quoted price the minimum is met
+    let quoted = vault.quote_deposit(&20_000, &tkn).shares_minted;
+    assert_eq!(vault.deposit_protected(&user, &20_000, &tkn, &quoted), quoted);
+
+    // Pumping TKN and re-marking the book raises the share price before the next deposit lands
+    let quoted = vault.quote_deposit(&20_000, &tkn).shares_minted;
+    mint(&env, &xlm, &pair, 1_000_000);
+    MockPairClient::new(&env, &pair).sync();
+    vault.reconcile(&owner);
+
+    assert_eq!(
+        vault.try_deposit_protected(&user, &20_000, &tkn, &quoted),
+        Err(Ok(VaultError::InsufficientShares))
+    );
+    assert_eq!(balance(&env, &tkn, &user), 20_000);
+}
+
+#[test]
+fn test_deposit_rejects_reentry_from_token_transfer() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let evil = env.register_contract(None, ReentrantToken);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, evil.clone()], &factory);
+    let vault = create_vault(&env, &config, &evil, &evil);
+    let evil_client = ReentrantTokenClient::new(&env, &evil);
+    evil_client.set_vault(&vault.address);
+
+    let user = Address::generate(&env);
+    vault.deposit(&user, &10_000);
+
+    assert_ne!(evil_client.reentry_error(), 0);
+    assert_eq!(vault.get_state().total_value, 10_000);
+
+    // Re-entry that does reach the vault while a deposit holds the lock is refused by the vault itself
+    env.as_contract(&vault.address, || {
+        env.storage().instance().set(&symbol_short!("LOCKED"), &true);
+        assert_eq!(
+            VaultContract::deposit(env.clone(), user.clone(), 5_000),
+            Err(VaultError::Reentrancy)
+        );
+    });
+}
+
+#[test]
+fn test_dead_shares_blunt_first_depositor_inflation() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let attacker = Address::generate(&env);
+    let victim = Address::generate(&env);
+    mint(&env, &xlm, &attacker, 1_000_000);
+    mint(&env, &xlm, &victim, 10_000);
+
+    // The first deposit has to cover the dead shares
+    assert_eq!(vault.try_deposit(&attacker, &1_000), Err(Ok(VaultError::InvalidAmount)));
+
+    // Smallest possible position, then a large donation to try to inflate the share price
+    assert_eq!(vault.deposit(&attacker, &1_001), 1);
+    assert_eq!(vault.get_state().total_shares, 1_001);
+    token::Client::new(&env, &xlm).transfer(&attacker, &vault.address, &900_000);
+
+    // Shares are minted against book value, so the victim isn't rounded down to nothing
+    let victim_shares = vault.deposit(&victim, &10_000);
+    assert_eq!(victim_shares, 10_000);
+    assert_eq!(vault.get_position(&victim).shares, 10_000);
+
+    // The attacker's share still redeems at book value; the donation is simply lost
+    assert_eq!(vault.withdraw(&attacker, &1), 1);
+}
+
+#[test]
+fn test_force_rebalance_to_moves_a_balanced_vault_to_an_explicit_target() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+
+    let mut config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    config.rules = vec![&env, rebalance_rule(&env, vec![&env, 50_0000, 50_0000])];
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+    create_custom_pool(&env, &vault, &owner, &tkn, 10_000_000, &xlm, 10_000_000);
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 100_000);
+    vault.deposit(&user, &100_000);
+    vault.force_rebalance();
+    assert!((balance(&env, &tkn, &vault.address) - 50_000).abs() < 1_000);
+
+    vault.force_rebalance_to(&owner, &vec![&env, 80_0000, 20_0000]);
+
+    let xlm_held = balance(&env, &xlm, &vault.address);
+    let tkn_held = balance(&env, &tkn, &vault.address);
+    assert!((xlm_held - 80_000).abs() < 1_000, "xlm {}", xlm_held);
+    assert!((tkn_held - 20_000).abs() < 1_000, "tkn {}", tkn_held);
+
+    let stranger = Address::generate(&env);
+    assert_eq!(
+        vault.try_force_rebalance_to(&stranger, &vec![&env, 50_0000, 50_0000]),
+        Err(Ok(VaultError::Unauthorized))
+    );
+}
+
+#[test]
+fn test_deposit_share_math_survives_large_totals() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let whale = Address::generate(&env);
+    let user = Address::generate(&env);
+    let big: i128 = 100_000_000_000_000_000_000; // 1e20
+    mint(&env, &xlm, &whale, big);
+    mint(&env, &xlm, &user, big / 10);
+    vault.deposit(&whale, &big);
+
+    // 1e19 * 1e20 overflows i128 before the division; the widened intermediate doesn't
+    assert!((big / 10).checked_mul(vault.get_state().total_shares).is_none());
+    assert_eq!(vault.deposit(&user, &(big / 10)), big / 10);
+}
+
+#[test]
+fn test_simulate_rebalance_tracks_the_executed_rebalance() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+    create_custom_pool(&env, &vault, &owner, &tkn, 10_000_000, &xlm, 10_000_000);
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 100_000);
+    vault.deposit(&user, &100_000);
+
+    let target = vec![&env, 50_0000, 50_0000];
+    let projected = vault.simulate_rebalance(&target);
+    // Simulating moves nothing
+    assert_eq!(balance(&env, &xlm, &vault.address), 100_000);
+
+    vault.force_rebalance_to(&owner, &target);
+
+    // The plan sells the target gap while execution buys it, so they differ by about the fee
+    for (i, asset) in [xlm, tkn].iter().enumerate() {
+        let simulated = projected.get(i as u32).unwrap();
+        let actual = balance(&env, asset, &vault.address);
+        assert_eq!(&simulated.token, asset);
+        assert!((simulated.amount - actual).abs() <= actual / 100, "simulated {} actual {}", simulated.amount, actual);
+    }
+}
+
+#[test]
+fn test_force_rebalance_reports_hitting_the_swap_cap() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let factory = register_factory(&env);
+
+    let mut assets = vec![&env, xlm.clone()];
+    for _ in 0..4 {
+        assets.push_back(create_token(&env));
+    }
+    let config = test_config(&env, &owner, assets.clone(), &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+    for token in assets.iter().skip(1) {
+        create_custom_pool(&env, &vault, &owner, &token, 10_000_000, &xlm, 10_000_000);
+    }
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 100_000);
+    vault.deposit(&user, &100_000);
+
+    // Four assets to buy, but a single call stops after three swaps
+    let target = vec![&env, 20_0000, 20_0000, 20_0000, 20_0000, 20_0000];
+    let outcome = vault.force_rebalance_to(&owner, &target);
+    assert_eq!(outcome.swaps_executed, 3);
+    assert!(outcome.hit_budget_cap);
+    assert!(!outcome.converged);
+
+    // A follow-up call finishes the job
+    let outcome = vault.force_rebalance_to(&owner, &target);
+    assert!(!outcome.hit_budget_cap);
+    assert!(outcome.converged);
+}
+
+#[test]
+fn test_emergency_withdraw_exits_in_kind_when_the_pool_is_broken() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+    let pair = create_pair(&env, &factory, &tkn, 1_000_000, &xlm, 1_000_000);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 20_000);
+    mint(&env, &tkn, &user, 80_000);
+    let shares = vault.deposit(&user, &20_000) + vault.deposit_with_token(&user, &80_000, &tkn);
+
+    MockPairClient::new(&env, &pair).set_broken();
+
+    // withdraw has to sell TKN for XLM and can't
+    assert!(vault.try_withdraw(&user, &shares).is_err());
+    assert_eq!(vault.get_position(&user).shares, shares);
+
+    // The in-kind exit needs no pool at all
+    let total_shares = vault.get_state().total_shares;
+    let paid = vault.emergency_withdraw(&user, &shares);
+    assert_eq!(paid.get(0).unwrap().amount, 20_000 * shares / total_shares);
+    assert_eq!(paid.get(1).unwrap().amount, 80_000 * shares / total_shares);
+    assert_eq!(balance(&env, &xlm, &user), 20_000 * shares / total_shares);
+    assert_eq!(balance(&env, &tkn, &user), 80_000 * shares / total_shares);
+    assert_eq!(vault.get_position(&user).shares, 0);
+}
+
+/// Vault holding 50_000 each of a token with a shallow XLM pool and one with a deep XLM pool,
+/// rebalanced by trigger_rebalance toward half XLM. Returns the (shallow, deep, xlm) balances after
+fn rebalance_two_sources(strategy: SourceStrategy) -> (i128, i128, i128) {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let shallow = create_token(&env);
+    let deep = create_token(&env);
+    let factory = register_factory(&env);
+    create_pair(&env, &factory, &shallow, 100_000, &xlm, 100_000);
+    create_pair(&env, &factory, &deep, 10_000_000, &xlm, 10_000_000);
+
+    let mut config = test_config(&env, &owner, vec![&env, shallow.clone(), deep.clone(), xlm.clone()], &factory);
+    config.rules = vec![&env, rebalance_rule(&env, vec![&env, 25_0000, 25_0000, 50_0000])];
+    config.source_strategy = strategy;
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+    mint(&env, &shallow, &vault.address, 50_000);
+    mint(&env, &deep, &vault.address, 50_000);
+
+    vault.trigger_rebalance();
+
+    (
+        balance(&env, &shallow, &vault.address),
+        balance(&env, &deep, &vault.address),
+        balance(&env, &xlm, &vault.address),
+    )
+}
+
+#[test]
+fn test_deepest_pool_strategy_sells_through_the_deeper_pool() {
+    // Config order sells the shallow-pool token first
+    let (shallow, deep, first_excess_xlm) = rebalance_two_sources(SourceStrategy::FirstExcess);
+    assert!(shallow < 50_000);
+    assert_eq!(deep, 50_000);
+
+    let (shallow, deep, deepest_pool_xlm) = rebalance_two_sources(SourceStrategy::DeepestPool);
+    assert_eq!(shallow, 50_000);
+    assert!(deep < 50_000);
+
+    // Same amount sold, less of it lost to slippage
+    assert!(
+        deepest_pool_xlm > first_excess_xlm * 11 / 10,
+        "deepest {} first {}", deepest_pool_xlm, first_excess_xlm
+    );
+}
+
+/// Liquidate a vault holding 100_000 each of a thin-pool and a deep-pool token through withdraw.
+/// Returns (XLM raised by the swaps, thin tokens sold, deep tokens sold)
+fn liquidate_thin_and_deep(max_liquidation_impact_bps: i128) -> (i128, i128, i128) {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let thin = create_token(&env);
+    let deep = create_token(&env);
+    let factory = register_factory(&env);
+    create_pair(&env, &factory, &thin, 100_000, &xlm, 100_000);
+    create_pair(&env, &factory, &deep, 100_000_000, &xlm, 100_000_000);
+
+    let mut config = test_config(&env, &owner, vec![&env, xlm.clone(), thin.clone(), deep.clone()], &factory);
+    config.max_liquidation_impact_bps = max_liquidation_impact_bps;
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 10_000);
+    let shares = vault.deposit(&user, &10_000);
+    mint(&env, &thin, &vault.address, 100_000);
+    mint(&env, &deep, &vault.address, 100_000);
+
+    let paid = vault.withdraw(&user, &(shares / 10));
+
+    (
+        balance(&env, &xlm, &vault.address) + paid - 10_000,
+        100_000 - balance(&env, &thin, &vault.address),
+        100_000 - balance(&env, &deep, &vault.address),
+    )
+}
+
+#[test]
+fn test_liquidation_impact_cap_keeps_thin_pools_from_being_dumped() {
+    let (uncapped_xlm, uncapped_thin, uncapped_deep) = liquidate_thin_and_deep(0);
+    let (capped_xlm, capped_thin, capped_deep) = liquidate_thin_and_deep(1_000);
+
+    // Without a cap the whole thin balance goes in at half price
+    assert_eq!(uncapped_thin, 100_000);
+    assert_eq!(uncapped_deep, 100_000);
+
+    // With a 10% cap only 11_111 is sold into the thin pool; the deep pool is barely moved
+    assert_eq!(capped_thin, 11_111);
+    assert_eq!(capped_deep, 100_000);
+
+    // XLM per token sold is far better under the cap
+    let uncapped_rate = uncapped_xlm * 10_000 / (uncapped_thin + uncapped_deep);
+    let capped_rate = capped_xlm * 10_000 / (capped_thin + capped_deep);
+    assert!(capped_rate > uncapped_rate * 11 / 10, "capped {} uncapped {}", capped_rate, uncapped_rate);
+}
+
+/// preview_rebalance_cost of taking an all-XLM vault to 50/50 through a custom pool of the given depth
+fn preview_cost_at_depth(pool_depth: i128) -> i128 {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+    create_custom_pool(&env, &vault, &owner, &tkn, pool_depth, &xlm, pool_depth);
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 100_000);
+    vault.deposit(&user, &100_000);
+
+    vault.preview_rebalance_cost(&vec![&env, 50_0000, 50_0000])
+}
+
+#[test]
+fn test_preview_rebalance_cost_is_higher_through_a_thin_pool() {
+    let thin_cost = preview_cost_at_depth(100_000);
+    let deep_cost = preview_cost_at_depth(100_000_000);
+
+    // The deep pool still charges its fee
+    assert!(deep_cost > 0);
+    assert!(thin_cost > deep_cost * 10, "thin {} deep {}", thin_cost, deep_cost);
+}
+
+#[test]
+fn test_pumped_pool_mints_no_extra_deposit_shares() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+    let pair = create_pair(&env, &factory, &tkn, 1_000_000, &xlm, 1_000_000);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let seed = Address::generate(&env);
+    mint(&env, &xlm, &seed, 100_000);
+    vault.deposit(&seed, &100_000);
+
+    let attacker = Address::generate(&env);
+    mint(&env, &tkn, &attacker, 10_000);
+    let unpumped = vault.quote_deposit(&10_000, &tkn).shares_minted;
+
+    // Quadruple TKN's spot price within the same transaction, then deposit at it
+    mint(&env, &xlm, &pair, 3_000_000);
+    MockPairClient::new(&env, &pair).sync();
+    let shares = vault.deposit_with_token(&attacker, &10_000, &tkn);
+
+    assert_eq!(shares, unpumped);
+    // Redeemed at book value the shares are worth what went in, not the pumped price
+    let book = shares * vault.get_state().total_value / vault.get_state().total_shares;
+    assert!(book <= 10_000, "book {}", book);
+}
+
+#[test]
+fn test_initialize_requires_base_and_native_among_assets() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let usdc = create_token(&env);
+    let xlm = create_token(&env);
+    let outsider = create_token(&env);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, usdc.clone(), xlm.clone()], &factory);
+    let vault = VaultContractClient::new(&env, &env.register_contract(None, VaultContract));
+    assert_eq!(
+        vault.try_initialize(&config, &outsider, &xlm, &false),
+        Err(Ok(VaultError::InvalidConfiguration))
+    );
+    assert_eq!(
+        vault.try_initialize(&config, &usdc, &outsider, &false),
+        Err(Ok(VaultError::InvalidConfiguration))
+    );
+
+    vault.initialize(&config, &usdc, &xlm, &false);
+    assert_eq!(vault.get_base_token(), usdc);
+    assert_eq!(vault.get_native_token(), xlm);
+}
+
+#[test]
+fn test_withdraw_pays_base_value_converted_to_native() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let usdc = create_token(&env);
+    let xlm = create_token(&env);
+    let factory = register_factory(&env);
+    // One USDC trades for two XLM
+    create_pair(&env, &factory, &usdc, 500_000_000, &xlm, 1_000_000_000);
+
+    let config = test_config(&env, &owner, vec![&env, usdc.clone(), xlm.clone()], &factory);
+    let vault = create_vault(&env, &config, &usdc, &xlm);
+
+    let user = Address::generate(&env);
+    let queued = Address::generate(&env);
+    mint(&env, &usdc, &user, 100_000);
+    mint(&env, &usdc, &queued, 100_000);
+    let shares = vault.deposit(&user, &100_000);
+    let queued_shares = vault.deposit(&queued, &100_000);
+
+    // 99_000 USDC of book value is owed as 198_000 XLM, not 99_000
+    assert_eq!(vault.request_withdrawal(&queued, &queued_shares), 200_000);
+    assert_eq!(vault.max_withdrawable(&user), 198_000);
+    assert_eq!(vault.withdraw(&user, &shares), 198_000);
+    assert_eq!(balance(&env, &xlm, &user), 198_000);
+    assert_eq!(vault.get_state().total_value, 1_000);
+}
+
+#[test]
+fn test_swap_into_uninitialized_pool_fails_without_transfer() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+    mint(&env, &xlm, &vault.address, 10_000);
+
+    // Deployed but never initialized
+    let pool = env.register_contract(None, MockCustomPool);
+
+    env.as_contract(&vault.address, || {
+        assert_eq!(
+            crate::real_pool_client::swap_via_real_pool(&env, &pool, &xlm, &tkn, 5_000, 0),
+            Err(VaultError::PoolNotFound)
+        );
+    });
+    assert_eq!(balance(&env, &xlm, &vault.address), 10_000);
+    assert_eq!(balance(&env, &xlm, &pool), 0);
+}
+
+#[test]
+fn test_preview_add_liquidity_matches_trimmed_deposit() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    // Pool priced 1 xlm : 2 tkn
+    let pool = env.register_contract(None, MockLpPool);
+    let provider = Address::generate(&env);
+    mint(&env, &xlm, &provider, 1_000);
+    mint(&env, &tkn, &provider, 2_000);
+    MockLpPoolClient::new(&env, &pool).add_liquidity(&provider, &xlm, &tkn, &1_000, &2_000, &0, &0, &0);
+    vault.set_liquidity_pool(&owner, &pool);
+
+    // Surplus tkn is left behind: only 100 xlm + 200 tkn go in
+    let preview = vault.preview_add_liquidity(&100, &500);
+    assert_eq!(preview, 300);
+
+    mint(&env, &xlm, &vault.address, 100);
+    mint(&env, &tkn, &vault.address, 500);
+    env.mock_all_auths_allowing_non_root_auth();
+    let (lp_tokens, used_a, used_b) = env.as_contract(&vault.address, || {
+        crate::liquidity_router::add_liquidity_to_pool(&env, &pool, &xlm, &tkn, 100, 500, 5)
+    }).unwrap();
+    assert_eq!(lp_tokens, preview);
+    assert_eq!((used_a, used_b), (100, 200));
+    assert_eq!(balance(&env, &tkn, &vault.address), 300);
+}
+
+#[test]
+fn test_force_rebalance_to_rejects_negative_entry_summing_to_100() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let usdc = create_token(&env);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone(), usdc.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 100_000);
+    vault.deposit(&user, &100_000);
+
+    let result = vault.try_force_rebalance_to(&owner, &vec![&env, -10_0000, 60_0000, 50_0000]);
+    assert_eq!(result, Err(Ok(VaultError::InvalidConfiguration)));
+}
+
+#[test]
+fn test_initialize_rejects_all_zero_rule_allocation() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+
+    let mut config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    config.rules = vec![&env, rebalance_rule(&env, vec![&env, 0, 0])];
+    let vault = VaultContractClient::new(&env, &env.register_contract(None, VaultContract));
+
+    let result = vault.try_initialize(&config, &xlm, &xlm, &false);
+    assert_eq!(result, Err(Ok(VaultError::InvalidConfiguration)));
+}
+
+fn rule_fires(env: &Env, vault: &Address, condition_type: &str, threshold: i128, target_allocation: Vec<i128>) -> bool {
+    let rule = RebalanceRule {
+        condition_type: String::from_str(env, condition_type),
+        threshold,
+        action: String::from_str(env, "rebalance"),
+        target_allocation,
+    };
+    env.as_contract(vault, || crate::engine::evaluate_rules(env, &vec![env, rule]))
+}
+
+#[test]
+fn test_time_elapsed_rule_fires_only_after_threshold() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 10_000);
+    vault.deposit(&user, &10_000);
+
+    env.ledger().with_mut(|l| l.timestamp = 1_000);
+    vault.force_rebalance_to(&owner, &vec![&env, 100_0000]);
+
+    env.ledger().with_mut(|l| l.timestamp = 1_000 + 3_599);
+    assert!(!rule_fires(&env, &vault.address, "time_elapsed", 3_600, Vec::new(&env)));
+
+    env.ledger().with_mut(|l| l.timestamp = 1_000 + 3_600);
+    assert!(rule_fires(&env, &vault.address, "time_elapsed", 3_600, Vec::new(&env)));
+}
+
+#[test]
+fn test_drift_rule_fires_only_past_threshold() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    // 60/40 in raw balances against a 50/50 target is 1000 bps of drift
+    mint(&env, &xlm, &vault.address, 6_000);
+    mint(&env, &tkn, &vault.address, 4_000);
+    let target = vec![&env, 50_0000, 50_0000];
+
+    assert!(rule_fires(&env, &vault.address, "drift", 1_000, target.clone()));
+    assert!(!rule_fires(&env, &vault.address, "drift", 1_001, target));
+}
+
+/// Vault holding only xlm with half of it staked in a fresh staking pool
+fn staked_vault<'a>(env: &Env, owner: &Address) -> (VaultContractClient<'a>, Address, Address) {
+    let xlm = create_token(env);
+    let factory = register_factory(env);
+
+    let pool = env.register_contract(None, MockStakingPool);
+    MockStakingPoolClient::new(env, &pool).init(&xlm);
+
+    let mut config = test_config(env, owner, vec![env, xlm.clone()], &factory);
+    config.staking_pool_address = Some(pool.clone());
+    config.rules = vec![env, RebalanceRule {
+        condition_type: String::from_str(env, "time"),
+        threshold: 50_0000,
+        action: String::from_str(env, "stake"),
+        target_allocation: Vec::new(env),
+    }];
+    let vault = create_vault(env, &config, &xlm, &xlm);
+
+    let user = Address::generate(env);
+    mint(env, &xlm, &user, 100_000);
+    vault.deposit(&user, &100_000);
+    vault.trigger_stake();
+    (vault, xlm, pool)
+}
+
+#[test]
+fn test_compound_reinvests_accrued_staking_rewards() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let (vault, xlm, pool) = staked_vault(&env, &owner);
+    assert_eq!(vault.get_staking_position().staked_amount, 50_000);
+
+    // Nothing accrued yet
+    assert_eq!(vault.compound(&owner), 0);
+
+    let funder = Address::generate(&env);
+    mint(&env, &xlm, &funder, 2_000);
+    MockStakingPoolClient::new(&env, &pool).accrue_rewards(&funder, &vault.address, &2_000);
+
+    let total_before = vault.get_state().total_value;
+    assert_eq!(vault.compound(&owner), 2_000);
+
+    let position = vault.get_staking_position();
+    assert_eq!(position.staked_amount, 52_000);
+    assert_eq!(position.st_token_amount, 52_000);
+    assert_eq!(MockStakingPoolClient::new(&env, &pool).get_staked_balance(&vault.address), 52_000);
+    assert_eq!(balance(&env, &xlm, &vault.address), 50_000);
+    assert_eq!(vault.get_state().total_value, total_before + 2_000);
+}
+
+#[test]
+fn test_sweep_dust_sends_leftovers_to_fee_recipient() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let fee_recipient = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+
+    let mut config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    config.sweep_dust = true;
+    config.fee_recipient = Some(fee_recipient.clone());
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 100_000);
+    vault.deposit(&user, &100_000);
+
+    // Too little tkn to be worth selling
+    mint(&env, &tkn, &vault.address, 999);
+    vault.force_rebalance_to(&owner, &vec![&env, 100_0000, 0]);
+
+    assert_eq!(balance(&env, &tkn, &fee_recipient), 999);
+    assert_eq!(balance(&env, &tkn, &vault.address), 0);
+    assert_eq!(balance(&env, &xlm, &vault.address), 100_000);
+    assert_eq!(balance(&env, &xlm, &fee_recipient), 0);
+}
+
+/// Vault holding an LP position of 1_000 xlm + 2_000 tkn (3_000 LP) in a fresh liquidity pool
+fn lp_position_vault<'a>(env: &Env, owner: &Address) -> (VaultContractClient<'a>, Address, Address, Address) {
+    let xlm = create_token(env);
+    let tkn = create_token(env);
+    let factory = register_factory(env);
+
+    let config = test_config(env, owner, vec![env, xlm.clone(), tkn.clone()], &factory);
+    let vault = create_vault(env, &config, &xlm, &xlm);
+    let pool = env.register_contract(None, MockLpPool);
+    vault.set_liquidity_pool(owner, &pool);
+
+    mint(env, &xlm, &vault.address, 1_000);
+    mint(env, &tkn, &vault.address, 2_000);
+    env.mock_all_auths_allowing_non_root_auth();
+    env.as_contract(&vault.address, || {
+        let (lp_tokens, amount_a, amount_b) =
+            crate::liquidity_router::add_liquidity_to_pool(env, &pool, &xlm, &tkn, 1_000, 2_000, 0).unwrap();
+        let position = crate::LiquidityPosition {
+            pool_address: pool.clone(),
+            token_a: xlm.clone(),
+            token_b: tkn.clone(),
+            lp_tokens,
+            amount_a_provided: amount_a,
+            amount_b_provided: amount_b,
+            timestamp: env.ledger().timestamp(),
+        };
+        env.storage().instance().set(&String::from_str(env, "lp_position"), &position);
+    });
+    (vault, xlm, tkn, pool)
+}
+
+#[test]
+fn test_remove_liquidity_position_rejects_bad_inputs() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let (vault, xlm, _, pool) = lp_position_vault(&env, &owner);
+
+    assert_eq!(vault.try_remove_liquidity_position(&owner, &50, &10_001), Err(Ok(VaultError::InvalidConfiguration)));
+    assert_eq!(vault.try_remove_liquidity_position(&owner, &50, &-1), Err(Ok(VaultError::InvalidConfiguration)));
+    assert_eq!(vault.try_remove_liquidity_position(&owner, &101, &50), Err(Ok(VaultError::InvalidAmount)));
+    assert_eq!(
+        vault.try_remove_liquidity_position(&Address::generate(&env), &50, &50),
+        Err(Ok(VaultError::Unauthorized))
+    );
+
+    // Nothing left the pool
+    assert_eq!(vault.get_liquidity_position().lp_tokens, 3_000);
+    assert_eq!(balance(&env, &xlm, &pool), 1_000);
+}
+
+#[test]
+fn test_remove_liquidity_position_pays_out_and_shrinks_position() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let (vault, xlm, tkn, _) = lp_position_vault(&env, &owner);
+
+    assert_eq!(vault.remove_liquidity_position(&owner, &50, &50), (500, 1_000));
+    assert_eq!(vault.get_liquidity_position().lp_tokens, 1_500);
+    assert_eq!(balance(&env, &xlm, &vault.address), 500);
+    assert_eq!(balance(&env, &tkn, &vault.address), 1_000);
+
+    assert_eq!(vault.remove_liquidity_position(&owner, &100, &50), (500, 1_000));
+    assert!(vault.try_get_liquidity_position().is_err());
+    assert_eq!(balance(&env, &xlm, &vault.address), 1_000);
+    assert_eq!(balance(&env, &tkn, &vault.address), 2_000);
+}
+
+#[test]
+fn test_ensure_allowance_skips_sufficient_approval() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+    let router = Address::generate(&env);
+    let token_client = token::Client::new(&env, &xlm);
+
+    env.as_contract(&vault.address, || {
+        assert_eq!(crate::token_client::ensure_allowance(&env, &xlm, &router, 1_000), Ok(true));
+        assert_eq!(crate::token_client::ensure_allowance(&env, &xlm, &router, 500), Ok(false));
+    });
+    assert_eq!(token_client.allowance(&vault.address, &router), 1_000);
+
+    env.as_contract(&vault.address, || {
+        assert_eq!(crate::token_client::ensure_allowance(&env, &xlm, &router, 2_000), Ok(true));
+    });
+    assert_eq!(token_client.allowance(&vault.address, &router), 2_000);
+}
+
+#[test]
+fn test_get_spot_prices_reads_each_pool() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let usdc = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    // usdc at 4 xlm, tkn at 0.5 xlm (listed quote-first), and a pool without xlm
+    let usdc_pool = create_pair(&env, &factory, &usdc, 1_000_000, &xlm, 4_000_000);
+    let tkn_pool = create_pair(&env, &factory, &xlm, 1_000_000, &tkn, 2_000_000);
+    let unrelated = create_pair(&env, &factory, &usdc, 1_000_000, &tkn, 1_000_000);
+
+    let prices = vault.get_spot_prices(&vec![&env, usdc_pool, tkn_pool, unrelated], &xlm);
+    assert_eq!(prices, vec![&env, 40_000_000, 5_000_000, 0]);
+}
+
+#[test]
+fn test_get_amount_in_round_trips_through_amount_out() {
+    let env = setup_env();
+    let xlm = create_token(&env);
+    let usdc = create_token(&env);
+    let factory = register_factory(&env);
+    let pair = create_pair(&env, &factory, &xlm, 10_000_000, &usdc, 3_000_000);
+
+    for amount_out in [1_i128, 777, 50_000, 1_000_000] {
+        let amount_in = crate::pool_client::get_amount_in(&env, &pair, &usdc, amount_out).unwrap();
+        let received = crate::pool_client::calculate_swap_output(&env, &pair, &xlm, &usdc, amount_in).unwrap();
+        assert!(received >= amount_out);
+
+        // One stroop less must fall short, so the input is the minimum within 1 stroop
+        let short = crate::pool_client::calculate_swap_output(&env, &pair, &xlm, &usdc, amount_in - 1).unwrap();
+        assert!(short <= amount_out);
+    }
+}
+
+#[test]
+fn test_underlying_breakdown_values_two_assets_in_base() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let usdc = create_token(&env);
+    let factory = register_factory(&env);
+    // 1 usdc = 4 xlm
+    create_pair(&env, &factory, &usdc, 10_000_000, &xlm, 40_000_000);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone(), usdc.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+    mint(&env, &xlm, &vault.address, 30_000);
+    mint(&env, &usdc, &vault.address, 10_000);
+
+    let breakdown = vault.get_underlying_breakdown();
+    let xlm_row = breakdown.get(0).unwrap();
+    let usdc_row = breakdown.get(1).unwrap();
+    assert_eq!((xlm_row.token, xlm_row.balance, xlm_row.value_base), (xlm, 30_000, 30_000));
+    assert_eq!((usdc_row.token, usdc_row.balance, usdc_row.value_base), (usdc, 10_000, 40_000));
+
+    // 30k : 40k of base value; integer rounding may shave a bp
+    assert_eq!(xlm_row.pct_bps, 4_285);
+    assert_eq!(usdc_row.pct_bps, 5_714);
+    assert!((9_999..=10_000).contains(&(xlm_row.pct_bps + usdc_row.pct_bps)));
+}
+
+#[test]
+fn test_non_router_address_surfaces_router_call_failed() {
+    let env = setup_env();
+    // A live contract without the router interface
+    let not_a_router = env.register_contract(None, MockOracle);
+    let (vault, xlm, token_a) = router_only_vault(&env, &not_a_router, RouterKind::Soroswap);
+
+    let result = vault.try_execute_rebalance_step(&direct_step(&env, &token_a, &xlm, 10_000));
+
+    assert_eq!(result, Err(Ok(VaultError::RouterCallFailed)));
+    assert_eq!(balance(&env, &token_a, &vault.address), 50_000);
+    assert_eq!(balance(&env, &xlm, &vault.address), 0);
+}
+
+#[test]
+fn test_share_of_splits_three_to_one() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    mint(&env, &xlm, &alice, 3_000_000);
+    mint(&env, &xlm, &bob, 1_000_000);
+    vault.deposit(&bob, &1_000_000);
+    vault.deposit(&alice, &3_000_000);
+
+    // The first depositor's 1000 dead shares stay in the total but belong to no one
+    assert_eq!(vault.share_of(&alice), 75_0000);
+    assert_eq!(vault.share_of(&bob), 24_9750);
+    assert_eq!(vault.share_of(&Address::generate(&env)), 0);
+}
+
+#[test]
+fn test_config_address_getters() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let factory = register_factory(&env);
+    let staking_pool = Address::generate(&env);
+
+    let mut config = test_config(&env, &owner, vec![&env, xlm.clone()], &factory);
+    config.staking_pool_address = Some(staking_pool.clone());
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    assert_eq!(vault.get_router(), config.router_address);
+    assert_eq!(vault.get_factory(), Some(factory));
+    assert_eq!(vault.get_staking_pool(), Some(staking_pool));
+
+    config.router_address = None;
+    config.staking_pool_address = None;
+    let bare = create_vault(&env, &config, &xlm, &xlm);
+    assert_eq!(bare.get_router(), None);
+    assert_eq!(bare.get_staking_pool(), None);
+}
+
+#[test]
+fn test_total_value_in_prices_every_asset_through_pools() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let usdc = create_token(&env);
+    let factory = register_factory(&env);
+    // 1 usdc = 4 xlm
+    create_pair(&env, &factory, &usdc, 10_000_000, &xlm, 40_000_000);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone(), usdc.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+    mint(&env, &xlm, &vault.address, 20_000);
+    mint(&env, &usdc, &vault.address, 5_000);
+
+    // Same holdings, priced from either side of the pair
+    assert_eq!(vault.total_value_in(&xlm), 20_000 + 5_000 * 4);
+    assert_eq!(vault.total_value_in(&usdc), 20_000 / 4 + 5_000);
+}
+
+/// Data of the last event published under (topic, user), decoded as T
+fn last_user_event<T: TryFromVal<Env, Val>>(env: &Env, topic: Symbol, user: &Address) -> Option<T> {
+    let topics: Vec<Val> = (topic, user.clone()).into_val(env);
+    env.events().all().iter()
+        .filter(|(_, event_topics, _)| *event_topics == topics)
+        .last()
+        .and_then(|(_, _, data)| T::try_from_val(env, &data).ok())
+}
+
+#[test]
+fn test_cross_token_deposit_emits_deposit_token_event() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+    create_pair(&env, &factory, &tkn, 1_000_000, &xlm, 1_000_000);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let user = Address::generate(&env);
+    mint(&env, &tkn, &user, 5_000);
+    vault.deposit_with_token(&user, &5_000, &tkn);
+
+    // No swap runs at deposit time, so the credited amount is the amount deposited
+    let event: Option<(Address, i128, i128)> = last_user_event(&env, symbol_short!("dep_token"), &user);
+    assert_eq!(event, Some((tkn, 5_000, 5_000)));
+    assert_eq!(last_user_event::<(Address, i128, i128)>(&env, symbol_short!("dep_token"), &owner), None);
+}
+
+#[test]
+fn test_remove_liquidity_position_reverts_when_pool_underdelivers() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let (vault, xlm, tkn, pool) = lp_position_vault(&env, &owner);
+
+    // Pays 5% short of the position's share of reserves
+    MockLpPoolClient::new(&env, &pool).set_skim(&500);
+    assert_eq!(vault.try_remove_liquidity_position(&owner, &50, &100), Err(Ok(VaultError::SlippageTooHigh)));
+
+    // The whole removal rolled back
+    assert_eq!(vault.get_liquidity_position().lp_tokens, 3_000);
+    assert_eq!(balance(&env, &xlm, &pool), 1_000);
+    assert_eq!(balance(&env, &tkn, &pool), 2_000);
+    assert_eq!(balance(&env, &xlm, &vault.address), 0);
+
+    // The same payout is accepted once it is within tolerance
+    assert_eq!(vault.remove_liquidity_position(&owner, &50, &500), (475, 950));
+}
+
+#[test]
+fn test_initialize_caps_asset_count() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let factory = register_factory(&env);
+
+    let mut assets = vec![&env, xlm.clone()];
+    while assets.len() < 10 {
+        assets.push_back(create_token(&env));
+    }
+    let mut config = test_config(&env, &owner, assets.clone(), &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+    assert_eq!(vault.get_config().assets.len(), 10);
+
+    assets.push_back(create_token(&env));
+    config.assets = assets;
+    let oversized = VaultContractClient::new(&env, &env.register_contract(None, VaultContract));
+    assert_eq!(
+        oversized.try_initialize(&config, &xlm, &xlm, &false),
+        Err(Ok(VaultError::InvalidConfiguration))
+    );
+}
+
+#[test]
+fn test_staking_position_is_valued_at_live_exchange_rate() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let (vault, _, pool) = staked_vault(&env, &owner);
+    assert_eq!(vault.get_staking_position().current_value, 50_000);
+
+    // 1 st-token now redeems for 1.1 xlm
+    MockStakingPoolClient::new(&env, &pool).set_rate(&11, &10);
+    assert_eq!(crate::staking_client::get_exchange_rate(&env, &pool), 11_000_000);
+
+    let position = vault.get_staking_position();
+    assert_eq!(position.staked_amount, 50_000);
+    assert_eq!(position.current_value, 55_000);
+    assert!(position.current_value > position.staked_amount);
+    assert_eq!(vault.nav(), 50_000 + 55_000);
+}
+
+#[test]
+fn test_config_setters_fail_after_lock() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+    let router = Address::generate(&env);
+    vault.set_router(&router);
+
+    vault.lock_config(&owner);
+    // Locking again is a no-op
+    vault.lock_config(&owner);
+
+    let other = Address::generate(&env);
+    assert_eq!(vault.try_set_router(&other), Err(Ok(VaultError::ConfigLocked)));
+    assert_eq!(vault.try_set_factory(&owner, &other), Err(Ok(VaultError::ConfigLocked)));
+    assert_eq!(vault.try_set_staking_pool(&owner, &other), Err(Ok(VaultError::ConfigLocked)));
+    assert_eq!(vault.try_set_liquidity_pool(&owner, &other), Err(Ok(VaultError::ConfigLocked)));
+
+    assert_eq!(vault.get_router(), Some(router));
+    assert_eq!(vault.get_factory(), Some(factory));
+    assert_eq!(vault.get_staking_pool(), None);
+}
+
+#[test]
+fn test_rebalance_step_falls_back_to_direct_pool_without_router_route() {
+    let env = setup_env();
+    // The router can't route anything and the hardcoded factory has no pair
+    let not_a_router = env.register_contract(None, MockOracle);
+    let (vault, xlm, token_a) = router_only_vault(&env, &not_a_router, RouterKind::Soroswap);
+
+    // A direct pool exists only in the vault's configured factory
+    let factory = env.register_contract(None, MockFactory);
+    create_pair(&env, &factory, &token_a, 1_000_000, &xlm, 1_000_000);
+    vault.set_factory(&vault.get_config().owner, &factory);
+
+    let mut step = direct_step(&env, &token_a, &xlm, 10_000);
+    step.min_amount_out = 9_000;
+    let amount_out = vault.execute_rebalance_step(&step);
+
+    assert!(amount_out >= 9_000);
+    assert_eq!(balance(&env, &token_a, &vault.address), 40_000);
+    assert_eq!(balance(&env, &xlm, &vault.address), amount_out);
+
+    let swap_path: Vec<Val> = (symbol_short!("swap_path"),).into_val(&env);
+    let used = env.events().all().iter()
+        .filter(|(_, topics, _)| *topics == swap_path)
+        .last()
+        .and_then(|(_, _, data)| <(Symbol, Address, Address)>::try_from_val(&env, &data).ok());
+    assert_eq!(used, Some((symbol_short!("pool"), token_a, xlm)));
+}
+
+#[test]
+fn test_compute_min_amounts_applies_slippage() {
+    use crate::liquidity_router::compute_min_amounts;
+
+    assert_eq!(compute_min_amounts(1000, 500, 5), Ok((950, 475)));
+    assert_eq!(compute_min_amounts(1000, 500, 0), Ok((1000, 500)));
+    assert_eq!(compute_min_amounts(1000, 500, 101), Err(VaultError::InvalidConfiguration));
+}
+
+#[test]
+fn test_deposit_with_unroutable_token_fails_before_transfer() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+
+    // No pool pairs tkn with the base token
+    let config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    // A user holding none of the token gets the route error, not a failed transfer
+    let empty = Address::generate(&env);
+    assert_eq!(vault.try_deposit_with_token(&empty, &5_000, &tkn), Err(Ok(VaultError::NoRouteFound)));
+
+    let user = Address::generate(&env);
+    mint(&env, &tkn, &user, 5_000);
+    assert_eq!(vault.try_deposit_with_token(&user, &5_000, &tkn), Err(Ok(VaultError::NoRouteFound)));
+    assert_eq!(balance(&env, &tkn, &user), 5_000);
+    assert_eq!(balance(&env, &tkn, &vault.address), 0);
+    assert_eq!(vault.get_state().total_shares, 0);
+}
+
+/// Vault in the given unstake mode with half of a 100_000 deposit staked, after its only
+/// depositor redeems 40_000 shares; returns (vault, xlm, pool, payout)
+fn withdraw_from_staked_vault<'a>(env: &Env, unstake_mode: UnstakeMode) -> (VaultContractClient<'a>, Address, Address, i128) {
+    let owner = Address::generate(env);
+    let xlm = create_token(env);
+    let factory = register_factory(env);
+
+    let pool = env.register_contract(None, MockStakingPool);
+    MockStakingPoolClient::new(env, &pool).init(&xlm);
+
+    let mut config = test_config(env, &owner, vec![env, xlm.clone()], &factory);
+    config.staking_pool_address = Some(pool.clone());
+    config.unstake_mode = unstake_mode;
+    config.rules = vec![env, RebalanceRule {
+        condition_type: String::from_str(env, "time"),
+        threshold: 50_0000,
+        action: String::from_str(env, "stake"),
+        target_allocation: Vec::new(env),
+    }];
+    let vault = create_vault(env, &config, &xlm, &xlm);
+
+    let user = Address::generate(env);
+    mint(env, &xlm, &user, 100_000);
+    vault.deposit(&user, &100_000);
+    vault.trigger_stake();
+
+    let payout = vault.withdraw(&user, &40_000);
+    (vault, xlm, pool, payout)
+}
+
+#[test]
+fn test_full_unstake_mode_unwinds_whole_position_on_partial_withdrawal() {
+    let env = setup_env();
+    let (vault, xlm, pool, payout) = withdraw_from_staked_vault(&env, UnstakeMode::Full);
+
+    assert_eq!(payout, 40_000);
+    assert!(vault.try_get_staking_position().is_err());
+    assert_eq!(MockStakingPoolClient::new(&env, &pool).get_staked_balance(&vault.address), 0);
+    assert_eq!(balance(&env, &xlm, &vault.address), 100_000 - 40_000);
+}
+
+#[test]
+fn test_proportional_unstake_mode_unwinds_redeemed_slice() {
+    let env = setup_env();
+    let (vault, xlm, pool, payout) = withdraw_from_staked_vault(&env, UnstakeMode::Proportional);
+
+    // 40% of the shares unwind 40% of the 50_000 staked
+    assert_eq!(payout, 40_000);
+    assert_eq!(vault.get_staking_position().staked_amount, 30_000);
+    assert_eq!(MockStakingPoolClient::new(&env, &pool).get_staked_balance(&vault.address), 30_000);
+    assert_eq!(balance(&env, &xlm, &vault.address), 50_000 + 20_000 - 40_000);
+}
+
+#[test]
+fn test_real_pool_quote_rejects_empty_reserves() {
+    let env = setup_env();
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let pool = env.register_contract(None, MockCustomPool);
+    let client = MockCustomPoolClient::new(&env, &pool);
+    client.init(&xlm, &tkn);
+
+    let quote = |amount_in: i128| crate::real_pool_client::calculate_real_pool_output(&env, &pool, &xlm, &tkn, amount_in);
+    assert_eq!(quote(1_000), Err(VaultError::InsufficientLiquidity));
+
+    // One-sided reserves are no better
+    mint(&env, &tkn, &pool, 1_000_000);
+    client.sync();
+    assert_eq!(quote(1_000), Err(VaultError::InsufficientLiquidity));
+
+    mint(&env, &xlm, &pool, 1_000_000);
+    client.sync();
+    assert_eq!(quote(1_000), Ok(996));
+}
+
+#[test]
+fn test_list_depositors_tracks_deposits_and_full_withdrawals() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+    assert_eq!(vault.list_depositors(), Vec::new(&env));
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    mint(&env, &xlm, &alice, 20_000);
+    mint(&env, &xlm, &bob, 10_000);
+    vault.deposit(&alice, &10_000);
+    vault.deposit(&bob, &10_000);
+    // A repeat deposit doesn't list alice twice
+    vault.deposit(&alice, &10_000);
+    assert_eq!(vault.list_depositors(), vec![&env, alice.clone(), bob.clone()]);
+
+    // A partial withdrawal keeps bob listed, a full one drops alice
+    vault.withdraw(&bob, &5_000);
+    vault.withdraw(&alice, &vault.get_position(&alice).shares);
+    assert_eq!(vault.list_depositors(), vec![&env, bob.clone()]);
+
+    vault.withdraw(&bob, &vault.get_position(&bob).shares);
+    assert_eq!(vault.list_depositors(), Vec::new(&env));
+}
+
+#[test]
+fn test_get_reserves_for_pair_follows_requested_order() {
+    let env = setup_env();
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+    // Internally tkn is token_0 and xlm is token_1
+    let pair = create_pair(&env, &factory, &tkn, 2_000_000, &xlm, 5_000_000);
+
+    let reserves = |a: &Address, b: &Address| crate::pool_client::get_reserves_for_pair(&env, &pair, a, b);
+    assert_eq!(reserves(&xlm, &tkn), Ok((5_000_000, 2_000_000)));
+    assert_eq!(reserves(&tkn, &xlm), Ok((2_000_000, 5_000_000)));
+    assert_eq!(reserves(&xlm, &create_token(&env)), Err(VaultError::InvalidConfiguration));
+}
+
+#[test]
+fn test_deposit_minting_zero_shares_is_rejected() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let factory = register_factory(&env);
+
+    let mut config = test_config(&env, &owner, vec![&env, xlm.clone()], &factory);
+    config.min_deposit = 100;
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 10_000);
+    assert_eq!(vault.try_deposit(&user, &99), Err(Ok(VaultError::InvalidAmount)));
+    vault.deposit(&user, &2_000);
+
+    // A large donation reconciled into total_value makes each share worth ~5_000 xlm
+    mint(&env, &xlm, &vault.address, 10_000_000);
+    vault.reconcile(&owner);
+
+    let small = Address::generate(&env);
+    mint(&env, &xlm, &small, 4_000);
+    assert_eq!(vault.try_deposit(&small, &4_000), Err(Ok(VaultError::InvalidAmount)));
+    assert_eq!(balance(&env, &xlm, &small), 4_000);
+    assert_eq!(vault.get_position(&small).shares, 0);
+    assert_eq!(vault.get_state().total_shares, 2_000);
+}
+
+#[test]
+fn test_implied_apr_annualizes_history_growth() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+    assert_eq!(vault.implied_apr(), 0);
+
+    let seed = |records: Vec<crate::RebalanceRecord>| {
+        env.as_contract(&vault.address, || {
+            env.storage().instance().set(&symbol_short!("HISTORY"), &records);
+        });
+    };
+    let record = |timestamp: u64, total_value_after: i128| crate::RebalanceRecord { timestamp, total_value_after };
+    let half_year = 31_536_000 / 2;
+
+    seed(vec![&env, record(1_000, 1_000_000)]);
+    assert_eq!(vault.implied_apr(), 0);
+
+    // 5% over half a year is 10% a year
+    seed(vec![&env, record(1_000, 1_000_000), record(1_000 + half_year, 1_050_000)]);
+    assert_eq!(vault.implied_apr(), 1_000);
+
+    seed(vec![&env, record(1_000, 1_000_000), record(1_000 + half_year, 950_000)]);
+    assert_eq!(vault.implied_apr(), -1_000);
+}
+
+#[test]
+fn test_emergency_withdraw_event_matches_in_kind_transfers() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 100_000);
+    vault.deposit(&user, &100_000);
+    mint(&env, &tkn, &vault.address, 40_000);
+
+    let paid = vault.emergency_withdraw(&user, &50_000);
+
+    let event: Option<(i128, Vec<crate::AssetBalance>)> = last_user_event(&env, symbol_short!("wd_inkind"), &user);
+    let (shares, breakdown) = event.unwrap();
+    assert_eq!(shares, 50_000);
+    assert_eq!(breakdown, paid);
+    assert_eq!(breakdown.len(), 2);
+    for entry in breakdown.iter() {
+        assert_eq!(balance(&env, &entry.token, &user), entry.amount);
+    }
+    assert_eq!(balance(&env, &xlm, &user), 50_000);
+    assert_eq!(balance(&env, &tkn, &user), 20_000);
+}
+
+#[test]
+fn test_swap_path_via_router_passes_three_token_path() {
+    let env = setup_env();
+    let router = env.register_contract(None, MockSoroswapRouter);
+    let (vault, xlm, token_a) = router_only_vault(&env, &router, RouterKind::Soroswap);
+    let mid = create_token(&env);
+    let path = vec![&env, token_a.clone(), mid.clone(), xlm.clone()];
+
+    env.mock_all_auths_allowing_non_root_auth();
+    let amount_out = env.as_contract(&vault.address, || {
+        crate::swap_router::swap_path_via_router(&env, &router, &path, 10_000, 10_000)
+    });
+
+    assert_eq!(amount_out, Ok(10_000));
+    assert_eq!(MockSoroswapRouterClient::new(&env, &router).last_path(), path);
+    assert_eq!(balance(&env, &token_a, &vault.address), 40_000);
+    assert_eq!(balance(&env, &xlm, &vault.address), 10_000);
+    // The intermediate token never settles in the vault
+    assert_eq!(balance(&env, &mid, &vault.address), 0);
+}
+
+#[test]
+fn test_get_rule_reads_individual_rules() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+
+    let mut config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    config.rules = vec![
+        &env,
+        rebalance_rule(&env, vec![&env, 50_0000, 50_0000]),
+        rebalance_rule(&env, vec![&env, 70_0000, 30_0000]),
+    ];
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    assert_eq!(vault.rule_count(), 2);
+    assert_eq!(vault.get_rule(&0), config.rules.get(0).unwrap());
+    assert_eq!(vault.get_rule(&1), config.rules.get(1).unwrap());
+    assert_eq!(vault.try_get_rule(&2), Err(Ok(VaultError::InvalidConfiguration)));
+
+    config.rules = Vec::new(&env);
+    let passive = create_vault(&env, &config, &xlm, &xlm);
+    assert_eq!(passive.rule_count(), 0);
+    assert_eq!(passive.try_get_rule(&0), Err(Ok(VaultError::InvalidConfiguration)));
+}
+
+#[test]
+fn test_initialize_requires_rules_for_auto_managed_vaults() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let factory = register_factory(&env);
+
+    let mut config = test_config(&env, &owner, vec![&env, xlm.clone()], &factory);
+    let auto_managed = VaultContractClient::new(&env, &env.register_contract(None, VaultContract));
+    assert_eq!(
+        auto_managed.try_initialize(&config, &xlm, &xlm, &true),
+        Err(Ok(VaultError::InvalidConfiguration))
+    );
+
+    // Passive vaults may still go without rules
+    let passive = VaultContractClient::new(&env, &env.register_contract(None, VaultContract));
+    passive.initialize(&config, &xlm, &xlm, &false);
+
+    config.rules = vec![&env, rebalance_rule(&env, vec![&env, 100_0000])];
+    auto_managed.initialize(&config, &xlm, &xlm, &true);
+    assert_eq!(auto_managed.rule_count(), 1);
+}
+
+#[test]
+fn test_deposit_and_withdraw_events_filter_by_user_topic() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    mint(&env, &xlm, &alice, 10_000);
+    mint(&env, &xlm, &bob, 10_000);
+
+    vault.deposit(&alice, &10_000);
+    assert_eq!(last_user_event::<(i128, i128)>(&env, symbol_short!("deposit"), &alice), Some((10_000, 9_000)));
+    assert_eq!(last_user_event::<(i128, i128)>(&env, symbol_short!("deposit"), &bob), None);
+
+    vault.deposit(&bob, &10_000);
+    assert_eq!(last_user_event::<(i128, i128)>(&env, symbol_short!("deposit"), &bob), Some((10_000, 10_000)));
+
+    vault.withdraw(&alice, &4_000);
+    assert_eq!(last_user_event::<(i128, i128)>(&env, symbol_short!("withdraw"), &alice), Some((4_000, 4_000)));
+    assert_eq!(last_user_event::<(i128, i128)>(&env, symbol_short!("withdraw"), &bob), None);
+}
+
+#[test]
+fn test_position_value_reflects_appreciation() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 100_000);
+    vault.deposit(&user, &100_000);
+    assert_eq!(vault.position_value(&user), 99_000);
+
+    // A 10% gain shows up at live nav even though cached total_value hasn't moved
+    mint(&env, &xlm, &vault.address, 10_000);
+    assert_eq!(vault.get_state().total_value, 100_000);
+    assert_eq!(vault.position_value(&user), 99_000 * 110_000 / 100_000);
+    assert_eq!(vault.position_value(&Address::generate(&env)), 0);
+}
+
+#[test]
+fn test_set_liquidity_pool_updates_config() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+    assert_eq!(vault.get_config().liquidity_pool_address, None);
+
+    let pool = Address::generate(&env);
+    assert_eq!(
+        vault.try_set_liquidity_pool(&Address::generate(&env), &pool),
+        Err(Ok(VaultError::Unauthorized))
+    );
+    vault.set_liquidity_pool(&owner, &pool);
+    assert_eq!(vault.get_config().liquidity_pool_address, Some(pool));
+}
+
+#[test]
+fn test_withdraw_from_illiquid_vault_reverts_before_swapping() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+
+    let config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 100_000);
+    vault.deposit(&user, &100_000);
+
+    // Most of the value now sits in tkn, whose only pool is too thin to sell into
+    let sink = Address::generate(&env);
+    env.as_contract(&vault.address, || {
+        token::Client::new(&env, &xlm).transfer(&vault.address, &sink, &95_000);
+    });
+    mint(&env, &tkn, &vault.address, 95_000);
+    let pair = create_pair(&env, &factory, &tkn, 1_000, &xlm, 1_000);
+    // Any swap attempt would trap rather than return a vault error
+    MockPairClient::new(&env, &pair).set_broken();
+
+    assert_eq!(vault.try_withdraw(&user, &99_000), Err(Ok(VaultError::InsufficientLiquidity)));
+    assert_eq!(vault.get_position(&user).shares, 99_000);
+    assert_eq!(balance(&env, &tkn, &vault.address), 95_000);
+    assert_eq!(balance(&env, &xlm, &vault.address), 5_000);
+}
+
+#[test]
+fn test_extended_balances_include_staking_position() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let (vault, xlm, pool) = staked_vault(&env, &owner);
+
+    let balances = vault.get_extended_balances();
+    assert_eq!(balances.len(), 2);
+
+    let liquid = balances.get(0).unwrap();
+    assert_eq!((liquid.token, liquid.amount), (xlm, 50_000));
+    let staked = balances.get(1).unwrap();
+    assert_eq!((staked.token, staked.amount), (pool, vault.get_staking_position().st_token_amount));
+}
+
+/// Valid xlm/usdc config with a 50/50 rebalance rule; returns (config, xlm, usdc)
+fn preflight_config(env: &Env) -> (VaultConfig, Address, Address) {
+    let owner = Address::generate(env);
+    let xlm = create_token(env);
+    let usdc = create_token(env);
+    let factory = register_factory(env);
+
+    let mut config = test_config(env, &owner, vec![env, xlm.clone(), usdc.clone()], &factory);
+    config.rules = vec![env, rebalance_rule(env, vec![env, 50_0000, 50_0000])];
+    (config, xlm, usdc)
+}
+
+/// Run validate_config against a fresh, uninitialized vault
+fn preflight(env: &Env, config: &VaultConfig, base_token: &Address, native_token: &Address, require_rules: bool) -> Result<(), VaultError> {
+    let vault = VaultContractClient::new(env, &env.register_contract(None, VaultContract));
+    match vault.try_validate_config(config, base_token, native_token, &require_rules) {
+        Ok(Ok(())) => Ok(()),
+        Err(Ok(err)) => Err(err),
+        other => panic!("validate_config did not return cleanly: {:?}", other),
+    }
+}
+
+#[test]
+fn test_validate_config_accepts_valid_config_without_storing() {
+    let env = setup_env();
+    let (config, xlm, usdc) = preflight_config(&env);
+
+    let vault = VaultContractClient::new(&env, &env.register_contract(None, VaultContract));
+    vault.validate_config(&config, &usdc, &xlm, &true);
+    assert_eq!(vault.try_get_config(), Err(Ok(VaultError::NotInitialized)));
+}
+
+#[test]
+fn test_validate_config_rejects_empty_assets() {
+    let env = setup_env();
+    let (mut config, xlm, _) = preflight_config(&env);
+    config.assets = Vec::new(&env);
+    config.rules = Vec::new(&env);
+
+    assert_eq!(preflight(&env, &config, &xlm, &xlm, false), Err(VaultError::InvalidConfiguration));
+}
+
+#[test]
+fn test_validate_config_rejects_too_many_assets() {
+    let env = setup_env();
+    let (mut config, xlm, _) = preflight_config(&env);
+    while config.assets.len() <= 10 {
+        config.assets.push_back(create_token(&env));
+    }
+    config.rules = Vec::new(&env);
+
+    assert_eq!(preflight(&env, &config, &xlm, &xlm, false), Err(VaultError::InvalidConfiguration));
+}
+
+#[test]
+fn test_validate_config_rejects_base_or_native_outside_assets() {
+    let env = setup_env();
+    let (config, xlm, usdc) = preflight_config(&env);
+    let outsider = create_token(&env);
+
+    assert_eq!(preflight(&env, &config, &outsider, &xlm, false), Err(VaultError::InvalidConfiguration));
+    assert_eq!(preflight(&env, &config, &usdc, &outsider, false), Err(VaultError::InvalidConfiguration));
+}
+
+#[test]
+fn test_validate_config_rejects_out_of_range_liquidation_cap() {
+    let env = setup_env();
+    let (mut config, xlm, _) = preflight_config(&env);
+
+    config.max_liquidation_impact_bps = 10_000;
+    assert_eq!(preflight(&env, &config, &xlm, &xlm, false), Err(VaultError::InvalidConfiguration));
+    config.max_liquidation_impact_bps = -1;
+    assert_eq!(preflight(&env, &config, &xlm, &xlm, false), Err(VaultError::InvalidConfiguration));
+}
+
+#[test]
+fn test_validate_config_rejects_negative_min_rebalance_nav() {
+    let env = setup_env();
+    let (mut config, xlm, _) = preflight_config(&env);
+    config.min_rebalance_nav = -1;
+
+    assert_eq!(preflight(&env, &config, &xlm, &xlm, false), Err(VaultError::InvalidConfiguration));
+}
+
+#[test]
+fn test_validate_config_rejects_dust_sweep_without_fee_recipient() {
+    let env = setup_env();
+    let (mut config, xlm, _) = preflight_config(&env);
+    config.sweep_dust = true;
+
+    assert_eq!(preflight(&env, &config, &xlm, &xlm, false), Err(VaultError::InvalidConfiguration));
+}
+
+#[test]
+fn test_validate_config_rejects_missing_rules_when_required() {
+    let env = setup_env();
+    let (mut config, xlm, _) = preflight_config(&env);
+    config.rules = Vec::new(&env);
+
+    assert_eq!(preflight(&env, &config, &xlm, &xlm, true), Err(VaultError::InvalidConfiguration));
+    assert_eq!(preflight(&env, &config, &xlm, &xlm, false), Ok(()));
+}
+
+#[test]
+fn test_validate_config_rejects_misshapen_rule_allocation() {
+    let env = setup_env();
+    let (mut config, xlm, _) = preflight_config(&env);
+
+    for allocation in [
+        vec![&env, 100_0000],
+        vec![&env, 50_0000, 40_0000],
+        vec![&env, 150_0000, -50_0000],
+    ] {
+        config.rules = vec![&env, rebalance_rule(&env, allocation)];
+        assert_eq!(preflight(&env, &config, &xlm, &xlm, false), Err(VaultError::InvalidConfiguration));
+    }
+}
+
+#[test]
+fn test_force_rebalance_on_empty_vault_is_a_noop() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let tkn = create_token(&env);
+    let factory = register_factory(&env);
+    create_pair(&env, &factory, &tkn, 1_000_000, &xlm, 1_000_000);
+
+    let mut config = test_config(&env, &owner, vec![&env, xlm.clone(), tkn.clone()], &factory);
+    config.rules = vec![&env, rebalance_rule(&env, vec![&env, 50_0000, 50_0000])];
+    config.min_rebalance_nav = 1_000;
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let noop = crate::RebalanceOutcome { swaps_executed: 0, hit_budget_cap: false, converged: true };
+    assert_eq!(vault.force_rebalance(), noop);
+
+    // Dust below min_rebalance_nav is skipped the same way
+    mint(&env, &xlm, &vault.address, 999);
+    assert_eq!(vault.force_rebalance(), noop);
+    assert_eq!(balance(&env, &xlm, &vault.address), 999);
+    assert_eq!(vault.get_rebalance_history().len(), 0);
+    assert_eq!(vault.get_state().last_rebalance, 0);
+}
+
+#[test]
+fn test_recovered_staking_position_is_unwound_on_withdrawal() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let factory = register_factory(&env);
+
+    let pool = env.register_contract(None, MockStakingPool);
+    let pool_client = MockStakingPoolClient::new(&env, &pool);
+    pool_client.init(&xlm);
+
+    let mut config = test_config(&env, &owner, vec![&env, xlm.clone()], &factory);
+    config.staking_pool_address = Some(pool.clone());
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 100_000);
+    vault.deposit(&user, &100_000);
+
+    // Staked under the old key: the pool holds the stake but the vault doesn't track it
+    mint(&env, &xlm, &pool, 30_000);
+    pool_client.deposit(&vault.address, &30_000);
+    assert!(vault.try_get_staking_position().is_err());
+
+    vault.recover_staking_position(&owner, &pool, &30_000, &30_000);
+    assert_eq!(vault.get_staking_position().st_token_amount, 30_000);
+    // Only writes when nothing is tracked
+    assert_eq!(
+        vault.try_recover_staking_position(&owner, &pool, &30_000, &30_000),
+        Err(Ok(VaultError::InvalidConfiguration))
+    );
+
+    // A Full-mode withdrawal liquidates every position, now including the recovered one
+    vault.withdraw(&user, &99_000);
+    assert!(vault.try_get_staking_position().is_err());
+    assert_eq!(pool_client.get_staked_balance(&vault.address), 0);
+    assert_eq!(balance(&env, &xlm, &pool), 0);
+}
+
+#[test]
+fn test_effective_allocation_sits_between_start_and_target_after_capped_pass() {
+    let env = setup_env();
+    let owner = Address::generate(&env);
+    let xlm = create_token(&env);
+    let factory = register_factory(&env);
+
+    let mut assets = vec![&env, xlm.clone()];
+    for _ in 0..4 {
+        assets.push_back(create_token(&env));
+    }
+    let config = test_config(&env, &owner, assets.clone(), &factory);
+    let vault = create_vault(&env, &config, &xlm, &xlm);
+    for token in assets.iter().skip(1) {
+        create_custom_pool(&env, &vault, &owner, &token, 10_000_000, &xlm, 10_000_000);
+    }
+
+    let user = Address::generate(&env);
+    mint(&env, &xlm, &user, 100_000);
+    vault.deposit(&user, &100_000);
+    assert_eq!(vault.effective_allocation(), vec![&env, 100_0000, 0, 0, 0, 0]);
+
+    // The swap cap stops the pass after three of the four purchases
+    let target = vec![&env, 20_0000, 20_0000, 20_0000, 20_0000, 20_0000];
+    assert!(vault.force_rebalance_to(&owner, &target).hit_budget_cap);
+
+    // Partway there: xlm is still overweight and the last asset hasn't been bought yet
+    let effective = vault.effective_allocation();
+    let xlm_pct = effective.get(0).unwrap();
+    assert!(20_0000 < xlm_pct && xlm_pct < 100_0000, "xlm at {}", xlm_pct);
+    assert_eq!(effective.get(4).unwrap(), 0);
+
+    // The three bought assets sit at target, give or take pool fees shrinking the total
+    for i in 1..4 {
+        let pct = effective.get(i).unwrap();
+        assert!((pct - 20_0000).abs() <= 1_000, "asset {} at {}", i, pct);
+    }
+}
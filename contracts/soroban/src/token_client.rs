@@ -1,7 +1,19 @@
 // Token client utilities for interacting with Stellar Asset Contract tokens
-use soroban_sdk::{Address, Env, token};
+use soroban_sdk::{Address, Env, Symbol, symbol_short, token};
 use crate::errors::VaultError;
 
+/// Storage key for the allowance lifetime (in ledgers) set by the vault owner
+pub const ALLOWANCE_TTL: Symbol = symbol_short!("ALW_TTL");
+
+/// Default allowance lifetime when none is configured
+const DEFAULT_ALLOWANCE_TTL: u32 = 100;
+
+/// Expiration ledger for a new approval, using the configured lifetime
+fn allowance_expiration(env: &Env) -> u32 {
+    let ttl: u32 = env.storage().instance().get(&ALLOWANCE_TTL).unwrap_or(DEFAULT_ALLOWANCE_TTL);
+    env.ledger().sequence() + ttl
+}
+
 /// Transfer tokens from one address to another
 /// Uses the standard Stellar Asset Contract interface
 pub fn transfer_tokens(
@@ -78,7 +90,7 @@ pub fn approve_router(
 
     let token_client = token::TokenClient::new(env, token_address);
     let vault_address = env.current_contract_address();
-    let expiration_ledger = env.ledger().sequence() + 100;
+    let expiration_ledger = allowance_expiration(env);
     
     token_client.approve(&vault_address, router, &amount, &expiration_ledger);
     
@@ -98,13 +110,38 @@ pub fn approve_pool(
 
     let token_client = token::TokenClient::new(env, token_address);
     let vault_address = env.current_contract_address();
-    let expiration_ledger = env.ledger().sequence() + 100;
+    let expiration_ledger = allowance_expiration(env);
     
     token_client.approve(&vault_address, pool, &amount, &expiration_ledger);
     
     Ok(())
 }
 
+/// Approve spender (router or pool) only if its current allowance is below amount
+/// Expired allowances read as 0, so this also refreshes approvals that lapsed mid-operation
+/// Returns true if a new approval was issued
+pub fn ensure_allowance(
+    env: &Env,
+    token_address: &Address,
+    spender: &Address,
+    amount: i128,
+) -> Result<bool, VaultError> {
+    if amount <= 0 {
+        return Err(VaultError::InvalidAmount);
+    }
+
+    let vault_address = env.current_contract_address();
+    let token_client = token::TokenClient::new(env, token_address);
+    if token_client.allowance(&vault_address, spender) >= amount {
+        return Ok(false);
+    }
+
+    let expiration_ledger = allowance_expiration(env);
+    token_client.approve(&vault_address, spender, &amount, &expiration_ledger);
+
+    Ok(true)
+}
+
 /// Check if router has sufficient allowance
 #[allow(dead_code)]
 pub fn check_allowance(
@@ -117,6 +154,18 @@ pub fn check_allowance(
     token_client.allowance(&vault_address, router)
 }
 
+/// Check the pool's current allowance over the vault's tokens
+#[allow(dead_code)]
+pub fn pool_allowance(
+    env: &Env,
+    token_address: &Address,
+    pool: &Address,
+) -> i128 {
+    let vault_address = env.current_contract_address();
+    let token_client = token::TokenClient::new(env, token_address);
+    token_client.allowance(&vault_address, pool)
+}
+
 /// Check if an account has a trustline for a token
 /// Returns true if the account can receive the token (trustline exists)
 /// Returns false if the trustline is missing
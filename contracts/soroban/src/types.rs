@@ -12,6 +12,41 @@ pub struct VaultConfig {
     pub liquidity_pool_address: Option<Address>, // Liquidity pool for add/remove liquidity operations
     pub staking_pool_address: Option<Address>, // Liquid staking pool (e.g., stXLM)
     pub factory_address: Option<Address>, // Soroswap factory for finding pools
+    pub auto_rebalance_on_deposit: bool, // Run force_rebalance inside deposit instead of a second transaction
+    pub max_deposit_impact_bps: i128, // Max price impact of converting a non-base deposit to base (0 = no cap)
+    pub max_liquidation_impact_bps: i128, // Max price impact of each swap to native on withdrawal (0 = no cap)
+    pub unstake_mode: UnstakeMode, // How much of the staking position a withdrawal unwinds
+    pub min_deposit: i128, // Smallest accepted deposit amount (0 = no minimum)
+    pub max_total_value: Option<i128>, // Deposits may not push nav above this (None = uncapped)
+    pub source_strategy: SourceStrategy, // Which overweight asset a rebalance sells first
+    pub withdrawal_cooldown: u64, // Seconds a user must wait between withdrawals (0 = no cooldown)
+    pub router_kind: RouterKind, // Call shape of the contract at router_address
+    pub fee_recipient: Option<Address>, // Receives protocol revenue such as swept dust
+    pub sweep_dust: bool, // After force rebalances, send sub-threshold balances to fee_recipient
+    pub price_oracle: Option<Address>, // Preferred over pool reserves for pricing assets (None = pool prices only)
+    pub min_rebalance_nav: i128, // force_rebalance is a no-op while nav is below this (0 = only when empty)
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RouterKind {
+    Soroswap, // swap_exact_tokens_for_tokens(amount_in, amount_out_min, path, to, deadline)
+    Phoenix,  // Multihop swap(recipient, operations, max_spread_bps, amount)
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SourceStrategy {
+    FirstExcess, // First overweight asset in config order
+    DeepestPool, // Overweight asset whose pool holds the most of the asset being bought
+    LeastImpact, // Overweight asset whose swap moves its pool's price the least
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UnstakeMode {
+    Full,         // Unstake the whole position on any withdrawal
+    Proportional, // Unstake only the redeemed shares' slice of the position
 }
 
 #[contracttype]
@@ -37,6 +72,7 @@ pub struct RebalanceRule {
 pub struct UserPosition {
     pub shares: i128,
     pub last_deposit: u64,
+    pub last_withdrawal: u64, // Timestamp of the user's last withdrawal (0 = never)
 }
 
 #[contracttype]
@@ -54,6 +90,7 @@ pub struct StakingPosition {
     pub staked_amount: i128,      // Original amount staked
     pub st_token_amount: i128,    // Liquid staking tokens received (e.g., stXLM)
     pub timestamp: u64,           // When staked
+    pub current_value: i128,      // Redemption value of st_token_amount at the pool's live rate
 }
 
 #[contracttype]
@@ -76,6 +113,7 @@ pub struct RebalanceStep {
     pub amount_in: i128,          // Amount to swap
     pub min_amount_out: i128,     // Minimum amount to receive (with slippage)
     pub pool_address: Address,    // Pool to use for swap
+    pub path: Vec<Address>,       // Route from from_token to to_token; more than 2 entries = multi-hop
 }
 
 #[contracttype]
@@ -84,3 +122,51 @@ pub struct RebalancePlan {
     pub steps: Vec<RebalanceStep>, // Ordered list of swaps to execute
     pub total_steps: u32,          // Total number of steps
 }
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HealthReport {
+    pub value_drift_bps: i128,    // |total_value - nav| relative to total_value, in basis points
+    pub has_missing_pools: bool,  // A configured asset has no pool to the base token
+    pub solvent: bool,            // Outstanding shares are backed by a non-zero value
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AssetValue {
+    pub token: Address,
+    pub balance: i128,      // Raw vault balance of the token
+    pub value_base: i128,   // Balance valued in the base token at pool spot price (0 if no pool)
+    pub pct_bps: i128,      // Share of the vault's base-denominated value, in basis points
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PendingWithdrawal {
+    pub shares: i128,       // Shares burned when the request was queued
+    pub amount_owed: i128,  // Native token still owed to the user
+    pub requested_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DepositQuote {
+    pub base_amount_after_swap: i128, // Deposit converted to the base token at live reserves, after the pool fee
+    pub shares_minted: i128,          // Shares deposit would mint right now
+    pub route: Vec<Address>,          // Swap path from deposit token to base; a single entry means no swap
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RebalanceOutcome {
+    pub swaps_executed: u32,   // Swaps that actually went through
+    pub hit_budget_cap: bool,  // Stopped at the per-call swap cap with assets left to visit
+    pub converged: bool,       // Every asset ended within the negligible-diff threshold of target
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RebalanceRecord {
+    pub timestamp: u64,
+    pub total_value_after: i128, // Live nav right after the rebalance
+}
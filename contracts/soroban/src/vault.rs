@@ -1,13 +1,37 @@
 // Vault core contract functionality
 use soroban_sdk::{contract, contractimpl, Address, Env, Symbol, symbol_short, token, log};
 
-use crate::types::{VaultConfig, VaultState, UserPosition};
+use crate::types::{VaultConfig, VaultState, UnstakeMode, UserPosition, HealthReport, AssetValue, PendingWithdrawal, DepositQuote, RebalanceOutcome, RebalanceRecord, AssetBalance};
 use crate::errors::VaultError;
-use crate::events::{emit_deposit, emit_withdraw};
+use crate::events::{emit_deposit, emit_deposit_token, emit_withdraw, emit_withdraw_queued, emit_withdraw_filled, emit_withdraw_in_kind};
 
 const CONFIG: Symbol = symbol_short!("CONFIG");
 const STATE: Symbol = symbol_short!("STATE");
 const POSITION: Symbol = symbol_short!("POSITION");
+const LOCKED: Symbol = symbol_short!("LOCKED");
+const BASE: Symbol = symbol_short!("BASE");
+const NATIVE: Symbol = symbol_short!("NATIVE");
+const PENDING: Symbol = symbol_short!("PENDING");
+const CFG_LOCK: Symbol = symbol_short!("CFG_LOCK");
+const DEPOSITORS: Symbol = symbol_short!("DEPOSITRS");
+const HISTORY: Symbol = symbol_short!("HISTORY");
+const PAUSED: Symbol = symbol_short!("PAUSED");
+const RESERVED: Symbol = symbol_short!("RESERVED");
+
+/// Most assets a vault may hold; rebalance loops past this exceed the transaction budget
+const MAX_ASSETS: u32 = 10;
+
+/// Shares minted to no one on the first deposit to make share-price inflation uneconomical
+const DEAD_SHARES: i128 = 1000;
+
+/// Rebalance records kept for implied_apr; the oldest is dropped past this
+const MAX_HISTORY: u32 = 20;
+
+const SECONDS_PER_YEAR: i128 = 31_536_000;
+
+/// Withdrawals whose estimated payout covers less than this share (bps) of the nominal
+/// amount revert before any liquidation or swap is attempted
+const MIN_WITHDRAW_COVERAGE_BPS: i128 = 1_000;
 
 #[contract]
 pub struct VaultContract;
@@ -15,16 +39,22 @@ pub struct VaultContract;
 #[contractimpl]
 impl VaultContract {
     /// Initialize a new vault
-    pub fn initialize(env: Env, config: VaultConfig) -> Result<(), VaultError> {
+    /// base_token is the default deposit/valuation token, native_token is paid out on withdrawal;
+    /// both must be among the configured assets
+    /// require_rules rejects an empty rule set for vaults meant to be auto-managed
+    pub fn initialize(
+        env: Env,
+        config: VaultConfig,
+        base_token: Address,
+        native_token: Address,
+        require_rules: bool,
+    ) -> Result<(), VaultError> {
         // Check if already initialized
         if env.storage().instance().has(&CONFIG) {
             return Err(VaultError::AlreadyInitialized);
         }
 
-        // Validate configuration
-        if config.assets.is_empty() {
-            return Err(VaultError::InvalidConfiguration);
-        }
+        Self::check_config(&env, &config, &base_token, &native_token, require_rules)?;
 
         // Initialize vault state
         // Set last_rebalance to 0 so first trigger always works
@@ -38,6 +68,8 @@ impl VaultContract {
         // Store configuration and state
         env.storage().instance().set(&CONFIG, &config);
         env.storage().instance().set(&STATE, &state);
+        env.storage().instance().set(&BASE, &base_token);
+        env.storage().instance().set(&NATIVE, &native_token);
 
         // AUTO-REGISTER KNOWN CUSTOM POOLS ON TESTNET
         // This allows all vaults to immediately use custom tokens without manual setup
@@ -47,31 +79,113 @@ impl VaultContract {
         Ok(())
     }
 
+    /// Run every initialize check against a config without storing anything
+    /// Lets tooling preflight a vault before paying to deploy it
+    pub fn validate_config(
+        env: Env,
+        config: VaultConfig,
+        base_token: Address,
+        native_token: Address,
+        require_rules: bool,
+    ) -> Result<(), VaultError> {
+        Self::check_config(&env, &config, &base_token, &native_token, require_rules)
+    }
+
+    /// Shared by initialize and validate_config
+    fn check_config(
+        env: &Env,
+        config: &VaultConfig,
+        base_token: &Address,
+        native_token: &Address,
+        require_rules: bool,
+    ) -> Result<(), VaultError> {
+        if config.assets.is_empty() || config.assets.len() > MAX_ASSETS {
+            return Err(VaultError::InvalidConfiguration);
+        }
+        if !config.assets.contains(base_token) || !config.assets.contains(native_token) {
+            log!(env, "Base and native tokens must be vault assets");
+            return Err(VaultError::InvalidConfiguration);
+        }
+        if !(0..10_000).contains(&config.max_liquidation_impact_bps) {
+            return Err(VaultError::InvalidConfiguration);
+        }
+        if config.min_rebalance_nav < 0 {
+            return Err(VaultError::InvalidConfiguration);
+        }
+        if config.sweep_dust && config.fee_recipient.is_none() {
+            log!(env, "Dust sweeping needs a fee recipient");
+            return Err(VaultError::InvalidConfiguration);
+        }
+        // Auto-managed vaults with no rules would never do anything on trigger_*
+        if require_rules && config.rules.is_empty() {
+            log!(env, "Auto-managed vault needs at least one rule");
+            return Err(VaultError::InvalidConfiguration);
+        }
+
+        // Rule allocations drive force_rebalance, so they must line up with the assets
+        // An empty allocation leaves the rule without a target; all zeros is rejected below
+        for rule in config.rules.iter() {
+            if rule.target_allocation.is_empty() {
+                continue;
+            }
+            if let Err(err) = crate::rebalance::validate_allocation(&rule.target_allocation, config.assets.len()) {
+                log!(env, "Rule allocation must have one entry per asset summing to 100%");
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
     /// Deposit assets into the vault (with optional auto-swap)
     /// If deposit_token is different from base token, it will be swapped automatically
     pub fn deposit(env: Env, user: Address, amount: i128) -> Result<i128, VaultError> {
-        // Call deposit_with_token using the base asset (first asset)
-        let config: VaultConfig = env.storage().instance().get(&CONFIG)
-            .ok_or(VaultError::NotInitialized)?;
-        
-        if config.assets.is_empty() {
-            return Err(VaultError::InvalidConfiguration);
-        }
-        
-        let base_token = config.assets.get(0)
-            .ok_or(VaultError::InvalidConfiguration)?;
+        // Call deposit_with_token using the base token chosen at initialization
+        let base_token = Self::get_base_token(env.clone())?;
         
         Self::deposit_with_token(env, user, amount, base_token)
     }
 
-    /// Deposit with specific token (will auto-swap if not base asset)
+    /// Deposit with specific token
+    /// Non-base deposits are held as-is and converted by the next force_rebalance, so no swap
+    /// runs here; max_deposit_impact_bps bounds the conversion against live pool depth up front
     pub fn deposit_with_token(env: Env, user: Address, amount: i128, deposit_token: Address) -> Result<i128, VaultError> {
+        Self::deposit_internal(env, user, amount, deposit_token, 0)
+    }
+
+    /// Deposit with share-level slippage protection
+    /// Reverts with InsufficientShares if fewer than min_shares would be minted
+    pub fn deposit_protected(
+        env: Env,
+        user: Address,
+        amount: i128,
+        deposit_token: Address,
+        min_shares: i128,
+    ) -> Result<i128, VaultError> {
+        if min_shares < 0 {
+            return Err(VaultError::InvalidAmount);
+        }
+
+        Self::deposit_internal(env, user, amount, deposit_token, min_shares)
+    }
+
+    /// Shared deposit flow: transfer in, mint shares, record position
+    fn deposit_internal(
+        env: Env,
+        user: Address,
+        amount: i128,
+        deposit_token: Address,
+        min_shares: i128,
+    ) -> Result<i128, VaultError> {
         // Debug: Entry point
         env.events().publish((symbol_short!("debug"),), symbol_short!("start"));
         
         // Require authorization from the user first
         user.require_auth();
         env.events().publish((symbol_short!("debug"),), symbol_short!("auth_ok"));
+
+        // Block re-entry from the deposit token's transfer hook
+        Self::acquire_lock(&env)?;
         
         // Check vault is initialized
         if !env.storage().instance().has(&CONFIG) {
@@ -97,9 +211,46 @@ impl VaultContract {
         if config.assets.is_empty() {
             return Err(VaultError::InvalidConfiguration);
         }
+
+        // Reject dust deposits that would only create dust positions
+        if amount < config.min_deposit {
+            log!(&env, "Deposit {} below minimum {}", amount, config.min_deposit);
+            return Err(VaultError::InvalidAmount);
+        }
+
+        // The cap is measured in base-token value (oracle first, then pool price), the unit nav uses
+        let base_token = Self::get_base_token(env.clone())?;
+        let deposit_value = Self::value_in_token(&env, &config, &deposit_token, amount, &base_token);
+
+        // Capped vaults refuse deposits beyond the remaining room
+        if let Some(remaining) = Self::remaining_capacity(env.clone()) {
+            if deposit_value > remaining {
+                log!(&env, "Deposit worth {} exceeds remaining capacity {}", deposit_value, remaining);
+                return Err(VaultError::DepositCapExceeded);
+            }
+        }
         
         env.events().publish((symbol_short!("debug"),), symbol_short!("tok_ok"));
 
+        // Reject deposit tokens that can't be swapped to base before pulling any funds
+        // Otherwise the tx would only revert later, after the transfer
+        if deposit_token != base_token
+            && Self::pair_reserves(&env, &config, &deposit_token, &base_token).is_none()
+        {
+            log!(&env, "No route from deposit token to base token");
+            return Err(VaultError::NoRouteFound);
+        }
+
+        // Cap the price impact of converting a non-base deposit into the base token
+        // A whale deposit into a thin pool would otherwise swap with no slippage bound
+        if config.max_deposit_impact_bps > 0 && deposit_token != base_token {
+            let impact_bps = Self::price_impact_bps(&env, &config, &deposit_token, &base_token, amount)?;
+            if impact_bps > config.max_deposit_impact_bps {
+                log!(&env, "Deposit price impact {} bps exceeds cap {}", impact_bps, config.max_deposit_impact_bps);
+                return Err(VaultError::SlippageTooHigh);
+            }
+        }
+
         // Get vault address
         let vault_address = env.current_contract_address();
         env.events().publish((symbol_short!("debug"),), symbol_short!("addr_ok"));
@@ -112,7 +263,8 @@ impl VaultContract {
 
         // NO AUTO-SWAP: Keep deposit token as-is
         // The force_rebalance function will swap to target allocation in a separate transaction
-        // This is cleaner and more gas-efficient
+        // This is cleaner and more gas-efficient. Shares follow the raw amount: a spot-priced
+        // credit could be pumped inside the same transaction to mint extra shares
         let final_amount = amount;
 
         // Get current state
@@ -120,23 +272,44 @@ impl VaultContract {
             .ok_or(VaultError::NotInitialized)?;
 
         // Calculate shares to mint based on final amount (after swap if needed)
-        let shares = if state.total_shares == 0 {
-            final_amount // First deposit: 1:1 ratio
+        // First deposit: 1:1 ratio, minus DEAD_SHARES locked forever so the share
+        // price can't be cheaply inflated by a first depositor (ERC4626-style mitigation)
+        let (shares, dead_shares) = if state.total_shares == 0 {
+            if final_amount <= DEAD_SHARES {
+                log!(&env, "First deposit must exceed {} to seed dead shares", DEAD_SHARES);
+                return Err(VaultError::InvalidAmount);
+            }
+            (final_amount - DEAD_SHARES, DEAD_SHARES)
         } else {
             // shares = (final_amount * total_shares) / total_value
-            final_amount.checked_mul(state.total_shares)
-                .and_then(|v| v.checked_div(state.total_value))
-                .ok_or(VaultError::InvalidAmount)?
+            // mul_div keeps a 256-bit intermediate so large vaults don't overflow here
+            let shares = Self::mul_div(final_amount, state.total_shares, state.total_value)
+                .ok_or(VaultError::InvalidAmount)?;
+            (shares, 0)
         };
 
-        // Update state with final amount
+        // Integer division can round a small deposit down to nothing; never take tokens for 0 shares
+        if shares == 0 {
+            log!(&env, "Deposit {} would mint 0 shares", final_amount);
+            return Err(VaultError::InvalidAmount);
+        }
+
+        // Share-level slippage protection (min_shares = 0 disables it)
+        if shares < min_shares {
+            log!(&env, "Deposit minted too few shares: {} < min {}", shares, min_shares);
+            return Err(VaultError::InsufficientShares);
+        }
+
+        // Update state with final amount (dead shares count toward supply but belong to no one)
         state.total_shares = state.total_shares.checked_add(shares)
+            .and_then(|v| v.checked_add(dead_shares))
             .ok_or(VaultError::InvalidAmount)?;
         state.total_value = state.total_value.checked_add(final_amount)
             .ok_or(VaultError::InvalidAmount)?;
         state.last_deposit_token = Some(deposit_token.clone()); // Track deposit token for rebalancing
 
         // Update user position (position was already fetched at the start)
+        let is_new_depositor = position.shares == 0;
         position.shares = position.shares.checked_add(shares)
             .ok_or(VaultError::InvalidAmount)?;
         position.last_deposit = env.ledger().timestamp();
@@ -144,9 +317,13 @@ impl VaultContract {
         // Store updates
         env.storage().instance().set(&STATE, &state);
         env.storage().instance().set(&(POSITION, user.clone()), &position);
+        if is_new_depositor {
+            Self::add_depositor(&env, &user);
+        }
 
         // Emit event with final amount (no swap in deposit)
         emit_deposit(&env, &user, final_amount, shares);
+        emit_deposit_token(&env, &user, &deposit_token, amount, final_amount);
 
         // NOTE: Deposit does NOT swap tokens - it just accepts them as-is
         // The deposit token (e.g., XLM) stays as XLM in the vault
@@ -159,6 +336,14 @@ impl VaultContract {
         //   Transaction 2 (force_rebalance): Swap 50 XLM → AQX, 50 XLM → SLX
         //
         // This 2-transaction approach is cleaner and gives users control over when to rebalance
+        //
+        // Vaults with auto_rebalance_on_deposit set skip the second transaction:
+        // force_rebalance runs here (still capped at max swaps per rebalance), after the
+        // deposit's own lock is released so the rebalance runs as it would standalone
+        Self::release_lock(&env);
+        if config.auto_rebalance_on_deposit && config.assets.len() > 1 {
+            Self::force_rebalance(env.clone())?;
+        }
 
         Ok(shares)
     }
@@ -169,6 +354,9 @@ impl VaultContract {
     pub fn withdraw(env: Env, user: Address, shares: i128) -> Result<i128, VaultError> {
         // Require authorization from the user first
         user.require_auth();
+
+        // Block re-entry from tokens/pools called during liquidation
+        Self::acquire_lock(&env)?;
         
         // Check vault is initialized
         if !env.storage().instance().has(&CONFIG) {
@@ -185,6 +373,7 @@ impl VaultContract {
         if position.shares < shares {
             return Err(VaultError::InsufficientShares);
         }
+        Self::require_withdrawal_cooldown(&env, &position)?;
 
         // Get current state
         let mut state: VaultState = env.storage().instance().get(&STATE)
@@ -195,9 +384,9 @@ impl VaultContract {
             return Err(VaultError::InvalidAmount);
         }
 
-        // Calculate amount to return
-        // amount = (shares * total_value) / total_shares
-        let amount = shares.checked_mul(state.total_value)
+        // Calculate the book value to return, in base-token units
+        // amount_base = (shares * total_value) / total_shares
+        let amount_base = shares.checked_mul(state.total_value)
             .and_then(|v| v.checked_div(state.total_shares))
             .ok_or(VaultError::InvalidAmount)?;
 
@@ -209,11 +398,9 @@ impl VaultContract {
             return Err(VaultError::InvalidConfiguration);
         }
         
-        // Find XLM token in the vault's assets
-        // XLM is always the last asset we'll check, or we need to identify it properly
-        // For now, we'll try to find it by checking all assets and use the last one as fallback
-        // IMPORTANT: The vault MUST have XLM as one of its configured assets for withdrawals to work!
-        let xlm_token = Self::find_xlm_token(&env, &config)?;
+        // Withdrawals pay out in the native token chosen at initialization
+        let xlm_token = Self::get_native_token(env.clone())?;
+        let amount = Self::base_to_native(&env, &config, amount_base)?;
 
         // Get vault address
         let vault_address = env.current_contract_address();
@@ -224,21 +411,52 @@ impl VaultContract {
         
         log!(&env, "Withdrawal: shares={}, amount_needed={}, current_xlm_balance={}", shares, amount, current_balance);
         
+        // Fail fast on an illiquid vault instead of burning the budget on liquidation and swaps
+        let obtainable = Self::obtainable_native(&env, &config, &xlm_token)?;
+        let estimated_payout = if obtainable < amount {
+            Self::mul_div(obtainable, shares, state.total_shares)
+                .ok_or(VaultError::InvalidAmount)?
+        } else {
+            amount
+        };
+        let min_payout = Self::mul_div(amount, MIN_WITHDRAW_COVERAGE_BPS, 10_000)
+            .ok_or(VaultError::InvalidAmount)?;
+        if estimated_payout < min_payout {
+            log!(&env, "Vault too illiquid: estimated payout {} below minimum {}", estimated_payout, min_payout);
+            return Err(VaultError::InsufficientLiquidity);
+        }
+        
         // WITHDRAWAL STRATEGY: Always return XLM to avoid trustline issues
         // 1. Liquidate all positions (unstake, remove liquidity)
         // 2. Swap ALL non-XLM assets to XLM
         // 3. Send XLM to user (no trustline needed)
         
-        // Step 1: Liquidate ALL positions (not just proportional)
-        log!(&env, "Step 1: Liquidating all positions");
-        Self::liquidate_all_positions(&env, &config)?;
+        // Step 1: Liquidate positions according to the vault's unstake mode
+        match config.unstake_mode {
+            UnstakeMode::Full => {
+                log!(&env, "Step 1: Liquidating all positions");
+                Self::liquidate_all_positions(&env, &config)?;
+            },
+            UnstakeMode::Proportional => {
+                // Unwind only the slice of the staking position backing the redeemed shares
+                let staked_share = match env.storage().instance()
+                    .get::<_, crate::types::StakingPosition>(&soroban_sdk::String::from_str(&env, "stake_position"))
+                {
+                    Some(pos) => Self::mul_div(pos.staked_amount, shares, state.total_shares)
+                        .ok_or(VaultError::InvalidAmount)?,
+                    None => 0,
+                };
+                log!(&env, "Step 1: Liquidating proportional positions: {}", staked_share);
+                Self::liquidate_positions_for_withdrawal(&env, &config, staked_share)?;
+            },
+        }
         
         // Step 2: Swap ALL non-XLM assets to XLM
         log!(&env, "Step 2: Swapping all assets to XLM");
         Self::swap_all_assets_to_xlm(&env, &config, &xlm_token)?;
         
         // Step 3: Check final XLM balance
-        let final_xlm_balance = token_client.balance(&vault_address);
+        let final_xlm_balance = Self::spendable_native(&env, &xlm_token);
         log!(&env, "Final XLM balance after liquidation and swaps: {}", final_xlm_balance);
         
         // IMPORTANT: Use the actual XLM balance instead of the calculated amount
@@ -270,20 +488,28 @@ impl VaultContract {
         
         log!(&env, "Successfully transferred {} XLM to user (calculated: {}, actual: {})", actual_amount, amount, actual_amount);
 
-        // Update state using actual amount withdrawn
+        // Update state using actual amount withdrawn, back in base-token units
+        let value_out = if actual_amount == amount {
+            amount_base
+        } else {
+            Self::mul_div(amount_base, actual_amount, amount)
+                .ok_or(VaultError::InvalidAmount)?
+        };
         state.total_shares = state.total_shares.checked_sub(shares)
             .ok_or(VaultError::InvalidAmount)?;
-        state.total_value = state.total_value.checked_sub(actual_amount)
+        state.total_value = state.total_value.checked_sub(value_out)
             .ok_or(VaultError::InvalidAmount)?;
 
         // Update user position
         position.shares = position.shares.checked_sub(shares)
             .ok_or(VaultError::InvalidAmount)?;
+        position.last_withdrawal = env.ledger().timestamp();
 
         // Store updates
         env.storage().instance().set(&STATE, &state);
         if position.shares == 0 {
             env.storage().instance().remove(&(POSITION, user.clone()));
+            Self::remove_depositor(&env, &user);
         } else {
             env.storage().instance().set(&(POSITION, user.clone()), &position);
         }
@@ -291,101 +517,465 @@ impl VaultContract {
         // Emit event with actual amount withdrawn
         emit_withdraw(&env, &user, shares, actual_amount);
 
+        Self::release_lock(&env);
+
         Ok(actual_amount)
     }
-    
-    /// Find XLM token address in the vault's configured assets
-    /// XLM is identified by its contract address (native wrapped XLM)
-    fn find_xlm_token(
-        env: &Env,
-        config: &VaultConfig,
-    ) -> Result<Address, VaultError> {
-        if config.assets.is_empty() {
-            return Err(VaultError::InvalidConfiguration);
+
+    /// Exit path for when pools are broken: pays the user's share of every asset in-kind
+    /// Skips liquidation and swaps entirely, so the user needs trustlines for non-native assets
+    pub fn emergency_withdraw(env: Env, user: Address, shares: i128) -> Result<soroban_sdk::Vec<AssetBalance>, VaultError> {
+        user.require_auth();
+
+        Self::acquire_lock(&env)?;
+
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+
+        if shares <= 0 {
+            return Err(VaultError::InvalidAmount);
         }
-        
-        // XLM (native wrapped) address on testnet: CDLZFC3SYJYDZT7K67VZ75HPJVIEUVNIXF47ZG2FB2RMQQVU2HHGCYSC
-        use soroban_sdk::String;
-        let xlm_address_str = String::from_str(env, "CDLZFC3SYJYDZT7K67VZ75HPJVIEUVNIXF47ZG2FB2RMQQVU2HHGCYSC");
-        let xlm_address = Address::from_string(&xlm_address_str);
-        
-        // Check if XLM is in the vault's assets
-        let asset_count = config.assets.len();
-        for i in 0..asset_count {
-            if let Some(asset) = config.assets.get(i) {
-                if asset == xlm_address {
-                    log!(env, "Found XLM at asset index {}", i);
-                    return Ok(asset);
-                }
+
+        let mut position = Self::get_position(env.clone(), user.clone());
+        if position.shares < shares {
+            return Err(VaultError::InsufficientShares);
+        }
+        Self::require_withdrawal_cooldown(&env, &position)?;
+
+        let mut state: VaultState = env.storage().instance().get(&STATE)
+            .ok_or(VaultError::NotInitialized)?;
+
+        if state.total_shares == 0 {
+            return Err(VaultError::InvalidAmount);
+        }
+
+        // Proportional slice of each raw balance, computed before any transfer moves them
+        let vault_address = env.current_contract_address();
+        let mut paid: soroban_sdk::Vec<AssetBalance> = soroban_sdk::Vec::new(&env);
+        let native_token = Self::get_native_token(env.clone())?;
+        for asset in config.assets.iter() {
+            let balance = if asset == native_token {
+                Self::spendable_native(&env, &native_token)
+            } else {
+                crate::token_client::get_vault_balance(&env, &asset)
+            };
+            let amount = Self::mul_div(balance, shares, state.total_shares)
+                .ok_or(VaultError::InvalidAmount)?;
+            if amount > 0 {
+                token::TokenClient::new(&env, &asset).transfer(&vault_address, &user, &amount);
             }
+            paid.push_back(AssetBalance { token: asset, amount });
         }
-        
-        // Fallback: use last asset if XLM not found
-        log!(env, "XLM not found in assets, using last asset as fallback");
-        let xlm_candidate = config.assets.get(asset_count - 1)
-            .ok_or(VaultError::InvalidConfiguration)?;
-        
-        Ok(xlm_candidate)
+
+        // Book value leaves with the shares at the current share price
+        let value_out = Self::mul_div(shares, state.total_value, state.total_shares)
+            .ok_or(VaultError::InvalidAmount)?;
+
+        state.total_shares = state.total_shares.checked_sub(shares)
+            .ok_or(VaultError::InvalidAmount)?;
+        state.total_value = state.total_value.checked_sub(value_out)
+            .ok_or(VaultError::InvalidAmount)?;
+
+        position.shares = position.shares.checked_sub(shares)
+            .ok_or(VaultError::InvalidAmount)?;
+        position.last_withdrawal = env.ledger().timestamp();
+
+        env.storage().instance().set(&STATE, &state);
+        if position.shares == 0 {
+            env.storage().instance().remove(&(POSITION, user.clone()));
+            Self::remove_depositor(&env, &user);
+        } else {
+            env.storage().instance().set(&(POSITION, user.clone()), &position);
+        }
+
+        log!(&env, "Emergency withdrawal: {} shares paid in-kind", shares);
+        emit_withdraw_in_kind(&env, &user, shares, &paid, config.assets.len());
+
+        Self::release_lock(&env);
+
+        Ok(paid)
     }
-    
-    /// Liquidate ALL positions before withdrawal (unstake everything, remove all liquidity)
-    fn liquidate_all_positions(
-        env: &Env,
-        config: &VaultConfig,
-    ) -> Result<(), VaultError> {
-        use crate::staking_client;
-        
-        log!(env, "Liquidating ALL positions");
-        
-        // Unstake ALL tokens from staking pool if configured
-        if let Some(ref staking_pool) = config.staking_pool_address {
-            let staking_symbol = symbol_short!("STAKE");
-            
-            if let Some(staking_pos) = env.storage().instance().get::<_, crate::types::StakingPosition>(&staking_symbol) {
-                log!(env, "Unstaking ALL: {} tokens (st_tokens: {})", 
-                    staking_pos.staked_amount, 
-                    staking_pos.st_token_amount);
-                
-                // Unstake ALL st_tokens
-                match staking_client::unstake_tokens(
-                    env,
-                    staking_pool,
-                    staking_pos.st_token_amount // Burn ALL st_tokens
-                ) {
-                    Ok(tokens_received) => {
-                        log!(env, "Successfully unstaked all: {}", tokens_received);
-                        // Remove staking position
-                        env.storage().instance().remove(&staking_symbol);
-                    },
-                    Err(e) => {
-                        log!(env, "Failed to unstake: {:?}", e);
-                        // Continue anyway
-                    }
-                }
-            }
+
+    /// Queue a withdrawal for when the vault can't liquidate enough native token right away
+    /// Shares are burned now at the current share price; the owed amount is paid by fulfill_withdrawal
+    /// Returns the native token amount owed for these shares
+    pub fn request_withdrawal(env: Env, user: Address, shares: i128) -> Result<i128, VaultError> {
+        user.require_auth();
+        Self::acquire_lock(&env)?;
+
+        if shares <= 0 {
+            return Err(VaultError::InvalidAmount);
         }
-        
-        // Remove liquidity position from tracking
-        use soroban_sdk::String;
-        let position_key = String::from_str(env, "lp_position");
-        if env.storage().instance().has(&position_key) {
-            log!(env, "Releasing liquidity position for withdrawal");
-            env.storage().instance().remove(&position_key);
+
+        let mut position = Self::get_position(env.clone(), user.clone());
+        if position.shares < shares {
+            return Err(VaultError::InsufficientShares);
         }
-        
-        Ok(())
+        Self::require_withdrawal_cooldown(&env, &position)?;
+
+        let mut state: VaultState = env.storage().instance().get(&STATE)
+            .ok_or(VaultError::NotInitialized)?;
+        if state.total_shares == 0 {
+            return Err(VaultError::InvalidAmount);
+        }
+
+        // Lock in the claim at today's share price, owed in the native token
+        let value_out = Self::mul_div(shares, state.total_value, state.total_shares)
+            .ok_or(VaultError::InvalidAmount)?;
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+        let amount_owed = Self::base_to_native(&env, &config, value_out)?;
+
+        state.total_shares = state.total_shares.checked_sub(shares)
+            .ok_or(VaultError::InvalidAmount)?;
+        state.total_value = state.total_value.checked_sub(value_out)
+            .ok_or(VaultError::InvalidAmount)?;
+        position.shares = position.shares.checked_sub(shares)
+            .ok_or(VaultError::InvalidAmount)?;
+        position.last_withdrawal = env.ledger().timestamp();
+
+        // Merge with any claim already in the queue
+        let mut pending = Self::get_pending_withdrawal(env.clone(), user.clone())
+            .unwrap_or(PendingWithdrawal {
+                shares: 0,
+                amount_owed: 0,
+                requested_at: env.ledger().timestamp(),
+            });
+        pending.shares = pending.shares.checked_add(shares)
+            .ok_or(VaultError::InvalidAmount)?;
+        pending.amount_owed = pending.amount_owed.checked_add(amount_owed)
+            .ok_or(VaultError::InvalidAmount)?;
+
+        env.storage().instance().set(&STATE, &state);
+        if position.shares == 0 {
+            env.storage().instance().remove(&(POSITION, user.clone()));
+            Self::remove_depositor(&env, &user);
+        } else {
+            env.storage().instance().set(&(POSITION, user.clone()), &position);
+        }
+        env.storage().instance().set(&(PENDING, user.clone()), &pending);
+
+        // Earmark the owed native token so ordinary withdrawals can't spend it first
+        let reserved = Self::reserved_native(&env).checked_add(amount_owed)
+            .ok_or(VaultError::InvalidAmount)?;
+        env.storage().instance().set(&RESERVED, &reserved);
+
+        emit_withdraw_queued(&env, &user, shares, amount_owed);
+
+        Self::release_lock(&env);
+
+        Ok(amount_owed)
     }
-    
-    /// Liquidate positions to cover withdrawal shortfall (OLD - kept for compatibility)
-    #[allow(dead_code)]
-    fn liquidate_positions_for_withdrawal(
-        env: &Env,
-        config: &VaultConfig,
-        amount_needed: i128
-    ) -> Result<(), VaultError> {
-        use crate::staking_client;
-        
-        log!(env, "Liquidating positions to cover withdrawal. Amount needed: {}", amount_needed);
+
+    /// Pay out as much of a queued withdrawal as current liquidity allows
+    /// Any native shortfall is covered by selling a pro-rata slice of the other assets; the claim is cleared once fully paid
+    /// Returns the amount paid in this call
+    pub fn fulfill_withdrawal(env: Env, user: Address) -> Result<i128, VaultError> {
+        user.require_auth();
+        Self::acquire_lock(&env)?;
+
+        let mut pending = Self::get_pending_withdrawal(env.clone(), user.clone())
+            .ok_or(VaultError::InvalidAmount)?;
+
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+        let native_token = Self::get_native_token(env.clone())?;
+
+        let vault_address = env.current_contract_address();
+        let token_client = token::TokenClient::new(&env, &native_token);
+
+        let shortfall = pending.amount_owed - token_client.balance(&vault_address);
+        if shortfall > 0 {
+            Self::liquidate_for_shortfall(&env, &config, &native_token, shortfall)?;
+        }
+
+        let available = token_client.balance(&vault_address);
+        let paid = if available < pending.amount_owed { available } else { pending.amount_owed };
+        if paid > 0 {
+            token_client.transfer(&vault_address, &user, &paid);
+        }
+
+        pending.amount_owed -= paid;
+        if pending.amount_owed == 0 {
+            env.storage().instance().remove(&(PENDING, user.clone()));
+        } else {
+            env.storage().instance().set(&(PENDING, user.clone()), &pending);
+        }
+
+        let reserved = Self::reserved_native(&env) - paid;
+        env.storage().instance().set(&RESERVED, &reserved.max(0));
+
+        log!(&env, "Withdrawal fulfilled: paid={}, remaining={}", paid, pending.amount_owed);
+        emit_withdraw_filled(&env, &user, paid, pending.amount_owed);
+
+        Self::release_lock(&env);
+
+        Ok(paid)
+    }
+
+    /// Convert a base-token amount (the unit total_value is kept in) to the native token paid out
+    fn base_to_native(env: &Env, config: &VaultConfig, amount_base: i128) -> Result<i128, VaultError> {
+        let base_token = Self::get_base_token(env.clone())?;
+        let native_token = Self::get_native_token(env.clone())?;
+        if base_token == native_token {
+            return Ok(amount_base);
+        }
+
+        let amount = Self::value_in_token(env, config, &base_token, amount_base, &native_token);
+        if amount == 0 && amount_base > 0 {
+            log!(env, "No price from base to native token");
+            return Err(VaultError::PoolNotFound);
+        }
+        Ok(amount)
+    }
+
+    /// Native token earmarked for queued withdrawals
+    fn reserved_native(env: &Env) -> i128 {
+        env.storage().instance().get(&RESERVED).unwrap_or(0)
+    }
+
+    /// Native balance ordinary withdrawals may spend: everything not reserved for queued claims
+    fn spendable_native(env: &Env, native_token: &Address) -> i128 {
+        let balance = crate::token_client::get_vault_balance(env, native_token);
+        (balance - Self::reserved_native(env)).max(0)
+    }
+
+    /// Raise roughly `shortfall` native token by selling the same fraction of every other asset:
+    /// each sells balance * shortfall / (native value of all other assets), capped like swap_all_assets_to_xlm
+    fn liquidate_for_shortfall(
+        env: &Env,
+        config: &VaultConfig,
+        native_token: &Address,
+        shortfall: i128,
+    ) -> Result<(), VaultError> {
+        use crate::pool_client;
+
+        let mut other_native_value: i128 = 0;
+        for asset in config.assets.iter() {
+            if &asset == native_token {
+                continue;
+            }
+            let balance = crate::token_client::get_vault_balance(env, &asset);
+            other_native_value = other_native_value
+                .checked_add(Self::value_in_token(env, config, &asset, balance, native_token))
+                .ok_or(VaultError::InvalidAmount)?;
+        }
+        if other_native_value <= 0 {
+            return Ok(());
+        }
+
+        let factory_address = Self::resolve_factory(env, config);
+        for asset in config.assets.iter() {
+            if &asset == native_token {
+                continue;
+            }
+            let balance = crate::token_client::get_vault_balance(env, &asset);
+            let mut amount_to_swap = if shortfall >= other_native_value {
+                balance
+            } else {
+                Self::mul_div(balance, shortfall, other_native_value)
+                    .ok_or(VaultError::InvalidAmount)?
+            };
+
+            let pair_address = match pool_client::get_pool_for_pair(env, &factory_address, &asset, native_token) {
+                Ok(addr) => addr,
+                Err(_) => continue,
+            };
+
+            if config.max_liquidation_impact_bps > 0 {
+                if let Ok((reserve_in, _)) = pool_client::get_reserves_for_pair(env, &pair_address, &asset, native_token) {
+                    let max_in = Self::mul_div(
+                        reserve_in,
+                        config.max_liquidation_impact_bps,
+                        10_000 - config.max_liquidation_impact_bps,
+                    ).unwrap_or(amount_to_swap);
+                    amount_to_swap = amount_to_swap.min(max_in);
+                }
+            }
+
+            if amount_to_swap <= 0 {
+                continue;
+            }
+
+            if let Err(e) = pool_client::swap_via_pool(env, &pair_address, &asset, native_token, amount_to_swap, 0) {
+                log!(env, "Shortfall swap failed: {:?}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get a user's queued withdrawal, if any
+    pub fn get_pending_withdrawal(env: Env, user: Address) -> Option<PendingWithdrawal> {
+        env.storage().instance().get(&(PENDING, user))
+    }
+
+    /// Set the reentrancy lock, failing if it is already held
+    /// Error paths don't need to release it: a failed invocation rolls back storage
+    fn acquire_lock(env: &Env) -> Result<(), VaultError> {
+        if env.storage().instance().has(&LOCKED) {
+            log!(env, "Reentrant call rejected");
+            return Err(VaultError::Reentrancy);
+        }
+        env.storage().instance().set(&LOCKED, &true);
+        Ok(())
+    }
+
+    /// Fail with ConfigLocked once the owner has frozen the config
+    fn require_config_unlocked(env: &Env) -> Result<(), VaultError> {
+        if env.storage().instance().has(&CFG_LOCK) {
+            return Err(VaultError::ConfigLocked);
+        }
+        Ok(())
+    }
+
+    /// Fail with WithdrawalCooldown if the user's last withdrawal is within the configured cooldown
+    fn require_withdrawal_cooldown(env: &Env, position: &UserPosition) -> Result<(), VaultError> {
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+
+        if config.withdrawal_cooldown == 0 || position.last_withdrawal == 0 {
+            return Ok(());
+        }
+
+        let ready_at = position.last_withdrawal.saturating_add(config.withdrawal_cooldown);
+        if env.ledger().timestamp() < ready_at {
+            log!(env, "Withdrawal cooldown active until {}", ready_at);
+            return Err(VaultError::WithdrawalCooldown);
+        }
+        Ok(())
+    }
+
+    /// Clear the reentrancy lock
+    fn release_lock(env: &Env) {
+        env.storage().instance().remove(&LOCKED);
+    }
+
+    /// Compute a * b / denominator (rounded down) without overflowing on the product
+    /// Only defined for non-negative a, b and a positive denominator
+    fn mul_div(a: i128, b: i128, denominator: i128) -> Option<i128> {
+        if a < 0 || b < 0 || denominator <= 0 {
+            return None;
+        }
+
+        // Fast path: product fits in i128
+        if let Some(product) = a.checked_mul(b) {
+            return Some(product / denominator);
+        }
+
+        // Widen: build the 256-bit product (hi, lo) from 64-bit limbs
+        const MASK: u128 = u64::MAX as u128;
+        let (a, b, d) = (a as u128, b as u128, denominator as u128);
+        let (a0, a1, b0, b1) = (a & MASK, a >> 64, b & MASK, b >> 64);
+        let p00 = a0 * b0;
+        let p01 = a0 * b1;
+        let p10 = a1 * b0;
+        let p11 = a1 * b1;
+        let mid = (p00 >> 64) + (p01 & MASK) + (p10 & MASK);
+        let lo = (p00 & MASK) | ((mid & MASK) << 64);
+        let hi = p11 + (p01 >> 64) + (p10 >> 64) + (mid >> 64);
+
+        // Quotient wouldn't fit in 128 bits
+        if hi >= d {
+            return None;
+        }
+
+        // Binary long division of (hi, lo) by d
+        let mut rem = hi;
+        let mut quotient: u128 = 0;
+        for i in (0..128).rev() {
+            let carry = rem >> 127;
+            rem = (rem << 1) | ((lo >> i) & 1);
+            quotient <<= 1;
+            if carry == 1 || rem >= d {
+                rem = rem.wrapping_sub(d);
+                quotient |= 1;
+            }
+        }
+
+        if quotient > i128::MAX as u128 {
+            return None;
+        }
+        Some(quotient as i128)
+    }
+    
+    /// Append a user to the depositor index (on their first deposit)
+    fn add_depositor(env: &Env, user: &Address) {
+        let mut depositors = Self::list_depositors(env.clone());
+        if !depositors.contains(user) {
+            depositors.push_back(user.clone());
+            env.storage().instance().set(&DEPOSITORS, &depositors);
+        }
+    }
+
+    /// Drop a user from the depositor index (once their shares reach 0)
+    fn remove_depositor(env: &Env, user: &Address) {
+        let mut depositors = Self::list_depositors(env.clone());
+        if let Some(index) = depositors.first_index_of(user) {
+            depositors.remove(index);
+            env.storage().instance().set(&DEPOSITORS, &depositors);
+        }
+    }
+
+    /// Liquidate ALL positions before withdrawal (unstake everything, remove all liquidity)
+    fn liquidate_all_positions(
+        env: &Env,
+        config: &VaultConfig,
+    ) -> Result<(), VaultError> {
+        use crate::staking_client;
+        
+        log!(env, "Liquidating ALL positions");
+        
+        // Unstake ALL tokens from staking pool if configured
+        if let Some(ref staking_pool) = config.staking_pool_address {
+            // Same key stake_tokens writes the position under
+            let staking_symbol = soroban_sdk::String::from_str(env, "stake_position");
+            
+            if let Some(staking_pos) = env.storage().instance().get::<_, crate::types::StakingPosition>(&staking_symbol) {
+                log!(env, "Unstaking ALL: {} tokens (st_tokens: {})", 
+                    staking_pos.staked_amount, 
+                    staking_pos.st_token_amount);
+                
+                // Unstake ALL st_tokens
+                match staking_client::unstake_tokens(
+                    env,
+                    staking_pool,
+                    staking_pos.st_token_amount // Burn ALL st_tokens
+                ) {
+                    Ok(tokens_received) => {
+                        log!(env, "Successfully unstaked all: {}", tokens_received);
+                        // Remove staking position
+                        env.storage().instance().remove(&staking_symbol);
+                    },
+                    Err(e) => {
+                        log!(env, "Failed to unstake: {:?}", e);
+                        // Continue anyway
+                    }
+                }
+            }
+        }
+        
+        // Remove liquidity position from tracking
+        use soroban_sdk::String;
+        let position_key = String::from_str(env, "lp_position");
+        if env.storage().instance().has(&position_key) {
+            log!(env, "Releasing liquidity position for withdrawal");
+            env.storage().instance().remove(&position_key);
+        }
+        
+        Ok(())
+    }
+    
+    /// Liquidate just enough of the staking position to cover amount_needed
+    /// Used by withdraw when the vault's unstake mode is Proportional
+    fn liquidate_positions_for_withdrawal(
+        env: &Env,
+        config: &VaultConfig,
+        amount_needed: i128
+    ) -> Result<(), VaultError> {
+        use crate::staking_client;
+        
+        log!(env, "Liquidating positions to cover withdrawal. Amount needed: {}", amount_needed);
         
         let _vault_address = env.current_contract_address();
         let mut amount_liquidated: i128 = 0;
@@ -393,7 +983,8 @@ impl VaultContract {
         // Try to unstake from staking pool if configured
         if let Some(ref staking_pool) = config.staking_pool_address {
             // Check if vault has any staking position
-            let staking_symbol = symbol_short!("STAKE");
+            // Same key stake_tokens writes the position under
+            let staking_symbol = soroban_sdk::String::from_str(env, "stake_position");
             
             if let Some(staking_pos) = env.storage().instance().get::<_, crate::types::StakingPosition>(&staking_symbol) {
                 log!(env, "Found staking position: staked={}, st_tokens={}", 
@@ -480,6 +1071,8 @@ impl VaultContract {
     
     /// Swap ALL non-XLM assets to XLM for withdrawal
     /// This ensures users always receive XLM which doesn't require trustlines
+    /// Deepest XLM pools are drained first and each swap is capped at max_liquidation_impact_bps;
+    /// whatever a cap leaves unswapped stays in the vault
     fn swap_all_assets_to_xlm(
         env: &Env,
         config: &VaultConfig,
@@ -510,10 +1103,11 @@ impl VaultContract {
         let vault_address = env.current_contract_address();
         let mut total_xlm_received: i128 = 0;
         
-        // Iterate through ALL configured assets and swap everything to XLM
+        // Collect every non-XLM asset we hold that has a pool to XLM, with that pool's XLM depth
         let asset_count = config.assets.len();
         log!(env, "Scanning {} configured assets", asset_count);
         
+        let mut candidates: soroban_sdk::Vec<(Address, Address, i128)> = soroban_sdk::Vec::new(env);
         for i in 0..asset_count {
             let asset = config.assets.get(i)
                 .ok_or(VaultError::InvalidConfiguration)?;
@@ -526,15 +1120,11 @@ impl VaultContract {
             
             // Check balance of this asset
             let asset_client = token::TokenClient::new(env, &asset);
-            let asset_balance = asset_client.balance(&vault_address);
-            
-            if asset_balance <= 0 {
+            if asset_client.balance(&vault_address) <= 0 {
                 log!(env, "No balance for asset at index {}", i);
                 continue;
             }
             
-            log!(env, "Found {} balance of asset at index {} - swapping ALL to XLM", asset_balance, i);
-            
             // Find the liquidity pool between this asset and XLM
             let pair_address = match pool_client::get_pool_for_pair(
                 env,
@@ -549,16 +1139,58 @@ impl VaultContract {
                 }
             };
             
-            log!(env, "Found liquidity pair - swapping {} tokens", asset_balance);
+            let xlm_depth = match pool_client::get_reserves_for_pair(env, &pair_address, &asset, xlm_token) {
+                Ok((_, reserve_xlm)) => reserve_xlm,
+                Err(_) => 0,
+            };
+            
+            // Insert keeping descending XLM depth (asset count is capped at MAX_ASSETS)
+            let mut pos = candidates.len();
+            for j in 0..candidates.len() {
+                if let Some((_, _, depth)) = candidates.get(j) {
+                    if xlm_depth > depth {
+                        pos = j;
+                        break;
+                    }
+                }
+            }
+            candidates.insert(pos, (asset, pair_address, xlm_depth));
+        }
+        
+        // Deepest XLM pools first, so shallow pools are hit last and least
+        for (asset, pair_address, _) in candidates.iter() {
+            let asset_balance = token::TokenClient::new(env, &asset).balance(&vault_address);
+            
+            // Cap the swap so it moves the pool's price by at most max_liquidation_impact_bps:
+            // impact = amount / (reserve_in + amount), so amount <= reserve_in * cap / (10_000 - cap)
+            let mut amount_to_swap = asset_balance;
+            if config.max_liquidation_impact_bps > 0 {
+                if let Ok((reserve_in, _)) = pool_client::get_reserves_for_pair(env, &pair_address, &asset, xlm_token) {
+                    let max_in = Self::mul_div(
+                        reserve_in,
+                        config.max_liquidation_impact_bps,
+                        10_000 - config.max_liquidation_impact_bps,
+                    ).unwrap_or(asset_balance);
+                    if max_in < amount_to_swap {
+                        log!(env, "Capping swap at {} of {} to respect impact limit", max_in, asset_balance);
+                        amount_to_swap = max_in;
+                    }
+                }
+            }
+            
+            if amount_to_swap <= 0 {
+                continue;
+            }
+            
+            log!(env, "Found liquidity pair - swapping {} tokens", amount_to_swap);
             
-            // Swap ALL balance to XLM
             match pool_client::swap_via_pool(
                 env,
                 &pair_address,
                 &asset,
                 xlm_token,
-                asset_balance, // Swap entire balance
-                0, // min_amount_out = 0 (accepting any slippage for withdrawal)
+                amount_to_swap,
+                0, // min_amount_out = 0 (size is already bounded by the impact cap)
             ) {
                 Ok(xlm_received) => {
                     log!(env, "Swapped successfully. Received {} XLM", xlm_received);
@@ -719,19 +1351,777 @@ impl VaultContract {
             })
     }
 
-    /// Get user position
-    pub fn get_position(env: Env, user: Address) -> UserPosition {
-        env.storage().instance().get(&(POSITION, user))
-            .unwrap_or(UserPosition {
-                shares: 0,
-                last_deposit: 0,
-            })
+    /// Get user position
+    pub fn get_position(env: Env, user: Address) -> UserPosition {
+        env.storage().instance().get(&(POSITION, user))
+            .unwrap_or(UserPosition {
+                shares: 0,
+                last_deposit: 0,
+                last_withdrawal: 0,
+            })
+    }
+
+    /// Every address currently holding shares, in first-deposit order
+    pub fn list_depositors(env: Env) -> soroban_sdk::Vec<Address> {
+        env.storage().instance().get(&DEPOSITORS)
+            .unwrap_or(soroban_sdk::Vec::new(&env))
+    }
+
+    /// Get a user's ownership of the vault (100_0000 = 100%, same scale as allocations)
+    pub fn share_of(env: Env, user: Address) -> i128 {
+        let state = Self::get_state(env.clone());
+        if state.total_shares == 0 {
+            return 0;
+        }
+
+        let position = Self::get_position(env, user);
+        position.shares
+            .checked_mul(100_0000)
+            .and_then(|v| v.checked_div(state.total_shares))
+            .unwrap_or(0)
+    }
+
+    /// Value of the user's shares in base-token units at live nav rather than cached total_value
+    pub fn position_value(env: Env, user: Address) -> Result<i128, VaultError> {
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+
+        let state = Self::get_state(env.clone());
+        if state.total_shares == 0 {
+            return Ok(0);
+        }
+
+        let position = Self::get_position(env.clone(), user);
+        let nav = Self::calculate_nav(&env, &config)?;
+        Self::mul_div(position.shares, nav, state.total_shares)
+            .ok_or(VaultError::InvalidAmount)
+    }
+
+    /// Preview a deposit: base-token value after the swap, shares minted and the route used
+    /// Runs deposit's share math without transferring or minting anything
+    pub fn quote_deposit(env: Env, amount: i128, deposit_token: Address) -> Result<DepositQuote, VaultError> {
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+
+        if amount <= 0 {
+            return Err(VaultError::InvalidAmount);
+        }
+
+        let base_token = Self::get_base_token(env.clone())?;
+        let route = if deposit_token == base_token {
+            soroban_sdk::vec![&env, base_token.clone()]
+        } else {
+            if Self::pair_reserves(&env, &config, &deposit_token, &base_token).is_none() {
+                return Err(VaultError::NoRouteFound);
+            }
+            soroban_sdk::vec![&env, deposit_token.clone(), base_token.clone()]
+        };
+
+        let base_amount_after_swap = Self::swap_output_in(&env, &config, &deposit_token, amount, &base_token);
+
+        // Deposit credits the token as-is, so shares follow the raw amount
+        let state = Self::get_state(env.clone());
+        let shares_minted = if state.total_shares == 0 {
+            if amount <= DEAD_SHARES {
+                return Err(VaultError::InvalidAmount);
+            }
+            amount - DEAD_SHARES
+        } else {
+            Self::mul_div(amount, state.total_shares, state.total_value)
+                .ok_or(VaultError::InvalidAmount)?
+        };
+
+        Ok(DepositQuote {
+            base_amount_after_swap,
+            shares_minted,
+            route,
+        })
+    }
+
+    /// Preview withdraw's liquidation: (asset, amount_liquidated, xlm_received) per asset
+    /// Each row is the redeemed shares' slice of what withdraw would swap to the native token
+    /// against live reserves, capped at max_liquidation_impact_bps; the native token itself
+    /// passes through 1:1
+    pub fn simulate_withdrawal(
+        env: Env,
+        user: Address,
+        shares: i128,
+    ) -> Result<soroban_sdk::Vec<(Address, i128, i128)>, VaultError> {
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+
+        if shares <= 0 {
+            return Err(VaultError::InvalidAmount);
+        }
+        if Self::get_position(env.clone(), user).shares < shares {
+            return Err(VaultError::InsufficientShares);
+        }
+
+        let state = Self::get_state(env.clone());
+        let native_token = Self::get_native_token(env.clone())?;
+
+        let mut breakdown: soroban_sdk::Vec<(Address, i128, i128)> = soroban_sdk::Vec::new(&env);
+        for (asset, amount_liquidated, xlm_received) in Self::liquidation_quotes(&env, &config, &native_token)?.iter() {
+            let amount_share = Self::mul_div(amount_liquidated, shares, state.total_shares)
+                .ok_or(VaultError::InvalidAmount)?;
+            let xlm_share = Self::mul_div(xlm_received, shares, state.total_shares)
+                .ok_or(VaultError::InvalidAmount)?;
+            breakdown.push_back((asset, amount_share, xlm_share));
+        }
+
+        Ok(breakdown)
+    }
+
+    /// Native token a user could realistically withdraw right now
+    /// Simulates withdraw's liquidation and swaps against live reserves, so it can
+    /// fall below the nominal share value when pools are thin
+    pub fn max_withdrawable(env: Env, user: Address) -> Result<i128, VaultError> {
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+        let state = Self::get_state(env.clone());
+        let position = Self::get_position(env.clone(), user);
+
+        if state.total_shares == 0 || position.shares == 0 {
+            return Ok(0);
+        }
+
+        let native_token = Self::get_native_token(env.clone())?;
+
+        // Same nominal amount withdraw computes from cached total_value, in the native token
+        let amount_base = position.shares.checked_mul(state.total_value)
+            .and_then(|v| v.checked_div(state.total_shares))
+            .ok_or(VaultError::InvalidAmount)?;
+        let amount = Self::base_to_native(&env, &config, amount_base)?;
+
+        let obtainable = Self::obtainable_native(&env, &config, &native_token)?;
+
+        // Mirror withdraw: pay the nominal amount, or a proportional slice if short
+        if obtainable < amount {
+            return Self::mul_div(obtainable, position.shares, state.total_shares)
+                .ok_or(VaultError::InvalidAmount);
+        }
+
+        Ok(amount)
+    }
+
+    /// Native balance the vault would hold after withdraw's liquidation and swaps,
+    /// priced against live reserves with each swap capped like swap_all_assets_to_xlm
+    fn obtainable_native(env: &Env, config: &VaultConfig, native_token: &Address) -> Result<i128, VaultError> {
+        let mut obtainable: i128 = 0;
+        for (_, _, native_out) in Self::liquidation_quotes(env, config, native_token)?.iter() {
+            obtainable = obtainable.checked_add(native_out)
+                .ok_or(VaultError::InvalidAmount)?;
+        }
+
+        Ok(obtainable)
+    }
+
+    /// (asset, amount swapped, native received) for each configured asset if withdraw
+    /// liquidated the whole vault now: the staking position is unstaked into its token,
+    /// then each swap is capped at max_liquidation_impact_bps of its pool
+    fn liquidation_quotes(
+        env: &Env,
+        config: &VaultConfig,
+        native_token: &Address,
+    ) -> Result<soroban_sdk::Vec<(Address, i128, i128)>, VaultError> {
+        let staking_pos = env.storage().instance()
+            .get::<_, crate::types::StakingPosition>(&soroban_sdk::String::from_str(env, "stake_position"));
+
+        let mut quotes: soroban_sdk::Vec<(Address, i128, i128)> = soroban_sdk::Vec::new(env);
+        for asset in config.assets.iter() {
+            let mut balance = crate::token_client::get_vault_balance(env, &asset);
+            if let Some(pos) = staking_pos.as_ref().filter(|pos| pos.original_token == asset) {
+                let unstaked = crate::staking_client::current_staking_value(env, &pos.staking_pool, pos.st_token_amount);
+                balance = balance.checked_add(unstaked)
+                    .ok_or(VaultError::InvalidAmount)?;
+            }
+
+            if &asset == native_token {
+                balance = (balance - Self::reserved_native(env)).max(0);
+                quotes.push_back((asset, balance, balance));
+                continue;
+            }
+
+            // Same bound swap_all_assets_to_xlm applies: amount <= reserve_in * cap / (10_000 - cap)
+            let mut amount = balance;
+            if config.max_liquidation_impact_bps > 0 {
+                if let Some((reserve_in, _)) = Self::pair_reserves(env, config, &asset, native_token) {
+                    let max_in = Self::mul_div(
+                        reserve_in,
+                        config.max_liquidation_impact_bps,
+                        10_000 - config.max_liquidation_impact_bps,
+                    ).unwrap_or(balance);
+                    amount = amount.min(max_in);
+                }
+            }
+
+            let native_out = Self::swap_output_in(env, config, &asset, amount, native_token);
+            quotes.push_back((asset, amount, native_out));
+        }
+
+        Ok(quotes)
+    }
+
+    /// Vault balances of every configured asset, plus LP tokens held in the liquidity
+    /// position (keyed by pool) and st-tokens held in the staking position (keyed by staking pool)
+    pub fn get_extended_balances(env: Env) -> Result<soroban_sdk::Vec<AssetBalance>, VaultError> {
+        use soroban_sdk::String;
+
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+
+        let mut balances: soroban_sdk::Vec<AssetBalance> = soroban_sdk::Vec::new(&env);
+        for asset in config.assets.iter() {
+            let amount = crate::token_client::get_vault_balance(&env, &asset);
+            balances.push_back(AssetBalance { token: asset, amount });
+        }
+
+        let lp_key = String::from_str(&env, "lp_position");
+        if let Some(position) = env.storage().instance().get::<_, crate::types::LiquidityPosition>(&lp_key) {
+            balances.push_back(AssetBalance { token: position.pool_address, amount: position.lp_tokens });
+        }
+
+        let stake_key = String::from_str(&env, "stake_position");
+        if let Some(position) = env.storage().instance().get::<_, crate::types::StakingPosition>(&stake_key) {
+            balances.push_back(AssetBalance { token: position.staking_pool, amount: position.st_token_amount });
+        }
+
+        Ok(balances)
+    }
+
+    /// Get the configured router address
+    pub fn get_router(env: Env) -> Option<Address> {
+        let config: Option<VaultConfig> = env.storage().instance().get(&CONFIG);
+        config.and_then(|c| c.router_address)
+    }
+
+    /// Get the configured factory address
+    pub fn get_factory(env: Env) -> Option<Address> {
+        let config: Option<VaultConfig> = env.storage().instance().get(&CONFIG);
+        config.and_then(|c| c.factory_address)
+    }
+
+    /// Get the configured staking pool address
+    pub fn get_staking_pool(env: Env) -> Option<Address> {
+        let config: Option<VaultConfig> = env.storage().instance().get(&CONFIG);
+        config.and_then(|c| c.staking_pool_address)
+    }
+
+    /// Get the base token (default deposit and valuation token)
+    pub fn get_base_token(env: Env) -> Result<Address, VaultError> {
+        env.storage().instance().get(&BASE)
+            .ok_or(VaultError::NotInitialized)
+    }
+
+    /// Get the native token (paid out on withdrawal)
+    pub fn get_native_token(env: Env) -> Result<Address, VaultError> {
+        env.storage().instance().get(&NATIVE)
+            .ok_or(VaultError::NotInitialized)
+    }
+
+    /// Get vault configuration
+    pub fn get_config(env: Env) -> Result<VaultConfig, VaultError> {
+        env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)
+    }
+
+    /// Number of configured rebalance rules
+    pub fn rule_count(env: Env) -> u32 {
+        let config: Option<VaultConfig> = env.storage().instance().get(&CONFIG);
+        config.map_or(0, |c| c.rules.len())
+    }
+
+    /// Get a single rebalance rule by index
+    pub fn get_rule(env: Env, index: u32) -> Result<crate::types::RebalanceRule, VaultError> {
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+
+        config.rules.get(index)
+            .ok_or(VaultError::InvalidConfiguration)
+    }
+
+    /// Get live net asset value: configured assets plus the staking position, in base-token units
+    pub fn nav(env: Env) -> Result<i128, VaultError> {
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+
+        Self::calculate_nav(&env, &config)
+    }
+
+    /// Room left under the deposit cap (max_total_value - nav, floored at 0)
+    /// None for uncapped vaults
+    pub fn remaining_capacity(env: Env) -> Option<i128> {
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)?;
+        let cap = config.max_total_value?;
+
+        let nav = Self::calculate_nav(&env, &config).unwrap_or(i128::MAX);
+        Some(cap.saturating_sub(nav).max(0))
+    }
+
+    /// Overwrite the cached total_value with live nav (owner only)
+    /// Corrects drift from fees and slippage; total_shares is left untouched
+    /// Refuses with PoolNotFound while a held asset has no oracle price or pool to the base token
+    pub fn reconcile(env: Env, caller: Address) -> Result<i128, VaultError> {
+        caller.require_auth();
+
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+
+        // Only owner can reconcile accounting
+        if caller != config.owner {
+            return Err(VaultError::Unauthorized);
+        }
+
+        // nav counts an unpriceable balance as 0; writing that into total_value would cut
+        // the share price for an asset that still has value
+        let base_token = Self::get_base_token(env.clone())?;
+        for asset in config.assets.iter() {
+            let balance = crate::token_client::get_vault_balance(&env, &asset);
+            if balance > 0 && Self::value_in_token(&env, &config, &asset, balance, &base_token) == 0 {
+                log!(&env, "Cannot reconcile: no price source for a held asset");
+                return Err(VaultError::PoolNotFound);
+            }
+        }
+
+        let mut state: VaultState = env.storage().instance().get(&STATE)
+            .ok_or(VaultError::NotInitialized)?;
+
+        let before = state.total_value;
+        let after = Self::calculate_nav(&env, &config)?;
+
+        state.total_value = after;
+        env.storage().instance().set(&STATE, &state);
+
+        log!(&env, "Reconciled total_value: {} -> {}", before, after);
+        env.events().publish((symbol_short!("reconcile"),), (before, after));
+
+        Ok(after)
+    }
+
+    /// Claim staking rewards, swap them to the base token and re-stake the proceeds (owner only)
+    /// Proceeds stay in the vault when there is no base-token staking position to add to
+    /// Returns the base-token amount reinvested; 0 when no rewards have accrued
+    pub fn compound(env: Env, caller: Address) -> Result<i128, VaultError> {
+        caller.require_auth();
+
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+
+        // Only owner can trigger compounding
+        if caller != config.owner {
+            return Err(VaultError::Unauthorized);
+        }
+
+        let staking_pool = config.staking_pool_address.clone()
+            .ok_or(VaultError::InvalidConfiguration)?;
+
+        // The swap and re-stake call out to pools
+        Self::acquire_lock(&env)?;
+
+        let (reward_token, claimed) = match crate::staking_client::claim_staking_rewards(&env, &staking_pool)? {
+            Some((reward_token, claimed)) if claimed > 0 => (reward_token, claimed),
+            _ => {
+                log!(&env, "No staking rewards to compound");
+                Self::release_lock(&env);
+                return Ok(0);
+            }
+        };
+
+        let base_token: Address = env.storage().instance().get(&BASE)
+            .ok_or(VaultError::NotInitialized)?;
+
+        let proceeds = if reward_token == base_token {
+            claimed
+        } else {
+            let expected = Self::swap_output_in(&env, &config, &reward_token, claimed, &base_token);
+            let min_amount_out = expected.checked_mul(99)
+                .and_then(|v| v.checked_div(100))
+                .ok_or(VaultError::InvalidAmount)?;
+
+            let factory_address = Self::resolve_factory(&env, &config);
+            let pair_address = crate::pool_client::get_pool_for_pair(&env, &factory_address, &reward_token, &base_token)?;
+            crate::pool_client::swap_via_pool(
+                &env,
+                &pair_address,
+                &reward_token,
+                &base_token,
+                claimed,
+                min_amount_out,
+            )?
+        };
+
+        // Grow the existing position rather than opening a new one
+        let position_key = soroban_sdk::String::from_str(&env, "stake_position");
+        if let Some(position) = env.storage().instance().get::<_, crate::types::StakingPosition>(&position_key) {
+            if position.original_token == base_token && position.staking_pool == staking_pool && proceeds > 0 {
+                let st_tokens_received = crate::staking_client::stake_tokens(
+                    &env,
+                    &staking_pool,
+                    &base_token,
+                    proceeds,
+                )?;
+
+                let staked_amount = position.staked_amount.checked_add(proceeds)
+                    .ok_or(VaultError::InvalidAmount)?;
+                let st_token_amount = position.st_token_amount.checked_add(st_tokens_received)
+                    .ok_or(VaultError::InvalidAmount)?;
+                let updated_pos = crate::types::StakingPosition {
+                    staked_amount,
+                    st_token_amount,
+                    current_value: crate::staking_client::current_staking_value(&env, &staking_pool, st_token_amount),
+                    ..position
+                };
+                env.storage().instance().set(&position_key, &updated_pos);
+            }
+        }
+
+        let mut state: VaultState = env.storage().instance().get(&STATE)
+            .ok_or(VaultError::NotInitialized)?;
+        state.total_value = state.total_value.checked_add(proceeds)
+            .ok_or(VaultError::InvalidAmount)?;
+        env.storage().instance().set(&STATE, &state);
+
+        log!(&env, "Compounded {} rewards into {} base token", claimed, proceeds);
+        env.events().publish((symbol_short!("compound"),), (reward_token, claimed, proceeds));
+
+        Self::release_lock(&env);
+
+        Ok(proceeds)
+    }
+
+    /// Total value of every configured asset priced in `token` via pool reserves
+    /// Assets without a pool to `token` contribute 0
+    pub fn total_value_in(env: Env, token: Address) -> Result<i128, VaultError> {
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+
+        let mut total: i128 = 0;
+        for asset in config.assets.iter() {
+            let balance = crate::token_client::get_vault_balance(&env, &asset);
+            let value = Self::value_in_token(&env, &config, &asset, balance, &token);
+            total = total.checked_add(value)
+                .ok_or(VaultError::InvalidAmount)?;
+        }
+
+        Ok(total)
+    }
+
+    /// Self-check for accounting drift, missing pools and solvency
+    pub fn health_check(env: Env) -> Result<HealthReport, VaultError> {
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+
+        let state = Self::get_state(env.clone());
+        let nav = Self::calculate_nav(&env, &config)?;
+
+        // Drift between cached total_value and live NAV, in basis points of total_value
+        let value_drift_bps = if state.total_value > 0 {
+            (state.total_value - nav).abs()
+                .checked_mul(10_000)
+                .and_then(|v| v.checked_div(state.total_value))
+                .ok_or(VaultError::InvalidAmount)?
+        } else if nav > 0 {
+            10_000 // Value held with nothing recorded - fully drifted
+        } else {
+            0
+        };
+
+        // Every non-base asset needs a pool to the base token (custom pool or factory pair)
+        let base_token = Self::get_base_token(env.clone())?;
+        let factory_address = Self::resolve_factory(&env, &config);
+
+        let mut has_missing_pools = false;
+        for i in 0..config.assets.len() {
+            if let Some(asset) = config.assets.get(i) {
+                if asset == base_token {
+                    continue;
+                }
+
+                let has_custom_pool = crate::real_pool_client::get_custom_token_pool(&env, &asset).is_some();
+                if !has_custom_pool
+                    && crate::pool_client::get_pool_for_pair(&env, &factory_address, &asset, &base_token).is_err()
+                {
+                    log!(&env, "Health check: no pool for asset at index {}", i);
+                    has_missing_pools = true;
+                }
+            }
+        }
+
+        // Outstanding shares must be backed by value
+        let solvent = state.total_shares == 0 || (state.total_value > 0 && nav > 0);
+
+        Ok(HealthReport {
+            value_drift_bps,
+            has_missing_pools,
+            solvent,
+        })
+    }
+
+    /// Live allocation of each configured asset, 100_0000-scaled like rule target allocations
+    /// Measured on the same raw balances force_rebalance targets, so a keeper can compare it
+    /// with the target to decide whether another pass is worthwhile; all zeros when empty
+    pub fn effective_allocation(env: Env) -> Result<soroban_sdk::Vec<i128>, VaultError> {
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+
+        let mut balances: soroban_sdk::Vec<i128> = soroban_sdk::Vec::new(&env);
+        let mut total: i128 = 0;
+        for asset in config.assets.iter() {
+            let balance = crate::token_client::get_vault_balance(&env, &asset);
+            total = total.checked_add(balance)
+                .ok_or(VaultError::InvalidAmount)?;
+            balances.push_back(balance);
+        }
+
+        let mut allocation: soroban_sdk::Vec<i128> = soroban_sdk::Vec::new(&env);
+        for balance in balances.iter() {
+            let pct = if total > 0 {
+                Self::mul_div(balance, 100_0000, total).ok_or(VaultError::InvalidAmount)?
+            } else {
+                0
+            };
+            allocation.push_back(pct);
+        }
+
+        Ok(allocation)
+    }
+
+    /// Per-asset balances valued in the base token, with each asset's share of the vault
+    /// Assets without a pool to the base token are reported with value_base = 0
+    pub fn get_underlying_breakdown(env: Env) -> Result<soroban_sdk::Vec<AssetValue>, VaultError> {
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+
+        let base_token = Self::get_base_token(env.clone())?;
+
+        let mut breakdown: soroban_sdk::Vec<AssetValue> = soroban_sdk::Vec::new(&env);
+        let mut total_value_base: i128 = 0;
+
+        for i in 0..config.assets.len() {
+            if let Some(asset) = config.assets.get(i) {
+                let balance = crate::token_client::get_vault_balance(&env, &asset);
+                let value_base = Self::value_in_token(&env, &config, &asset, balance, &base_token);
+
+                total_value_base = total_value_base.checked_add(value_base)
+                    .ok_or(VaultError::InvalidAmount)?;
+
+                breakdown.push_back(AssetValue {
+                    token: asset,
+                    balance,
+                    value_base,
+                    pct_bps: 0, // Filled in once the total is known
+                });
+            }
+        }
+
+        if total_value_base > 0 {
+            for i in 0..breakdown.len() {
+                if let Some(mut entry) = breakdown.get(i) {
+                    entry.pct_bps = entry.value_base
+                        .checked_mul(10_000)
+                        .and_then(|v| v.checked_div(total_value_base))
+                        .ok_or(VaultError::InvalidAmount)?;
+                    breakdown.set(i, entry);
+                }
+            }
+        }
+
+        Ok(breakdown)
+    }
+
+    /// Price of one unit of asset in the base token, scaled by 1e7
+    /// Prefers the configured price oracle, falling back to the pool spot price (0 if no pool)
+    pub fn get_asset_price(env: Env, asset: Address) -> Result<i128, VaultError> {
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+        let base_token = Self::get_base_token(env.clone())?;
+
+        Ok(Self::value_in_token(&env, &config, &asset, crate::pool_client::PRICE_SCALE, &base_token))
+    }
+
+    /// Spot price of each pool's other token in quote_token, scaled by 1e7, in the order given
+    /// Pools that don't hold quote_token or can't be read report 0
+    pub fn get_spot_prices(env: Env, pools: soroban_sdk::Vec<Address>, quote_token: Address) -> soroban_sdk::Vec<i128> {
+        crate::pool_client::get_spot_prices(&env, &pools, &quote_token)
+    }
+
+    /// Value an amount of `asset` in `quote_token` terms at the oracle or pool spot price
+    /// Uses the price oracle first, then a registered custom pool, then the factory pair;
+    /// returns 0 if none of them can price the asset
+    fn value_in_token(
+        env: &Env,
+        config: &VaultConfig,
+        asset: &Address,
+        amount: i128,
+        quote_token: &Address,
+    ) -> i128 {
+        if asset == quote_token || amount == 0 {
+            return amount;
+        }
+
+        // A configured oracle is harder to move than a thin pool, so it wins when it answers
+        if let Some(oracle) = &config.price_oracle {
+            if let Some(price) = crate::oracle_client::get_price(env, oracle, asset, quote_token) {
+                return Self::mul_div(amount, price, crate::pool_client::PRICE_SCALE).unwrap_or(0);
+            }
+        }
+
+        let (reserve_asset, reserve_quote) = match Self::pair_reserves(env, config, asset, quote_token) {
+            Some(reserves) => reserves,
+            None => {
+                log!(env, "No pool to value asset - reporting 0");
+                return 0;
+            }
+        };
+
+        if reserve_asset <= 0 {
+            return 0;
+        }
+
+        amount.checked_mul(reserve_quote)
+            .and_then(|v| v.checked_div(reserve_asset))
+            .unwrap_or(0)
+    }
+
+    /// Output of swapping amount of asset into quote_token against live reserves
+    /// Assets without a pool to quote_token yield 0
+    fn swap_output_in(
+        env: &Env,
+        config: &VaultConfig,
+        asset: &Address,
+        amount: i128,
+        quote_token: &Address,
+    ) -> i128 {
+        if asset == quote_token || amount <= 0 {
+            return amount.max(0);
+        }
+
+        Self::quote_swap(env, config, asset, amount, quote_token).unwrap_or(0)
+    }
+
+    /// Quote a swap against the pair's live reserves, charging a custom pool's fee tier
+    /// for this size (factory pairs charge a flat 0.3%); None if no pool can quote it
+    fn quote_swap(
+        env: &Env,
+        config: &VaultConfig,
+        from_token: &Address,
+        amount_in: i128,
+        to_token: &Address,
+    ) -> Option<i128> {
+        let (reserve_in, reserve_out) = Self::pair_reserves(env, config, from_token, to_token)?;
+        if reserve_in <= 0 || reserve_out <= 0 {
+            return None;
+        }
+
+        let fee_bps = match crate::real_pool_client::find_pool_for_pair(env, from_token, to_token) {
+            Some(pool) => crate::real_pool_client::pool_fee_bps(env, &pool, amount_in),
+            None => 30,
+        };
+
+        let amount_in_with_fee = amount_in.checked_mul(10_000 - fee_bps)?;
+        let denominator = reserve_in.checked_mul(10_000)
+            .and_then(|v| v.checked_add(amount_in_with_fee))?;
+
+        Self::mul_div(amount_in_with_fee, reserve_out, denominator)
+    }
+
+    /// Price impact (bps) of swapping amount_in of from_token into to_token
+    /// For x * y = k, execution price falls short of spot by amount_in / (reserve_in + amount_in)
+    fn price_impact_bps(
+        env: &Env,
+        config: &VaultConfig,
+        from_token: &Address,
+        to_token: &Address,
+        amount_in: i128,
+    ) -> Result<i128, VaultError> {
+        let (reserve_in, _reserve_out) = Self::pair_reserves(env, config, from_token, to_token)
+            .ok_or(VaultError::PoolNotFound)?;
+
+        let denominator = reserve_in.checked_add(amount_in)
+            .ok_or(VaultError::InvalidAmount)?;
+        if denominator <= 0 {
+            return Err(VaultError::InsufficientLiquidity);
+        }
+
+        amount_in.checked_mul(10_000)
+            .and_then(|v| v.checked_div(denominator))
+            .ok_or(VaultError::InvalidAmount)
+    }
+
+    /// Reserves of the pool trading `asset` against `quote_token`, as (reserve_asset, reserve_quote)
+    /// Prefers a registered custom pool, then the factory pair; None if neither answers
+    fn pair_reserves(
+        env: &Env,
+        config: &VaultConfig,
+        asset: &Address,
+        quote_token: &Address,
+    ) -> Option<(i128, i128)> {
+        let pool_address = match crate::real_pool_client::find_pool_for_pair(env, asset, quote_token) {
+            Some(pool) => pool,
+            None => {
+                let factory_address = Self::resolve_factory(env, config);
+                crate::pool_client::get_pool_for_pair(env, &factory_address, asset, quote_token).ok()?
+            }
+        };
+
+        // Custom and Soroswap pools share the token_0/get_reserves shape
+        crate::pool_client::get_reserves_for_pair(env, &pool_address, asset, quote_token).ok()
+    }
+
+    /// Configured factory, or the Soroswap testnet factory when none is set
+    fn resolve_factory(env: &Env, config: &VaultConfig) -> Address {
+        match config.factory_address.clone() {
+            Some(addr) => addr,
+            None => crate::swap_router::get_soroswap_factory_address_internal(env),
+        }
+    }
+
+    /// Value the vault's live balances of all configured assets plus its staking position
+    /// (at the staking pool's live redemption rate) in base-token units
+    /// Every leg goes through value_in_token, so the oracle is preferred and pool reserves
+    /// fill in for assets it can't price; the unit is the base token either way
+    fn calculate_nav(env: &Env, config: &VaultConfig) -> Result<i128, VaultError> {
+        use soroban_sdk::String;
+
+        let base_token: Address = env.storage().instance().get(&BASE)
+            .ok_or(VaultError::NotInitialized)?;
+
+        let mut nav: i128 = 0;
+        for i in 0..config.assets.len() {
+            if let Some(asset) = config.assets.get(i) {
+                let balance = crate::token_client::get_vault_balance(env, &asset);
+                let value = Self::value_in_token(env, config, &asset, balance, &base_token);
+                nav = nav.checked_add(value)
+                    .ok_or(VaultError::InvalidAmount)?;
+            }
+        }
+
+        let position_key = String::from_str(env, "stake_position");
+        if let Some(position) = env.storage().instance().get::<_, crate::types::StakingPosition>(&position_key) {
+            let staking_value = crate::staking_client::current_staking_value(
+                env,
+                &position.staking_pool,
+                position.st_token_amount,
+            );
+            let value = Self::value_in_token(env, config, &position.original_token, staking_value, &base_token);
+            nav = nav.checked_add(value)
+                .ok_or(VaultError::InvalidAmount)?;
+        }
+
+        Ok(nav)
     }
 
-    /// Get vault configuration
-    pub fn get_config(env: Env) -> Result<VaultConfig, VaultError> {
-        env.storage().instance().get(&CONFIG)
-            .ok_or(VaultError::NotInitialized)
+    /// Sum of the vault's raw balances of every configured asset
+    /// The rebalance module sizes its targets in raw token units, so rebalance entry points
+    /// hand it this rather than the base-denominated nav
+    fn tracked_balance_total(env: &Env, config: &VaultConfig) -> Result<i128, VaultError> {
+        let mut total: i128 = 0;
+        for asset in config.assets.iter() {
+            let balance = crate::token_client::get_vault_balance(env, &asset);
+            total = total.checked_add(balance)
+                .ok_or(VaultError::InvalidAmount)?;
+        }
+        Ok(total)
     }
 
     /// Set router address for swaps (owner only)
@@ -746,6 +2136,7 @@ impl VaultContract {
             .ok_or(VaultError::NotInitialized)?;
         
         config.owner.require_auth();
+        Self::require_config_unlocked(&env)?;
         
         // Update router address
         config.router_address = Some(router);
@@ -756,6 +2147,137 @@ impl VaultContract {
         Ok(())
     }
 
+    /// Permanently freeze the router, factory and staking pool addresses (owner only)
+    /// Lets a vault become immutable so in-flight operations can't be redirected
+    pub fn lock_config(env: Env, caller: Address) -> Result<(), VaultError> {
+        caller.require_auth();
+        
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+        
+        // Only owner can lock the config
+        if caller != config.owner {
+            return Err(VaultError::Unauthorized);
+        }
+        
+        // Idempotent: locking twice is a no-op
+        env.storage().instance().set(&CFG_LOCK, &true);
+        
+        Ok(())
+    }
+
+    /// Recent rebalances, oldest first (at most MAX_HISTORY entries)
+    pub fn get_rebalance_history(env: Env) -> soroban_sdk::Vec<RebalanceRecord> {
+        env.storage().instance().get(&HISTORY)
+            .unwrap_or(soroban_sdk::Vec::new(&env))
+    }
+
+    /// Annualized growth of nav between the oldest and newest rebalance records, in bps
+    /// A rough performance figure; 0 until there are two records spanning some time
+    pub fn implied_apr(env: Env) -> i128 {
+        let history = Self::get_rebalance_history(env.clone());
+        let (oldest, newest) = match (history.first(), history.last()) {
+            (Some(oldest), Some(newest)) if history.len() >= 2 => (oldest, newest),
+            _ => return 0,
+        };
+
+        let elapsed = newest.timestamp.saturating_sub(oldest.timestamp) as i128;
+        if elapsed == 0 || oldest.total_value_after <= 0 {
+            return 0;
+        }
+
+        // apr_bps = growth / oldest * 10_000 * SECONDS_PER_YEAR / elapsed
+        let growth = newest.total_value_after - oldest.total_value_after;
+        let denominator = match oldest.total_value_after.checked_mul(elapsed) {
+            Some(d) => d,
+            None => return 0,
+        };
+        let apr_bps = growth.checked_abs()
+            .and_then(|g| g.checked_mul(10_000))
+            .and_then(|g| Self::mul_div(g, SECONDS_PER_YEAR, denominator))
+            .unwrap_or(0);
+
+        if growth < 0 { -apr_bps } else { apr_bps }
+    }
+
+    /// Append the post-rebalance nav to the history ring buffer
+    fn record_rebalance(env: &Env, config: &VaultConfig) -> Result<(), VaultError> {
+        let mut history = Self::get_rebalance_history(env.clone());
+        if history.len() >= MAX_HISTORY {
+            history.pop_front();
+        }
+
+        history.push_back(RebalanceRecord {
+            timestamp: env.ledger().timestamp(),
+            total_value_after: Self::calculate_nav(env, config)?,
+        });
+        env.storage().instance().set(&HISTORY, &history);
+
+        Ok(())
+    }
+
+    /// Exclude an asset from rebalancing, e.g. while its pool is broken (owner only)
+    /// Paused assets are neither bought nor sold; the rest of the vault still rebalances
+    pub fn pause_asset(env: Env, caller: Address, asset: Address) -> Result<(), VaultError> {
+        caller.require_auth();
+
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+
+        // Only owner can pause assets
+        if caller != config.owner {
+            return Err(VaultError::Unauthorized);
+        }
+
+        if !config.assets.contains(&asset) {
+            return Err(VaultError::InvalidConfiguration);
+        }
+
+        let mut paused = Self::get_paused_assets(env.clone());
+        if !paused.contains(&asset) {
+            paused.push_back(asset.clone());
+            env.storage().instance().set(&PAUSED, &paused);
+        }
+
+        env.events().publish((symbol_short!("paused"),), asset);
+
+        Ok(())
+    }
+
+    /// Return a paused asset to rebalancing (owner only)
+    pub fn unpause_asset(env: Env, caller: Address, asset: Address) -> Result<(), VaultError> {
+        caller.require_auth();
+
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+
+        // Only owner can unpause assets
+        if caller != config.owner {
+            return Err(VaultError::Unauthorized);
+        }
+
+        let mut paused = Self::get_paused_assets(env.clone());
+        if let Some(index) = paused.first_index_of(&asset) {
+            paused.remove(index);
+            env.storage().instance().set(&PAUSED, &paused);
+        }
+
+        env.events().publish((symbol_short!("unpaused"),), asset);
+
+        Ok(())
+    }
+
+    /// Assets currently excluded from rebalancing
+    pub fn get_paused_assets(env: Env) -> soroban_sdk::Vec<Address> {
+        env.storage().instance().get(&PAUSED)
+            .unwrap_or(soroban_sdk::Vec::new(&env))
+    }
+
+    /// Whether lock_config has been called
+    pub fn is_config_locked(env: Env) -> bool {
+        env.storage().instance().has(&CFG_LOCK)
+    }
+
     /// Set the staking pool address for liquid staking (e.g., stXLM)
     pub fn set_staking_pool(env: Env, caller: Address, staking_pool: Address) -> Result<(), VaultError> {
         caller.require_auth();
@@ -767,6 +2289,7 @@ impl VaultContract {
         if caller != config.owner {
             return Err(VaultError::Unauthorized);
         }
+        Self::require_config_unlocked(&env)?;
         
         config.staking_pool_address = Some(staking_pool);
         
@@ -776,6 +2299,27 @@ impl VaultContract {
         Ok(())
     }
 
+    /// Set how many ledgers router/pool approvals stay valid (owner only)
+    pub fn set_allowance_ttl(env: Env, caller: Address, ledgers: u32) -> Result<(), VaultError> {
+        caller.require_auth();
+        
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+        
+        // Only owner can change approval lifetime
+        if caller != config.owner {
+            return Err(VaultError::Unauthorized);
+        }
+        
+        if ledgers == 0 {
+            return Err(VaultError::InvalidConfiguration);
+        }
+        
+        env.storage().instance().set(&crate::token_client::ALLOWANCE_TTL, &ledgers);
+        
+        Ok(())
+    }
+
     /// Set the factory address for finding liquidity pools
     pub fn set_factory(env: Env, caller: Address, factory: Address) -> Result<(), VaultError> {
         caller.require_auth();
@@ -787,6 +2331,7 @@ impl VaultContract {
         if caller != config.owner {
             return Err(VaultError::Unauthorized);
         }
+        Self::require_config_unlocked(&env)?;
         
         config.factory_address = Some(factory);
         
@@ -796,6 +2341,76 @@ impl VaultContract {
         Ok(())
     }
 
+    /// Set the liquidity pool used for add/remove liquidity operations
+    pub fn set_liquidity_pool(env: Env, caller: Address, pool: Address) -> Result<(), VaultError> {
+        caller.require_auth();
+        
+        let mut config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+        
+        // Only owner can update liquidity pool
+        if caller != config.owner {
+            return Err(VaultError::Unauthorized);
+        }
+        Self::require_config_unlocked(&env)?;
+        
+        config.liquidity_pool_address = Some(pool);
+        
+        // Store updated config
+        env.storage().instance().set(&CONFIG, &config);
+        
+        Ok(())
+    }
+
+    /// Repair a vault whose staked funds went untracked under the old mismatched staking key
+    /// Writes the position under the key stake_tokens and liquidation both use (owner only);
+    /// refuses to overwrite a position that already exists
+    pub fn recover_staking_position(
+        env: Env,
+        caller: Address,
+        pool: Address,
+        staked_amount: i128,
+        st_token_amount: i128,
+    ) -> Result<(), VaultError> {
+        caller.require_auth();
+
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+
+        // Only owner can rewrite staking accounting
+        if caller != config.owner {
+            return Err(VaultError::Unauthorized);
+        }
+
+        if staked_amount <= 0 || st_token_amount <= 0 {
+            return Err(VaultError::InvalidAmount);
+        }
+
+        let position_key = soroban_sdk::String::from_str(&env, "stake_position");
+        if env.storage().instance().has(&position_key) {
+            log!(&env, "Staking position already tracked - nothing to recover");
+            return Err(VaultError::InvalidConfiguration);
+        }
+
+        // stake_tokens always stakes the first configured asset
+        let original_token = config.assets.get(0)
+            .ok_or(VaultError::InvalidConfiguration)?;
+
+        let position = crate::types::StakingPosition {
+            current_value: crate::staking_client::current_staking_value(&env, &pool, st_token_amount),
+            staking_pool: pool,
+            original_token,
+            staked_amount,
+            st_token_amount,
+            timestamp: env.ledger().timestamp(),
+        };
+        env.storage().instance().set(&position_key, &position);
+
+        log!(&env, "Recovered staking position: staked={}, st_tokens={}", staked_amount, st_token_amount);
+
+        Ok(())
+    }
+
     /// Register a custom token liquidity pool
     /// This allows the vault to trade custom tokens through their dedicated pools
     pub fn register_custom_pool(
@@ -910,7 +2525,7 @@ impl VaultContract {
         // The validation was preventing actual swaps from happening
         // Now we trust the caller to trigger rebalancing at appropriate times
 
-        let _config: VaultConfig = env.storage().instance().get(&CONFIG)
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
             .ok_or(VaultError::NotInitialized)?;
         
         let mut state: VaultState = env.storage().instance().get(&STATE)
@@ -922,6 +2537,7 @@ impl VaultContract {
         // Update last rebalance timestamp
         state.last_rebalance = env.ledger().timestamp();
         env.storage().instance().set(&STATE, &state);
+        Self::record_rebalance(&env, &config)?;
 
         // Emit rebalance event
         crate::events::emit_rebalance(&env, state.last_rebalance);
@@ -1012,7 +2628,8 @@ impl VaultContract {
 
     /// Force rebalance to target allocation (for post-deposit swaps)
     /// Always executes rebalance regardless of rules
-    pub fn force_rebalance(env: Env) -> Result<(), VaultError> {
+    /// Keepers should call again while the outcome reports hit_budget_cap
+    pub fn force_rebalance(env: Env) -> Result<RebalanceOutcome, VaultError> {
         use soroban_sdk::{Vec as SdkVec, String};
         
         // Check vault is initialized
@@ -1026,6 +2643,21 @@ impl VaultContract {
         let mut state: VaultState = env.storage().instance().get(&STATE)
             .ok_or(VaultError::NotInitialized)?;
 
+        // Single-asset vaults are trivially at target
+        let mut outcome = RebalanceOutcome {
+            swaps_executed: 0,
+            hit_budget_cap: false,
+            converged: true,
+        };
+
+        // Empty or dust-only vaults have nothing worth swapping; let polling keepers
+        // see a clean no-op instead of an InsufficientBalance error
+        let nav = Self::calculate_nav(&env, &config)?;
+        if nav == 0 || nav < config.min_rebalance_nav {
+            log!(&env, "Skipping rebalance, nav below minimum: {}", nav);
+            return Ok(outcome);
+        }
+
         // For multi-asset vaults, force rebalance to target allocation
         if config.assets.len() > 1 {
             // Get target allocation from existing rebalance rules
@@ -1058,17 +2690,10 @@ impl VaultContract {
             }
             
             // Calculate actual total value from real balances
-            let mut actual_total_value: i128 = 0;
-            for i in 0..config.assets.len() {
-                if let Some(asset) = config.assets.get(i) {
-                    let balance = crate::token_client::get_vault_balance(&env, &asset);
-                    actual_total_value = actual_total_value.checked_add(balance)
-                        .ok_or(VaultError::InvalidAmount)?;
-                }
-            }
+            let actual_total_value = Self::tracked_balance_total(&env, &config)?;
             
             // Execute the rebalance action directly
-            crate::rebalance::force_rebalance_to_allocation(
+            outcome = crate::rebalance::force_rebalance_to_allocation(
                 &env,
                 &config.assets,
                 &target_allocation,
@@ -1079,11 +2704,135 @@ impl VaultContract {
         // Update last rebalance timestamp
         state.last_rebalance = env.ledger().timestamp();
         env.storage().instance().set(&STATE, &state);
+        Self::record_rebalance(&env, &config)?;
 
         // Emit rebalance event
         crate::events::emit_rebalance(&env, state.last_rebalance);
 
-        Ok(())
+        Ok(outcome)
+    }
+
+    /// Force rebalance to an explicit caller-supplied allocation (owner only)
+    /// Bypasses the configured rules for one-off target allocations; targets are shares of
+    /// the vault's raw balances, like force_rebalance
+    pub fn force_rebalance_to(
+        env: Env,
+        caller: Address,
+        target_allocation: soroban_sdk::Vec<i128>,
+    ) -> Result<RebalanceOutcome, VaultError> {
+        caller.require_auth();
+
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+
+        // Only owner can push the vault to an arbitrary allocation
+        if caller != config.owner {
+            return Err(VaultError::Unauthorized);
+        }
+
+        crate::rebalance::validate_allocation(&target_allocation, config.assets.len())?;
+
+        let mut state: VaultState = env.storage().instance().get(&STATE)
+            .ok_or(VaultError::NotInitialized)?;
+
+        let total_value = Self::tracked_balance_total(&env, &config)?;
+
+        let outcome = crate::rebalance::force_rebalance_to_allocation(
+            &env,
+            &config.assets,
+            &target_allocation,
+            total_value,
+        )?;
+
+        // Update last rebalance timestamp
+        state.last_rebalance = env.ledger().timestamp();
+        env.storage().instance().set(&STATE, &state);
+        Self::record_rebalance(&env, &config)?;
+
+        crate::events::emit_rebalance(&env, state.last_rebalance);
+
+        Ok(outcome)
+    }
+
+    /// Preview the vault's balances after rebalancing to target_allocation, without swapping
+    /// Each planned step is quoted against live pool reserves at the pool's fee tier; steps
+    /// whose pool can't be queried are assumed to fill at their planned minimum output
+    pub fn simulate_rebalance(
+        env: Env,
+        target_allocation: soroban_sdk::Vec<i128>,
+    ) -> Result<soroban_sdk::Vec<crate::types::AssetBalance>, VaultError> {
+        use soroban_sdk::Map;
+
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+
+        let total_value = Self::tracked_balance_total(&env, &config)?;
+        let plan = crate::rebalance::calculate_rebalance_plan(
+            &env,
+            &config.assets,
+            &target_allocation,
+            total_value,
+            None,
+        )?;
+
+        // Start from live balances and apply each step's projected fill
+        let mut balances: Map<Address, i128> = Map::new(&env);
+        for asset in config.assets.iter() {
+            let balance = crate::token_client::get_vault_balance(&env, &asset);
+            balances.set(asset, balance);
+        }
+
+        for step in plan.steps.iter() {
+            let amount_out = Self::quote_swap(&env, &config, &step.from_token, step.amount_in, &step.to_token)
+                .unwrap_or(step.min_amount_out);
+
+            let from_balance = balances.get(step.from_token.clone()).unwrap_or(0);
+            let to_balance = balances.get(step.to_token.clone()).unwrap_or(0);
+            balances.set(step.from_token.clone(), from_balance - step.amount_in);
+            balances.set(step.to_token.clone(), to_balance + amount_out);
+        }
+
+        let mut projected: soroban_sdk::Vec<crate::types::AssetBalance> = soroban_sdk::Vec::new(&env);
+        for asset in config.assets.iter() {
+            let amount = balances.get(asset.clone()).unwrap_or(0);
+            projected.push_back(crate::types::AssetBalance { token: asset, amount });
+        }
+
+        Ok(projected)
+    }
+
+    /// Expected slippage cost of rebalancing to target_allocation, in base-token terms
+    /// Sums, over each planned step, the gap between the input's spot value and the quoted
+    /// output (fee plus price impact); steps without a pool contribute nothing
+    pub fn preview_rebalance_cost(
+        env: Env,
+        target_allocation: soroban_sdk::Vec<i128>,
+    ) -> Result<i128, VaultError> {
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+        let base_token = Self::get_base_token(env.clone())?;
+
+        let total_value = Self::tracked_balance_total(&env, &config)?;
+        let plan = crate::rebalance::calculate_rebalance_plan(
+            &env,
+            &config.assets,
+            &target_allocation,
+            total_value,
+            None,
+        )?;
+
+        let mut total_cost: i128 = 0;
+        for step in plan.steps.iter() {
+            let spot_out = Self::value_in_token(&env, &config, &step.from_token, step.amount_in, &step.to_token);
+            let expected_out = Self::swap_output_in(&env, &config, &step.from_token, step.amount_in, &step.to_token);
+            let shortfall = spot_out.saturating_sub(expected_out).max(0);
+
+            let cost = Self::value_in_token(&env, &config, &step.to_token, shortfall, &base_token);
+            total_cost = total_cost.checked_add(cost)
+                .ok_or(VaultError::InvalidAmount)?;
+        }
+
+        Ok(total_cost)
     }
 
     /// Get a rebalance plan (list of swap steps) without executing
@@ -1190,9 +2939,18 @@ impl VaultContract {
         
         let position_key = String::from_str(&env, "stake_position");
         
-        env.storage().instance()
+        let mut position: crate::types::StakingPosition = env.storage().instance()
             .get(&position_key)
-            .ok_or(VaultError::NotInitialized)
+            .ok_or(VaultError::NotInitialized)?;
+        
+        // Value at the live rate so accrued staking yield shows up
+        position.current_value = crate::staking_client::current_staking_value(
+            &env,
+            &position.staking_pool,
+            position.st_token_amount,
+        );
+        
+        Ok(position)
     }
 
     /// Get the current liquidity position for the vault
@@ -1206,6 +2964,79 @@ impl VaultContract {
             .ok_or(VaultError::NotInitialized)
     }
 
+    /// Unwind percent (1-100) of the vault's liquidity position (owner only)
+    /// Minimums are the position's share of live reserves less slippage_bps; the pool
+    /// reverts rather than underdeliver
+    pub fn remove_liquidity_position(
+        env: Env,
+        caller: Address,
+        percent: i128,
+        slippage_bps: i128,
+    ) -> Result<(i128, i128), VaultError> {
+        use soroban_sdk::String;
+
+        caller.require_auth();
+
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+
+        // Only owner can unwind liquidity
+        if caller != config.owner {
+            return Err(VaultError::Unauthorized);
+        }
+
+        if percent <= 0 || percent > 100 {
+            return Err(VaultError::InvalidAmount);
+        }
+        if !(0..=10_000).contains(&slippage_bps) {
+            return Err(VaultError::InvalidConfiguration);
+        }
+
+        let position_key = String::from_str(&env, "lp_position");
+        let mut position: crate::types::LiquidityPosition = env.storage().instance()
+            .get(&position_key)
+            .ok_or(VaultError::NotInitialized)?;
+
+        let lp_to_remove = position.lp_tokens.checked_mul(percent)
+            .and_then(|v| v.checked_div(100))
+            .ok_or(VaultError::InvalidAmount)?;
+
+        let (amount_a, amount_b) = crate::liquidity_router::remove_liquidity_from_pool(
+            &env,
+            &position.pool_address,
+            &position.token_a,
+            &position.token_b,
+            lp_to_remove,
+            slippage_bps,
+        )?;
+
+        position.lp_tokens -= lp_to_remove;
+        if position.lp_tokens == 0 {
+            env.storage().instance().remove(&position_key);
+        } else {
+            env.storage().instance().set(&position_key, &position);
+        }
+
+        log!(&env, "Removed {} LP tokens for ({}, {})", lp_to_remove, amount_a, amount_b);
+        crate::events::emit_vault_event(
+            &env,
+            String::from_str(&env, "liquidity_removed"),
+            lp_to_remove,
+        );
+
+        Ok((amount_a, amount_b))
+    }
+
+    /// LP tokens the configured liquidity pool would mint for (amount_a, amount_b)
+    /// Desired amounts are trimmed to the pool's reserve ratio first, as add_liquidity does
+    pub fn preview_add_liquidity(env: Env, amount_a: i128, amount_b: i128) -> Result<i128, VaultError> {
+        let config: VaultConfig = env.storage().instance().get(&CONFIG)
+            .ok_or(VaultError::NotInitialized)?;
+        let pool = config.liquidity_pool_address.ok_or(VaultError::PoolNotFound)?;
+
+        crate::liquidity_router::preview_add_liquidity(&env, &pool, amount_a, amount_b)
+    }
+
     /// Check if vault has an active staking position
     pub fn has_staking_position(env: Env) -> bool {
         use soroban_sdk::String;